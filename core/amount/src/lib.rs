@@ -0,0 +1,115 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt;
+
+/*
+@name Amount
+@desc a balance, or an amount moved between accounts, in whole units. The
+      state keeps it as a string of digits
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount {
+    pub value: u64
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/*
+@name StringToAmount
+@desc only digits are an amount, so a sign, a fraction, or a number past
+      u64 is refused. What is sent must also be above 0
+*/
+pub trait StringToAmount {
+    fn string_to_amount(amount_string: String) -> Result<Amount, String>;
+    fn positive_amount(amount_string: String) -> Result<Amount, String>;
+}
+
+impl StringToAmount for Amount {
+    fn string_to_amount(amount_string: String) -> Result<Amount, String> {
+        if amount_string.is_empty() || !amount_string.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Amount, {} is not a whole number", amount_string))
+        }
+        match amount_string.parse::<u64>() {
+            Ok(value) => Ok(Amount { value: value }),
+            Err(_) => Err(format!("Amount, {} is too large", amount_string))
+        }
+    }
+
+    fn positive_amount(amount_string: String) -> Result<Amount, String> {
+        let amount: Amount = Self::string_to_amount(amount_string)?;
+        if amount.value == 0 {
+            return Err(String::from("Amount, an amount sent must be above 0"))
+        }
+        Ok(amount)
+    }
+}
+
+/*
+@name CheckedAmount
+@desc adding and subtracting amounts fails, instead of wrapping around
+*/
+pub trait CheckedAmount {
+    fn add_amount(&self, other: Amount) -> Result<Amount, String>;
+    fn subtract_amount(&self, other: Amount) -> Result<Amount, String>;
+}
+
+impl CheckedAmount for Amount {
+    fn add_amount(&self, other: Amount) -> Result<Amount, String> {
+        match self.value.checked_add(other.value) {
+            Some(value) => Ok(Amount { value: value }),
+            None => Err(format!("Amount, {} plus {} overflows", self, other))
+        }
+    }
+
+    fn subtract_amount(&self, other: Amount) -> Result<Amount, String> {
+        match self.value.checked_sub(other.value) {
+            Some(value) => Ok(Amount { value: value }),
+            None => Err(format!("Amount, {} is less than {}", self, other))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Amount, StringToAmount, CheckedAmount};
+
+    #[test]
+    fn test_string_to_amount() {
+        assert_eq!(Amount::string_to_amount(String::from("100")), Ok(Amount { value: 100 }));
+        assert_eq!(Amount::string_to_amount(String::from("18446744073709551615")), Ok(Amount { value: u64::max_value() }));
+        assert!(Amount::string_to_amount(String::from("18446744073709551616")).is_err());
+        assert!(Amount::string_to_amount(String::from("-5")).is_err());
+        assert!(Amount::string_to_amount(String::from("+5")).is_err());
+        assert!(Amount::string_to_amount(String::from("1.5")).is_err());
+        assert!(Amount::string_to_amount(String::from("null")).is_err());
+        assert_eq!(Amount::string_to_amount(String::from("0")), Ok(Amount { value: 0 }));
+        assert!(Amount::positive_amount(String::from("0")).is_err());
+        assert_eq!(format!("{}", Amount::positive_amount(String::from("7")).unwrap()), "7");
+    }
+
+    #[test]
+    fn test_checked_amount() {
+        let max: Amount = Amount { value: u64::max_value() };
+        assert_eq!(Amount { value: 90 }.add_amount(Amount { value: 10 }), Ok(Amount { value: 100 }));
+        assert!(max.add_amount(Amount { value: 1 }).is_err());
+        assert_eq!(Amount { value: 100 }.subtract_amount(Amount { value: 100 }), Ok(Amount { value: 0 }));
+        assert!(Amount { value: 10 }.subtract_amount(Amount { value: 11 }).is_err());
+    }
+}
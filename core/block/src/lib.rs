@@ -19,31 +19,74 @@ use json::{JsonValue};
 use std::io::{Error, ErrorKind};
 use timestamp::{Timestamp, NewTimestamp, StringToTimestamp};
 use transaction::{Transaction,
-                  ReadTransactionFromDB,
+                  State,
+                  StateRoot,
                   ExecuteTransactions,
                   JsonConverter,
-                  ClearTransactionIndex};
+                  HashTransaction,
+                  RemoveTransactions};
 
 use db::{DB,
          DBWriteBlock,
          DBReadBlock,
+         DBBlockTree,
+         DBStateManager,
+         DBGenesis,
          FileDirectoryReader,
          LogDebug};
 
 use hash::{Hasher, CalculateSHA256Hash};
 
 
+/*
+@desc GENESIS_PARENT_HASH is the parent hash of every block 0
+*/
+pub const GENESIS_PARENT_HASH: &str = "00000000000000000";
+
+/*
+@desc block limits for a genesis that does not set them. A proposal
+      travels in a single request header, so its block must stay small
+*/
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 16384;
+pub const DEFAULT_MAX_BLOCK_TRANSACTIONS: usize = 50;
+
 #[derive(Clone,Debug,PartialEq)]
 pub struct Block {
   pub block_id: i64,
   pub block_hash: String,
   pub block_parent_hash: String,
   pub block_time: Timestamp,
+  pub block_state_root: String,
   pub proposal_hash: String,
   pub block_data: String,
   pub transactions: Vec<Transaction>
 }
 
+/*
+@name BlockLimits
+@desc the most transactions, and serialized transaction bytes, a block may carry
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct BlockLimits {
+    pub max_block_bytes: usize,
+    pub max_block_transactions: usize
+}
+
+/*
+@name BlockHeader
+@desc everything the block hash covers, with the transactions
+      reduced to their root. Enough to follow the chain without its bodies
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct BlockHeader {
+  pub header_block_id: i64,
+  pub header_block_hash: String,
+  pub header_parent_hash: String,
+  pub header_time: Timestamp,
+  pub header_transactions_root: String,
+  pub header_state_root: String
+}
+
 
 /*
 @name CreateBlockIndex
@@ -71,6 +114,18 @@ impl CreateBlockIndex for Block {
                 println!("Failure writing block index");
             }
         }
+        let new_block_tree = object!{
+            "blocks" => object!{},
+            "tip" => ""
+        };
+        match DB::write_block_tree( json::stringify(new_block_tree) ) {
+            Ok(_) => {
+                println!("Successfully wrote block tree");
+            },
+            Err(_) => {
+                println!("Failure writing block tree");
+            }
+        }
     }
 }
 
@@ -337,21 +392,37 @@ impl WriteBlockToDB for Block {
 /*
     @name HashBlock
 */
-trait HashBlock {
-    fn hash_block(block_id: i64, ts: Timestamp) -> String;
+pub trait HashBlock {
+    fn hash_block(block_id: i64, block_parent_hash: String, ts: Timestamp, transactions: Vec<Transaction>, state_root: String) -> String;
+    fn hash_header(block_id: i64, block_parent_hash: String, ts: Timestamp, transactions_root: String, state_root: String) -> String;
+    fn transactions_root(transactions: Vec<Transaction>) -> String;
 }
 
 impl HashBlock for Block {
-    fn hash_block(block_id: i64, ts: Timestamp) -> String {
-
-        //TODO URGENT: include other properties when hashing block
+    /*
+    @desc the hash covers the parent hash, the ordered transaction hashes and
+          the state after execution, so two blocks at the same height on different
+          branches never collide, and a header commits to its state
+    */
+    fn hash_block(block_id: i64, block_parent_hash: String, ts: Timestamp, transactions: Vec<Transaction>, state_root: String) -> String {
+        Self::hash_header(block_id, block_parent_hash, ts, Self::transactions_root(transactions), state_root)
+    }
 
-        let raw_str: String = format!("{}{}", block_id, ts.timestamp);
+    fn hash_header(block_id: i64, block_parent_hash: String, ts: Timestamp, transactions_root: String, state_root: String) -> String {
+        let raw_str: String = format!("{}{}{}{}{}", block_id, block_parent_hash, ts.timestamp, transactions_root, state_root);
         let str_to_hash: &str = raw_str.as_str();
         let string_to_hash: String = String::from( str_to_hash ) ;
         let submitted_proposal_hash: String = Hasher::calculate_sha256( string_to_hash );
         submitted_proposal_hash
     }
+
+    fn transactions_root(transactions: Vec<Transaction>) -> String {
+        let transaction_hashes: String = transactions.iter()
+                                                     .map(|tx| tx.transaction_hash.clone())
+                                                     .collect::<Vec<String>>()
+                                                     .concat();
+        Hasher::calculate_sha256( transaction_hashes )
+    }
 }
 
 /*
@@ -359,16 +430,22 @@ impl HashBlock for Block {
     @desc create a new block
 */
 pub trait CreateNewBlock {
-    fn new(proposal_hash: String) -> Result<Block, String>;
+    fn new(proposal_hash: String, candidate_transactions: Vec<Transaction>) -> Result<Block, String>;
 }
 
 impl CreateNewBlock for Block {
-    fn new(proposal_hash: String) -> Result<Block, String> {
+    /*
+    @name new
+    @desc the next block on our tip, carrying as many of the candidate
+          transactions, in their order, as the block limits allow
+    */
+    fn new(proposal_hash: String, candidate_transactions: Vec<Transaction>) -> Result<Block, String> {
         let new_block_time: Option<Timestamp> = Timestamp::new();
         let latest_block_id: i64 = DB::get_latest_block_id().unwrap();
         let new_block_id: i64 = latest_block_id + 1;
         let parent_hash: String = match latest_block_id.clone() {
-            -1 => String::from("00000000000000000"),
+            //block 0 is never proposed, every node builds it from the genesis file
+            -1 => return Err( String::from("Block error: no genesis block, start the node with a genesis file") ),
             _ => {
                 let current_block_by_id: Option<Block> = DB::get_block_by_block_id(latest_block_id);
                 if current_block_by_id.is_some() {
@@ -378,21 +455,23 @@ impl CreateNewBlock for Block {
                 }
             }
         };
-        //TODO: Retrieve Transactions from TX pool
-        let transactons_from_pool: Vec<Transaction> = DB::get_all_transactions();
+        //transactions stay in the pool until the block carrying them is committed
+        let transactons_from_pool: Vec<Transaction> = Self::select_transactions(candidate_transactions, Self::block_limits());
         println!("CreateNewBlock, transactons_from_pool, tx count: {}", transactons_from_pool.len());
+        let parent_state: JsonValue = match Self::state_after(parent_hash.clone()) {
+            Some(state) => state,
+            None => return Err( format!("Block error: no state for parent block {}", parent_hash) )
+        };
         match new_block_time {
             Some(ts) => {
-
-                //TODO: node just created a new block, now we can clear TX
-                Transaction::clear_transaction_index();
-
+                let state_root: String = State::state_root( Transaction::apply_block_transactions(parent_state, new_block_id, ts.clone(), transactons_from_pool.clone()) );
                 Ok(Block {
                     block_id: new_block_id,
                     // TODO: Transaction::json_from_tx_vec()
-                    block_hash: Self::hash_block(new_block_id, ts.clone()),
+                    block_hash: Self::hash_block(new_block_id, parent_hash.clone(), ts.clone(), transactons_from_pool.clone(), state_root.clone()),
                     block_parent_hash: parent_hash,
                     block_time: ts,
+                    block_state_root: state_root,
                     proposal_hash: proposal_hash,
                     block_data: String::from("TEST DATA"),
                     transactions: transactons_from_pool
@@ -405,6 +484,68 @@ impl CreateNewBlock for Block {
     }
 }
 
+/*
+@name SelectTransactions
+@desc pick the transactions of a new block out of the pool
+*/
+pub trait SelectTransactions {
+    fn block_limits() -> BlockLimits;
+    fn transactions_size(transactions: Vec<Transaction>) -> usize;
+    fn within_limits(transactions: Vec<Transaction>, limits: BlockLimits) -> bool;
+    fn select_transactions(pool: Vec<Transaction>, limits: BlockLimits) -> Vec<Transaction>;
+}
+
+impl SelectTransactions for Block {
+    /*
+    @name block_limits
+    @desc the limits set in the genesis consensus parameters
+    */
+    fn block_limits() -> BlockLimits {
+        let consensus: JsonValue = match DB::read_genesis() {
+            Some(genesis_record) => match json::parse(&genesis_record) {
+                Ok(genesis_record) => genesis_record["genesis"]["consensus"].clone(),
+                Err(_) => JsonValue::Null
+            },
+            None => JsonValue::Null
+        };
+        BlockLimits {
+            max_block_bytes: consensus["max_block_bytes"].as_usize().unwrap_or(DEFAULT_MAX_BLOCK_BYTES),
+            max_block_transactions: consensus["max_block_transactions"].as_usize().unwrap_or(DEFAULT_MAX_BLOCK_TRANSACTIONS)
+        }
+    }
+
+    fn transactions_size(transactions: Vec<Transaction>) -> usize {
+        transactions.into_iter().map(|tx| Transaction::to_json(tx).len()).sum()
+    }
+
+    fn within_limits(transactions: Vec<Transaction>, limits: BlockLimits) -> bool {
+        transactions.len() <= limits.max_block_transactions
+            && Self::transactions_size(transactions) <= limits.max_block_bytes
+    }
+
+    /*
+    @name select_transactions
+    @desc take transactions in pool order, skipping any that would push
+          the block over its byte limit
+    */
+    fn select_transactions(pool: Vec<Transaction>, limits: BlockLimits) -> Vec<Transaction> {
+        let mut selected: Vec<Transaction> = Vec::new();
+        let mut selected_bytes: usize = 0;
+        for tx in pool {
+            if selected.len() >= limits.max_block_transactions {
+                break
+            }
+            let tx_bytes: usize = Self::transactions_size(vec![tx.clone()]);
+            if selected_bytes + tx_bytes > limits.max_block_bytes {
+                continue
+            }
+            selected_bytes = selected_bytes + tx_bytes;
+            selected.push(tx);
+        }
+        selected
+    }
+}
+
 /*
 @name BlockToJson
 @desc convert a block to json
@@ -420,6 +561,7 @@ impl BlockToJson for Block {
             "block_hash" => block.block_hash,
             "block_parent_hash" => block.block_parent_hash,
             "block_time" => block.block_time.timestamp,
+            "block_state_root" => block.block_state_root,
             "proposal_hash" => block.proposal_hash,
             "block_data" => block.block_data,
             "transactions" => Transaction::json_from_tx_vec(block.transactions).unwrap()
@@ -458,6 +600,7 @@ impl BlockFromString for Block {
                               block_hash: String::from(parsed["block_hash"].as_str().unwrap()),
                               block_parent_hash: String::from( parsed["block_parent_hash"].as_str().unwrap() ),
                               block_time: ts,
+                              block_state_root: String::from( parsed["block_state_root"].as_str().unwrap_or("") ),
                               proposal_hash: String::from( parsed["proposal_hash"].as_str().unwrap() ),
                               block_data: String::from( parsed["block_data"].as_str().unwrap() ),
                               transactions: tx_vec.unwrap()
@@ -500,6 +643,7 @@ impl JsonToBlock for Block {
                     block_hash: String::from(payload["block_hash"].as_str().unwrap()),
                     block_parent_hash: String::from(payload["block_parent_hash"].as_str().unwrap()),
                     block_time: bt,
+                    block_state_root: String::from(payload["block_state_root"].as_str().unwrap_or("")),
                     proposal_hash: String::from(payload["proposal_hash"].as_str().unwrap()),
                     block_data: String::from(payload["block_data"].as_str().unwrap()),
                     transactions: Transaction::tx_vec_from_json( payload["transactions"].clone() ).unwrap()
@@ -545,181 +689,444 @@ pub trait ValidateAcceptedProposalBlock {
 }
 
 impl ValidateAcceptedProposalBlock for Block {
+    /*
+    @desc a block is valid if its hash and the hashes of its transactions
+          match their contents, it stays within the block limits, it extends a block we already hold
+          in the block tree, on any branch,
+          and executing it on its parent's state gives its state root.
+          Block 0 is only valid if it is the one we built from our genesis
+    */
     fn validate_block(block: Block) -> bool {
-        let current_block_id: Option<i64> = DB::get_latest_block_id();
-        match current_block_id {
-            Some(block_id) => {
-                println!("validate_block, after current_block_id, block_id: {}", block_id);
-                //TODO: CHECK IF GENESIS Block
-                if block_id == 0 {
-                    Self::process_genesis_block(block)
-                } else if block_id > 0 {
-                    Self::process_nongenesis_block(block)
-                } else {
-                    false
-                }
+        let calculated_block_hash: String = Self::hash_block(block.block_id,
+                                                             block.block_parent_hash.clone(),
+                                                             block.block_time.clone(),
+                                                             block.transactions.clone(),
+                                                             block.block_state_root.clone());
+        if calculated_block_hash != block.block_hash {
+            println!("validate_block, ERROR: block hash does not match block contents, block_id: {}", block.block_id);
+            return false
+        }
+        if block.transactions.iter().any(|tx| !Transaction::verify_hash(tx.clone())) {
+            println!("validate_block, ERROR: a transaction does not match its hash, block_id: {}", block.block_id);
+            return false
+        }
+        if !Self::within_limits(block.transactions.clone(), Self::block_limits()) {
+            println!("validate_block, ERROR: block is over the block limits, block_id: {}", block.block_id);
+            return false
+        }
+        if block.block_parent_hash == GENESIS_PARENT_HASH {
+            println!("validate_block, processing genesis block");
+            return block.block_id == 0
+                && Self::get_block_by_block_id(0).map(|genesis_block| genesis_block.block_hash) == Some(block.block_hash)
+        }
+        let extends_known_block: bool = match Self::get_block_by_hash(block.block_parent_hash.clone()) {
+            Some(parent_block) => {
+                //TODO: CALL CHAIN LOGIC BLOCKVALIDATION
+                Self::verify_block_anscestry(parent_block.clone(), block.clone())
+                    && block.block_id == parent_block.block_id + 1
+            },
+            None => {
+                println!("validate_block, ERROR: parent block is unknown, block_id: {}", block.block_id);
+                false
             }
+        };
+        if !extends_known_block {
+            return false
+        }
+        match Self::state_after(block.block_parent_hash.clone()) {
+            Some(parent_state) => {
+                let state_root: String = State::state_root( Transaction::apply_block_transactions(parent_state, block.block_id, block.block_time.clone(), block.transactions.clone()) );
+                if state_root != block.block_state_root {
+                    println!("validate_block, ERROR: state root does not match execution, block_id: {}", block.block_id);
+                }
+                state_root == block.block_state_root
+            },
             None => {
-
+                println!("validate_block, ERROR: no state for parent block, block_id: {}", block.block_id);
                 false
             }
         }
     }
 }
 
-
 /*
-    @name process block
+    @name BlockTree
+    @desc every block we receive is stored by hash in the block tree.
+          The canonical chain is the branch ending at the tip chosen by fork_choice
 */
+pub trait BlockTree {
+    fn get_block_tree_as_json() -> Option<JsonValue>;
+    fn get_block_by_hash(block_hash: String) -> Option<Block>;
+    fn get_tip_hash() -> Option<String>;
+    fn store_block_by_hash(block: Block) -> Result<(), String>;
+    fn fork_choice(tip_id: i64, tip_hash: &str, candidate_id: i64, candidate_hash: &str) -> bool;
+    fn is_canonical(block: Block) -> bool;
+}
 
-trait ProcessBlock {
-    fn process_genesis_block(submitted_block: Block) -> bool;
-    fn process_nongenesis_block(submitted_block: Block) -> bool;
-}
-
-impl ProcessBlock for Block {
-    fn process_genesis_block(submitted_block: Block) -> bool {
-        println!("PROCESSING GENESIS BLOCK, submitted_block_id: {}", submitted_block.block_id);
-        //TODO: COMPARE WITH BLOCKCHAIN CONFIG
-        //TODO: CALL CHAIN LOGIC BLOCKVALIDATION
-        Transaction::execute_block_transactions(submitted_block.transactions);
-        true
-    }
-
-    fn process_nongenesis_block(submitted_block: Block) -> bool {
-        println!("PROCESSING NONGENESIS BLOCK, submitted_block_id: {}", submitted_block.block_id);
-        //TODO: PROBLEM
-        //if we get the block with block id - 1, we just get the block "before" the submitted block
-        // might want to just get the latest block....?
-        //let current_block_id: Option<i64> = DB::get_latest_block_id();
-        //let current_block_by_id: Option<Block> = DB::get_block_by_block_id( current_block_id.unwrap() );
-        //TODO: validate the block getting added is the child of the most recent block
-        let previous_block_by_id: Option<Block> = DB::get_block_by_block_id(submitted_block.block_id - 1);
-        if previous_block_by_id.clone().is_some() {
-            //TODO: verify parent hash
-            match Self::verify_block_anscestry(previous_block_by_id.clone().unwrap(),
-                                               submitted_block.clone()) {
-                true => {
-
-
-                    println!("process_nongenesis_block, verify_block_anscestry, SUCCESS");
-
-                    //TODO: Self.verify_block_id(submitted_block, current_block_by_id);
-                    //TODO: REDUNDANT FOR SUBMITTED BLOCK ID?
-
-                    match submitted_block.clone().block_id
-                          ==
-                          (previous_block_by_id
-                           .clone()
-                           .unwrap().block_id + 1) {
-                        true => {
-
-                            println!("process_nongenesis_block, SUBMITTED_BLOCK ID IS EQUAL TO MY BLOCK ID + 1, SUCCESS");
-
-                            // TODO: get latest block
-                            let latest_block_id_option: Option<i64> = DB::get_latest_block_id();
-                            match latest_block_id_option {
-                                Some(latest_block_id) => {
-
-                                    //ONLY EXECUTE if submitted block is equal to latest block + 1
-                                    if ( submitted_block.clone().block_id == (latest_block_id + 1) ){
-                                        // THIS ONLY EXECUTES WHEN the submitted block is the correct valid anscestor
-                                        DB::write_block_debug( String::from( format!("Execute all txs in block: {}", submitted_block.clone().block_id) ) );
-                                        Transaction::execute_block_transactions(submitted_block.transactions);
-                                        return true
-
-                                    }else{
-                                        println!("process_nongenesis_block, latest_block_id + 1 is NOT equal to submitted_block.block_id");
-                                        return false
-                                    }
+impl BlockTree for Block {
+    fn get_block_tree_as_json() -> Option<JsonValue> {
+        match DB::read_block_tree() {
+            Some(block_tree) => json::parse( &format!(r#"{}"#, block_tree) ).ok(),
+            None => None
+        }
+    }
 
-                                },
-                                None => {
-                                    println!("process_nongenesis_block, latest_block_id option is NONE");
-                                    return false
-                                }
-                            }
+    fn get_block_by_hash(block_hash: String) -> Option<Block> {
+        match DB::read_block_by_hash(block_hash) {
+            Some(block_string) => Self::from_string(block_string).ok(),
+            None => None
+        }
+    }
 
+    /*
+    @name get_tip_hash
+    @desc the hash of the canonical head, None if we hold no block yet
+    */
+    fn get_tip_hash() -> Option<String> {
+        let block_tree: JsonValue = Self::get_block_tree_as_json()?;
+        match block_tree["tip"].as_str() {
+            Some("") | None => None,
+            Some(tip_hash) => Some(String::from(tip_hash))
+        }
+    }
 
+    fn store_block_by_hash(block: Block) -> Result<(), String> {
+        let mut block_tree: JsonValue = match Self::get_block_tree_as_json() {
+            Some(block_tree) => block_tree,
+            None => return Err(String::from("store_block_by_hash ERROR: block tree could not be read"))
+        };
+        match DB::write_block_by_hash(block.block_hash.clone(), Self::to_json(block.clone()).dump()) {
+            Ok(_) => {},
+            Err(_) => return Err(String::from("store_block_by_hash ERROR: could not write block"))
+        }
+        block_tree["blocks"][block.block_hash.as_str()] = object!{
+            "block_id" => block.block_id,
+            "block_parent_hash" => block.block_parent_hash
+        };
+        match DB::write_block_tree(block_tree.dump()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("store_block_by_hash ERROR: could not write block tree"))
+        }
+    }
 
-                        },
-                        false => {
-                            println!("process_nongenesis_block, SUBMITTED_BLOCK ID IS [NOT] EQUAL TO MY BLOCK ID + 1, ERROR");
-                            //TODO modularize out into a is_valid_current_block(submitted_block)
-                            let current_block_id_option: Option<i64> = DB::get_latest_block_id();  // Get my latest block
-                            match current_block_id_option {
-                                Some(current_block_id) => {
-                                    let current_block_by_id_option: Option<Block> = DB::get_block_by_block_id(current_block_id);
-                                    match current_block_by_id_option {
-                                        Some(current_block_by_id) => {
-
-                                              // if block ids are the same
-                                              // match ( submitted_block.clone().block_id == (current_block_by_id.clone().block_id) )
-                                              //       &&
-                                              //       // if the parent hashes are the same
-                                              //       ( submitted_block.clone().block_parent_hash == current_block_by_id.clone().block_parent_hash )
-                                              //       &&
-                                              //       // different block hashes
-                                              //       ( submitted_block.clone().block_hash != current_block_by_id.clone().block_hash ) {
-                                              //     true => {
-                                              //         println!("process_nongenesis_block, BLOCK ID MATCH MY TOP BLOCK, AND PARENT HASHES MATCH, and BLOCK HASHES ARE NOT THE SAME - SUCCESS");
-                                              //     },
-                                              //     false => {
-                                              //         println!("process_nongenesis_block ERROR!, BLOCK ID MATCH MY TOP, BLOCK AND PARENT HASHES DONT MATCH, and BLOCK HASHES ARE NOT THE SAME - ERROR");
-                                              //         return false
-                                              //     }
-                                              // }
-
-                                              /*
-                                              match current_block {
-                                                  _ if current_block.block_hash == proposed_block.block_parent_hash => {
-                                                      true
-                                                  },
-                                                  _ => {
-                                                      false
-                                                  }
-                                              }
-                                              */
-                                              //return false
-
-
-                                        },
-                                        None => {
-                                            println!("process_nongenesis_block, current_block_by_id_option is NONE");
-                                            return false
-                                        }
-                                    }
-                                },
-                                //current_block_id_option is NONE
-                                None => {
-                                    println!("process_nongenesis_block, current_block_id_option is NONE");
-                                    return false
-                                }
+    /*
+    @name fork_choice
+    @desc return true if the candidate should replace the current tip.
+          The longest chain wins, ties go to the lowest block hash,
+          so every node picks the same tip from the same tree
+    */
+    fn fork_choice(tip_id: i64, tip_hash: &str, candidate_id: i64, candidate_hash: &str) -> bool {
+        candidate_id > tip_id || (candidate_id == tip_id && candidate_hash < tip_hash)
+    }
 
-                            }
-                        }
-                    }
+    /*
+    @name is_canonical
+    @desc the canonical chain is the one indexed by block id
+    */
+    fn is_canonical(block: Block) -> bool {
+        match DB::get_block_by_block_id(block.block_id) {
+            Some(canonical_block) => canonical_block.block_hash == block.block_hash,
+            None => false
+        }
+    }
+}
 
-                },
-                false => {
-                    println!("process_nongenesis_block, verify_block_anscestry, ERROR");
-                    return false
+/*
+    @name StateSnapshot
+    @desc the state right after a block executed, kept per block hash so a
+          reorg can roll back to the common ancestor and replay from there
+*/
+pub trait StateSnapshot {
+    fn get_state_snapshot(block_hash: String) -> Option<JsonValue>;
+    fn write_state_snapshot(block_hash: String, state: JsonValue) -> Result<(), String>;
+    fn state_after(block_hash: String) -> Option<JsonValue>;
+}
+
+impl StateSnapshot for Block {
+    fn get_state_snapshot(block_hash: String) -> Option<JsonValue> {
+        match DB::read_state_snapshot(block_hash) {
+            Some(state) => json::parse( &format!(r#"{}"#, state) ).ok(),
+            None => None
+        }
+    }
+
+    fn write_state_snapshot(block_hash: String, state: JsonValue) -> Result<(), String> {
+        match DB::write_state_snapshot(block_hash, json::stringify(state)) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("write_state_snapshot ERROR: could not write state snapshot"))
+        }
+    }
+
+    /*
+    @name state_after
+    @desc the state right after the block with this hash executed. Side
+          branches have no snapshot, so replay from the closest block that has one
+    */
+    fn state_after(block_hash: String) -> Option<JsonValue> {
+        let mut branch: Vec<Block> = Vec::new();
+        let mut current_hash: String = block_hash;
+        let mut state: JsonValue = loop {
+            if current_hash == GENESIS_PARENT_HASH {
+                break object!{}
+            }
+            match Self::get_state_snapshot(current_hash.clone()) {
+                Some(snapshot) => break snapshot,
+                None => {}
+            }
+            let block: Block = Self::get_block_by_hash(current_hash.clone())?;
+            current_hash = block.block_parent_hash.clone();
+            branch.push(block);
+        };
+        for block in branch.into_iter().rev() {
+            state = Transaction::apply_block_transactions(state, block.block_id, block.block_time, block.transactions);
+        }
+        Some(state)
+    }
+}
+
+/*
+    @name Reorganize
+    @desc make a block the canonical tip
+*/
+trait Reorganize {
+    fn set_canonical_tip(new_tip: Block) -> Result<Vec<Transaction>, String>;
+    fn abandoned_blocks(common_ancestor: Option<Block>) -> Result<Vec<Block>, String>;
+    fn abandoned_transactions(abandoned: Vec<Block>, branch: Vec<Block>) -> Vec<Transaction>;
+}
+
+impl Reorganize for Block {
+    /*
+    @name set_canonical_tip
+    @desc walk back from the new tip to the first canonical block, restore
+          the state snapshot of that common ancestor and replay the branch
+          on top of it. Extending the current tip is a branch of one block.
+          Returns the transactions of the abandoned blocks that the new
+          branch does not include, for the caller to return to the pool
+    */
+    fn set_canonical_tip(new_tip: Block) -> Result<Vec<Transaction>, String> {
+        let mut branch: Vec<Block> = Vec::new();
+        let mut branch_block: Block = new_tip.clone();
+        let common_ancestor: Option<Block> = loop {
+            if Self::is_canonical(branch_block.clone()) {
+                break Some(branch_block)
+            }
+            branch.push(branch_block.clone());
+            if branch_block.block_parent_hash == GENESIS_PARENT_HASH {
+                break None
+            }
+            branch_block = match Self::get_block_by_hash(branch_block.block_parent_hash.clone()) {
+                Some(parent_block) => parent_block,
+                None => return Err(String::from("set_canonical_tip ERROR: branch has an unknown ancestor"))
+            };
+        };
+        let mut state: JsonValue = match common_ancestor.clone() {
+            Some(ancestor) => {
+                match Self::get_state_snapshot(ancestor.block_hash.clone()) {
+                    Some(snapshot) => snapshot,
+                    None => return Err(format!("set_canonical_tip ERROR: no state snapshot for ancestor {}", ancestor.block_hash))
                 }
+            },
+            None => object!{}
+        };
+        if branch.len() > 1 {
+            let reorg_message: String = format!("[BLOCK, CRITICAL] REORG to block {} at height {}, replaying {} blocks",
+                                                new_tip.block_hash,
+                                                new_tip.block_id,
+                                                branch.len());
+            println!("{}", reorg_message);
+            DB::write_block_debug(reorg_message);
+        }
+        let abandoned: Vec<Transaction> = Self::abandoned_transactions(Self::abandoned_blocks(common_ancestor)?, branch.clone());
+        for block in branch.into_iter().rev() {
+            DB::write_block_debug( String::from( format!("Execute all txs in block: {}", block.clone().block_id) ) );
+            state = Transaction::apply_block_transactions(state, block.block_id, block.block_time.clone(), block.transactions.clone());
+            Self::write_state_snapshot(block.block_hash.clone(), state.clone())?;
+            match Self::commit_block(block.clone()) {
+                Ok(_) => Transaction::remove_transactions(block.transactions.clone()),
+                Err(_) => return Err(format!("set_canonical_tip ERROR: could not commit block {}", block.block_id))
             }
+        }
+        match DB::write_state( json::stringify(state) ) {
+            Ok(_) => {},
+            Err(_) => return Err(String::from("set_canonical_tip ERROR: could not write state"))
+        }
+        let mut block_tree: JsonValue = match Self::get_block_tree_as_json() {
+            Some(block_tree) => block_tree,
+            None => return Err(String::from("set_canonical_tip ERROR: block tree could not be read"))
+        };
+        block_tree["tip"] = JsonValue::from(new_tip.block_hash);
+        match DB::write_block_tree(block_tree.dump()) {
+            Ok(_) => Ok(abandoned),
+            Err(_) => Err(String::from("set_canonical_tip ERROR: could not write block tree"))
+        }
+    }
 
+    /*
+    @name abandoned_blocks
+    @desc the canonical blocks above the common ancestor, from the current
+          tip down, read before the new branch replaces them
+    */
+    fn abandoned_blocks(common_ancestor: Option<Block>) -> Result<Vec<Block>, String> {
+        let ancestor_hash: String = match common_ancestor {
+            Some(ancestor) => ancestor.block_hash,
+            None => String::from(GENESIS_PARENT_HASH)
+        };
+        let mut abandoned: Vec<Block> = Vec::new();
+        let mut current_hash: String = match Self::get_tip_hash() {
+            Some(tip_hash) => tip_hash,
+            None => return Ok(abandoned)
+        };
+        while current_hash != ancestor_hash {
+            let block: Block = match Self::get_block_by_hash(current_hash.clone()) {
+                Some(block) => block,
+                None => return Err(String::from("set_canonical_tip ERROR: canonical chain has an unknown block"))
+            };
+            current_hash = block.block_parent_hash.clone();
+            abandoned.push(block);
+        }
+        Ok(abandoned)
+    }
 
+    /*
+    @name abandoned_transactions
+    @desc transactions of the abandoned blocks that are not in the new branch
+    */
+    fn abandoned_transactions(abandoned: Vec<Block>, branch: Vec<Block>) -> Vec<Transaction> {
+        let branch_hashes: Vec<String> = branch.into_iter()
+                                               .flat_map(|block| block.transactions)
+                                               .map(|tx| tx.transaction_hash)
+                                               .collect();
+        abandoned.into_iter()
+                 .rev()
+                 .flat_map(|block| block.transactions)
+                 .filter(|tx| !branch_hashes.contains(&tx.transaction_hash))
+                 .collect()
+    }
+}
 
-            //TODO: MACRO: CUSTOM_BLOCK_VALIDATION!()
-            // AFTER CHECKING IF BLOCK ID IS RIGHT SEQUENCE
-            //TODO: verify block hash
-            //TODO: CALL CHAIN LOGIC BLOCKVALIDATION
-            //TODO: error handling
-            //TODO: BUG THIS EXECUTE TX SITS OUTSIDE OF
-            //Transaction::execute_block_transactions(submitted_block.transactions);
-            true
-        } else {
-            //current block by block id is NOT SOME
-            false
+/*
+    @name BlockHeaders
+    @desc
+*/
+pub trait BlockHeaders {
+    fn header(block: Block) -> BlockHeader;
+    fn verify_header_hash(header: BlockHeader) -> bool;
+    fn header_to_json(header: BlockHeader) -> JsonValue;
+    fn header_from_json(payload: JsonValue) -> Result<BlockHeader, String>;
+}
+
+impl BlockHeaders for Block {
+    fn header(block: Block) -> BlockHeader {
+        BlockHeader {
+            header_block_id: block.block_id,
+            header_block_hash: block.block_hash,
+            header_parent_hash: block.block_parent_hash,
+            header_time: block.block_time,
+            header_transactions_root: Self::transactions_root(block.transactions),
+            header_state_root: block.block_state_root
+        }
+    }
+
+    fn verify_header_hash(header: BlockHeader) -> bool {
+        Self::hash_header(header.header_block_id,
+                          header.header_parent_hash,
+                          header.header_time,
+                          header.header_transactions_root,
+                          header.header_state_root) == header.header_block_hash
+    }
+
+    fn header_to_json(header: BlockHeader) -> JsonValue {
+        object!{
+            "header_block_id" => header.header_block_id,
+            "header_block_hash" => header.header_block_hash,
+            "header_parent_hash" => header.header_parent_hash,
+            "header_time" => header.header_time.timestamp,
+            "header_transactions_root" => header.header_transactions_root,
+            "header_state_root" => header.header_state_root
+        }
+    }
+
+    fn header_from_json(payload: JsonValue) -> Result<BlockHeader, String> {
+        match (payload["header_block_id"].as_i64(),
+               payload["header_block_hash"].as_str(),
+               payload["header_parent_hash"].as_str(),
+               payload["header_time"].as_str(),
+               payload["header_transactions_root"].as_str(),
+               payload["header_state_root"].as_str()) {
+            (Some(block_id), Some(block_hash), Some(parent_hash), Some(time), Some(transactions_root), Some(state_root)) => {
+                match Timestamp::string_to_timestamp(String::from(time)) {
+                    Some(ts) => {
+                        Ok(BlockHeader {
+                            header_block_id: block_id,
+                            header_block_hash: String::from(block_hash),
+                            header_parent_hash: String::from(parent_hash),
+                            header_time: ts,
+                            header_transactions_root: String::from(transactions_root),
+                            header_state_root: String::from(state_root)
+                        })
+                    },
+                    None => Err(String::from("header_from_json ERROR: header time is not a timestamp"))
+                }
+            },
+            _ => Err(String::from("header_from_json ERROR: header is missing a field"))
+        }
+    }
+}
+
+/*
+    @name InstallCheckpoint
+    @desc start the chain from a trusted block and its state, instead of
+          replaying every block since genesis. Block 0 is installed the same way
+*/
+pub trait InstallCheckpoint {
+    fn install_checkpoint(block: Block, state: JsonValue) -> Result<(), String>;
+}
+
+impl InstallCheckpoint for Block {
+    /*
+    @name install_checkpoint
+    @desc the caller vouches for the block, through a verified header chain.
+          Here we only check that the block and the state match that header
+    */
+    fn install_checkpoint(block: Block, state: JsonValue) -> Result<(), String> {
+        let latest_block_id: i64 = Self::get_latest_block_id().unwrap_or(-1);
+        if latest_block_id > 0 || block.block_id <= latest_block_id {
+            return Err(String::from("install_checkpoint ERROR: the chain already extends past genesis"))
+        }
+        let calculated_block_hash: String = Self::hash_block(block.block_id,
+                                                             block.block_parent_hash.clone(),
+                                                             block.block_time.clone(),
+                                                             block.transactions.clone(),
+                                                             block.block_state_root.clone());
+        if calculated_block_hash != block.block_hash {
+            return Err(String::from("install_checkpoint ERROR: block hash does not match block contents"))
+        }
+        if State::state_root(state.clone()) != block.block_state_root {
+            return Err(String::from("install_checkpoint ERROR: state does not match the block state root"))
+        }
+        Self::store_block_by_hash(block.clone())?;
+        Self::write_state_snapshot(block.block_hash.clone(), state.clone())?;
+        match Self::commit_block(block.clone()) {
+            Ok(_) => {},
+            Err(_) => return Err(String::from("install_checkpoint ERROR: could not commit block"))
+        }
+        match DB::write_state( json::stringify(state) ) {
+            Ok(_) => {},
+            Err(_) => return Err(String::from("install_checkpoint ERROR: could not write state"))
+        }
+        let mut block_tree: JsonValue = match Self::get_block_tree_as_json() {
+            Some(block_tree) => block_tree,
+            None => return Err(String::from("install_checkpoint ERROR: block tree could not be read"))
+        };
+        block_tree["tip"] = JsonValue::from(block.block_hash.clone());
+        match DB::write_block_tree(block_tree.dump()) {
+            Ok(_) => {
+                let checkpoint_message: String = format!("[BLOCK, CRITICAL] installed checkpoint {} at height {}", block.block_hash, block.block_id);
+                println!("{}", checkpoint_message);
+                DB::write_block_debug(checkpoint_message);
+                Ok(())
+            },
+            Err(_) => Err(String::from("install_checkpoint ERROR: could not write block tree"))
         }
     }
 }
@@ -729,46 +1136,46 @@ impl ProcessBlock for Block {
     @desc Attempt to commit the block to the ledger, called after block validation
 */
 pub trait CommitBlock {
-    fn commit_if_valid(block: Block) -> Result<(),String>;
+    fn commit_if_valid(block: Block) -> Result<Vec<Transaction>,String>;
     fn commit_block(block: Block) -> Result<(), ()>;
 }
 
 impl CommitBlock for Block {
-    fn commit_if_valid(block: Block) -> Result<(),String> {
-        match Self::validate_block(block.clone()) {
-            true => {
-                match Self::commit_block(block.clone()) {
-                    Ok(_) => {
-                        println!("[BLOCK, CRITICAL] COMMIT BLOCK SUCCESSFUL");
-
-                        // TODO: only clear if proposals[block_parent_hash].
-                        // This clears all transactions waiting to get into chain
-                        // upon every new block commit, regardless of who commited it
-                        //Transaction::clear_transaction_index();
-
-                        Ok(())
-                    },
-                    Err(_) => {
-                        println!("[BLOCK, CRITICAL] COMMIT BLOCK NOT SUCCESSFUL!");
-                        Err(String::from("[BLOCK, CRITICAL] COMMIT BLOCK NOT SUCCESSFUL!"))
-                    }
-                }
-            },
-            false => {
-                if block.clone().block_id == 0 {
-                    match Self::commit_block(block.clone()) {
-                        Ok(_) => {
-                            println!("[BLOCK, CRITICAL] COMMIT BLOCK SUCCESSFUL");
-                            Ok(())
-                        },
-                        Err(_) => {
-                            println!("[BLOCK, CRITICAL] COMMIT BLOCK NOT SUCCESSFUL!");
-                            Err(String::from("[BLOCK, CRITICAL] COMMIT BLOCK NOT SUCCESSFUL!"))
-                        }
-                    }
+    /*
+    @name commit_if_valid
+    @desc store a valid block in the block tree, and move the canonical
+          tip to it if it wins the fork choice. Returns the transactions
+          a reorg abandoned, which belong back in the pool
+    */
+    fn commit_if_valid(block: Block) -> Result<Vec<Transaction>,String> {
+        if Self::get_block_by_hash(block.block_hash.clone()).is_some() {
+            println!("[BLOCK] commit_if_valid, block {} is already in the block tree", block.block_hash);
+            return Ok(Vec::new())
+        }
+        if !Self::validate_block(block.clone()) {
+            return Err(format!("ERROR: commit_if_valid, block {} is NOT valid", block.block_id))
+        }
+        Self::store_block_by_hash(block.clone())?;
+        match Self::get_tip_hash() {
+            Some(tip_hash) => {
+                let tip_block: Block = match Self::get_block_by_hash(tip_hash.clone()) {
+                    Some(tip_block) => tip_block,
+                    None => return Err(String::from("ERROR: commit_if_valid, tip block is missing"))
+                };
+                if Self::fork_choice(tip_block.block_id, tip_hash.as_str(), block.block_id, block.block_hash.as_str()) {
+                    let abandoned: Vec<Transaction> = Self::set_canonical_tip(block.clone())?;
+                    println!("[BLOCK, CRITICAL] COMMIT BLOCK SUCCESSFUL");
+                    Ok(abandoned)
                 } else {
-                    Err(String::from("ERROR: commit_if_valid, block_id is NOT 0"))
+                    //its state is replayed from the common ancestor if this branch ever wins
+                    println!("[BLOCK] commit_if_valid, stored block {} on a side branch", block.block_hash);
+                    Ok(Vec::new())
                 }
+            },
+            None => {
+                let abandoned: Vec<Transaction> = Self::set_canonical_tip(block.clone())?;
+                println!("[BLOCK, CRITICAL] COMMIT BLOCK SUCCESSFUL");
+                Ok(abandoned)
             }
         }
     }
@@ -827,8 +1234,10 @@ impl ReadBlock for Block {
 
 #[cfg(test)]
 mod tests {
-    use super::{Block, BlockFromString, JsonToBlock};
+    use super::{Block, BlockHeader, BlockHeaders, BlockFromString, JsonToBlock, BlockTree, HashBlock, BlockLimits, SelectTransactions, Reorganize};
     use timestamp::{Timestamp, StringToTimestamp};
+    use transaction::{Transaction, TransactionType};
+    use amount::{Amount};
 
     #[test]
     fn test_from_string() {
@@ -837,6 +1246,7 @@ mod tests {
           block_hash: String::from("test block hash"),
           block_parent_hash: String::from("test parent hash"),
           block_time: Timestamp::string_to_timestamp(String::from("0")).unwrap(),
+          block_state_root: String::from(""),
           proposal_hash: String::from("test proposal hash"),
           block_data: String::from("test block data"),
           transactions: Vec::new()
@@ -869,6 +1279,7 @@ mod tests {
             block_hash: String::from("hash"),
             block_parent_hash: String::from("hash"),
             block_time: Timestamp::string_to_timestamp(String::from("0")).unwrap(),
+            block_state_root: String::from(""),
             proposal_hash: String::from("hash"),
             block_data: String::from("data"),
             transactions: Vec::new()
@@ -876,4 +1287,105 @@ mod tests {
         let actual_block: Result<Block, String> = Block::from_json(data);
         assert_eq!(expected_block, actual_block.unwrap());
     }
+
+    #[test]
+    fn test_fork_choice_prefers_longest_chain() {
+        assert!(Block::fork_choice(4, "aaaa", 5, "ffff"));
+        assert!(!Block::fork_choice(5, "ffff", 4, "aaaa"));
+    }
+
+    #[test]
+    fn test_fork_choice_breaks_ties_on_lowest_hash() {
+        assert!(Block::fork_choice(5, "bbbb", 5, "aaaa"));
+        assert!(!Block::fork_choice(5, "aaaa", 5, "bbbb"));
+        assert!(!Block::fork_choice(5, "aaaa", 5, "aaaa"));
+    }
+
+    #[test]
+    fn test_hash_block_covers_parent_hash() {
+        let ts: Timestamp = Timestamp::string_to_timestamp(String::from("0")).unwrap();
+        let left: String = Block::hash_block(1, String::from("parent a"), ts.clone(), Vec::new(), String::from("root"));
+        let right: String = Block::hash_block(1, String::from("parent b"), ts, Vec::new(), String::from("root"));
+        assert_ne!(left, right);
+    }
+
+    #[test]
+    fn test_header_verifies_and_round_trips() {
+        let ts: Timestamp = Timestamp::string_to_timestamp(String::from("0")).unwrap();
+        let mut block: Block = Block::from_json(object!{
+            "block_id" => 0,
+            "block_hash" => "",
+            "block_parent_hash" => "parent",
+            "block_time" => "0",
+            "block_state_root" => "root",
+            "proposal_hash" => "hash",
+            "block_data" => "data",
+        }).unwrap();
+        block.block_hash = Block::hash_block(0, String::from("parent"), ts, Vec::new(), String::from("root"));
+        let header: BlockHeader = Block::header(block);
+        assert!(Block::verify_header_hash(header.clone()));
+        let parsed: BlockHeader = Block::header_from_json(Block::header_to_json(header.clone())).unwrap();
+        assert_eq!(parsed, header);
+        let mut tampered: BlockHeader = header;
+        tampered.header_state_root = String::from("another root");
+        assert!(!Block::verify_header_hash(tampered));
+    }
+
+    fn test_transaction(transaction_id: i32, data: &str) -> Transaction {
+        Transaction {
+            transaction_timestamp: Timestamp::string_to_timestamp(String::from("0")).unwrap(),
+            transaction_type: TransactionType::Output,
+            transaction_sender: String::from("sender"),
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: 0 },
+            transaction_nonce: 0,
+            transaction_data: String::from(data),
+            transaction_hash: format!("hash {}", transaction_id)
+        }
+    }
+
+    #[test]
+    fn test_select_transactions_respects_limits() {
+        let small_size: usize = Block::transactions_size(vec![test_transaction(0, "small")]);
+        let pool: Vec<Transaction> = vec![test_transaction(0, "small"),
+                                          test_transaction(1, "a much larger transaction payload"),
+                                          test_transaction(2, "small"),
+                                          test_transaction(3, "small")];
+        let by_count: Vec<Transaction> = Block::select_transactions(pool.clone(), BlockLimits {
+            max_block_bytes: 100000,
+            max_block_transactions: 2
+        });
+        assert_eq!(by_count.iter().map(|tx| tx.transaction_hash.clone()).collect::<Vec<String>>(), vec![String::from("hash 0"), String::from("hash 1")]);
+        let limits: BlockLimits = BlockLimits {
+            max_block_bytes: small_size * 2,
+            max_block_transactions: 10
+        };
+        let by_bytes: Vec<Transaction> = Block::select_transactions(pool.clone(), limits.clone());
+        assert_eq!(by_bytes.iter().map(|tx| tx.transaction_hash.clone()).collect::<Vec<String>>(), vec![String::from("hash 0"), String::from("hash 2")]);
+        assert!(Block::within_limits(by_bytes, limits.clone()));
+        assert!(!Block::within_limits(pool, limits));
+    }
+
+    fn test_block(block_id: i64, transactions: Vec<Transaction>) -> Block {
+        Block {
+            block_id: block_id,
+            block_hash: format!("block {}", block_id),
+            block_parent_hash: format!("block {}", block_id - 1),
+            block_time: Timestamp::string_to_timestamp(String::from("0")).unwrap(),
+            block_state_root: String::from(""),
+            proposal_hash: String::from("proposal hash"),
+            block_data: String::from(""),
+            transactions: transactions
+        }
+    }
+
+    #[test]
+    fn test_abandoned_transactions_skip_the_new_branch() {
+        //tip first, the way the abandoned blocks are walked
+        let abandoned: Vec<Block> = vec![test_block(2, vec![test_transaction(2, "two"), test_transaction(3, "three")]),
+                                         test_block(1, vec![test_transaction(0, "zero"), test_transaction(1, "one")])];
+        let branch: Vec<Block> = vec![test_block(1, vec![test_transaction(1, "one"), test_transaction(3, "three")])];
+        let returned: Vec<Transaction> = Block::abandoned_transactions(abandoned, branch);
+        assert_eq!(returned.iter().map(|tx| tx.transaction_hash.clone()).collect::<Vec<String>>(), vec![String::from("hash 0"), String::from("hash 2")]);
+    }
 }
@@ -0,0 +1,364 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
+use db::{DB, DBReadEvidence, DBCertificate, DBGenesis};
+use block::{Block, ReadBlock};
+use proposal::{Proposal,
+               ProposalStatus,
+               StatusToString,
+               HashProposal};
+use validator::{Validator, ValidatorSchedule};
+use evidence::{Vote,
+               Evidence,
+               VoteToJson,
+               JsonToVote,
+               VerifySignedMessage};
+use timestamp::{Timestamp, StringToTimestamp};
+use signature::{DigitalSignature,
+                SignatureType,
+                SignatureFormat,
+                Verifier,
+                Signature};
+use std::collections::HashSet;
+
+/*
+@name Certificate
+@desc proof that the network committed a block: the signed proposal
+      that carried it, and the signed accept votes of the validators.
+      Everything needed to check it offline, given the validator keys
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Certificate {
+    pub certificate_block_id: i64,
+    pub certificate_block_hash: String,
+    pub certificate_proposal_id: i32,
+    pub certificate_proposal_hash: String,
+    pub certificate_proposal_sender: String,
+    pub certificate_proposal_time: Timestamp,
+    pub certificate_proposal_creator: i32,
+    pub certificate_chain_id: String,
+    pub certificate_proposal_signature: String,
+    pub certificate_votes: Vec<Vote>
+}
+
+/*
+@name CertificateToJson
+@desc
+*/
+pub trait CertificateToJson {
+    fn to_json(certificate: Certificate) -> JsonValue;
+}
+
+impl CertificateToJson for Certificate {
+    fn to_json(certificate: Certificate) -> JsonValue {
+        let mut votes: JsonValue = JsonValue::new_array();
+        for vote in certificate.certificate_votes {
+            match votes.push(Vote::to_json(vote)) {
+                Ok(_) => {},
+                Err(_) => println!("Certificate, ERROR: to_json, could not push vote")
+            }
+        }
+        object!{
+            "certificate_block_id" => certificate.certificate_block_id,
+            "certificate_block_hash" => certificate.certificate_block_hash,
+            "certificate_proposal_id" => certificate.certificate_proposal_id,
+            "certificate_proposal_hash" => certificate.certificate_proposal_hash,
+            "certificate_proposal_sender" => certificate.certificate_proposal_sender,
+            "certificate_proposal_time" => certificate.certificate_proposal_time.timestamp,
+            "certificate_proposal_creator" => certificate.certificate_proposal_creator,
+            "certificate_chain_id" => certificate.certificate_chain_id,
+            "certificate_proposal_signature" => certificate.certificate_proposal_signature,
+            "certificate_votes" => votes
+        }
+    }
+}
+
+/*
+@name JsonToCertificate
+@desc
+*/
+pub trait JsonToCertificate {
+    fn from_json(payload: JsonValue) -> Result<Certificate, String>;
+    fn from_json_string(json_string: String) -> Result<Certificate, String>;
+}
+
+impl JsonToCertificate for Certificate {
+    fn from_json(payload: JsonValue) -> Result<Certificate, String> {
+        let mut votes: Vec<Vote> = Vec::new();
+        for vote_json in payload["certificate_votes"].members() {
+            votes.push( Vote::from_json(vote_json.clone())? );
+        }
+        match (payload["certificate_block_id"].as_i64(),
+               payload["certificate_block_hash"].as_str(),
+               payload["certificate_proposal_id"].as_i32(),
+               payload["certificate_proposal_hash"].as_str(),
+               payload["certificate_proposal_sender"].as_str(),
+               payload["certificate_proposal_time"].as_str(),
+               payload["certificate_proposal_creator"].as_i32(),
+               payload["certificate_chain_id"].as_str(),
+               payload["certificate_proposal_signature"].as_str()) {
+            (Some(block_id),
+             Some(block_hash),
+             Some(proposal_id),
+             Some(proposal_hash),
+             Some(proposal_sender),
+             Some(proposal_time),
+             Some(proposal_creator),
+             Some(chain_id),
+             Some(proposal_signature)) => {
+                let time: Timestamp = match Timestamp::string_to_timestamp(String::from(proposal_time)) {
+                    Some(ts) => ts,
+                    None => return Err(String::from("Certificate, ERROR: from_json, proposal time is not a timestamp"))
+                };
+                Ok(Certificate {
+                    certificate_block_id: block_id,
+                    certificate_block_hash: String::from(block_hash),
+                    certificate_proposal_id: proposal_id,
+                    certificate_proposal_hash: String::from(proposal_hash),
+                    certificate_proposal_sender: String::from(proposal_sender),
+                    certificate_proposal_time: time,
+                    certificate_proposal_creator: proposal_creator,
+                    certificate_chain_id: String::from(chain_id),
+                    certificate_proposal_signature: String::from(proposal_signature),
+                    certificate_votes: votes
+                })
+            },
+            _ => {
+                Err(String::from("Certificate, ERROR: from_json, certificate is missing a field"))
+            }
+        }
+    }
+
+    fn from_json_string(json_string: String) -> Result<Certificate, String> {
+        match json::parse(&json_string) {
+            Ok(parsed) => Self::from_json(parsed),
+            Err(_) => Err(String::from("Certificate, ERROR: from_json_string, could not parse certificate"))
+        }
+    }
+}
+
+/*
+@name Quorum
+@desc a block is final once more than two thirds of the
+      registered validators signed for it, the creator included
+*/
+pub trait Quorum {
+    fn has_quorum(signer_count: usize, validator_count: usize) -> bool;
+}
+
+impl Quorum for Certificate {
+    fn has_quorum(signer_count: usize, validator_count: usize) -> bool {
+        validator_count > 0 && signer_count * 3 > validator_count * 2
+    }
+}
+
+/*
+@name CreateCertificate
+@desc build the certificate of a proposal from the accept votes
+      received for it, which are kept in the vote index
+*/
+pub trait CreateCertificate {
+    fn create_certificate(proposal: Proposal) -> Option<Certificate>;
+}
+
+impl CreateCertificate for Certificate {
+    fn create_certificate(proposal: Proposal) -> Option<Certificate> {
+        let vote_index: JsonValue = json::parse( &DB::read_vote_index()? ).ok()?;
+        let accepted: &str = Proposal::string_from_status(ProposalStatus::Accepted);
+        let mut votes: Vec<Vote> = Vec::new();
+        for (_key, vote_json) in vote_index["votes"].entries() {
+            match Vote::from_json(vote_json.clone()) {
+                Ok(vote) => {
+                    if vote.vote_block_id == proposal.proposal_block.block_id
+                        && vote.vote_proposal_id == proposal.proposal_id
+                        && vote.vote_proposal_hash == proposal.proposal_hash
+                        && vote.vote_status == accepted {
+                        votes.push(vote);
+                    }
+                },
+                Err(_) => {}
+            }
+        }
+        votes.sort_by(|a, b| a.vote_voter.cmp(&b.vote_voter));
+        Some(Certificate {
+            certificate_block_id: proposal.proposal_block.block_id,
+            certificate_block_hash: proposal.proposal_block.block_hash,
+            certificate_proposal_id: proposal.proposal_id,
+            certificate_proposal_hash: proposal.proposal_hash,
+            certificate_proposal_sender: proposal.proposal_sender,
+            certificate_proposal_time: proposal.proposal_time,
+            certificate_proposal_creator: proposal.proposal_creator,
+            certificate_chain_id: proposal.proposal_chain_id,
+            certificate_proposal_signature: proposal.proposal_signature,
+            certificate_votes: votes
+        })
+    }
+}
+
+/*
+@name VerifyCertificate
+@desc check a certificate against the validator registry
+*/
+pub trait VerifyCertificate {
+    fn verify_certificate(certificate: Certificate) -> Result<(), String>;
+}
+
+impl VerifyCertificate for Certificate {
+    fn verify_certificate(certificate: Certificate) -> Result<(), String> {
+        if Some(certificate.certificate_chain_id.clone()) != DB::read_chain_id() {
+            return Err(format!("verify_certificate, certificate is for chain {}", certificate.certificate_chain_id))
+        }
+        //the proposal hash covers the block hash, so the creator signed this exact block
+        let proposal_hash: String = Proposal::hash_proposal(certificate.certificate_proposal_id,
+                                                            certificate.certificate_proposal_sender.clone(),
+                                                            certificate.certificate_proposal_creator,
+                                                            certificate.certificate_proposal_time.clone(),
+                                                            certificate.certificate_chain_id.clone(),
+                                                            certificate.certificate_block_hash.clone());
+        if proposal_hash != certificate.certificate_proposal_hash {
+            return Err(String::from("verify_certificate, proposal hash does not cover the block"))
+        }
+        let creator_signed: bool = match Validator::get_validator_at(certificate.certificate_block_id, certificate.certificate_proposal_creator) {
+            Some(validator) => {
+                let digital_signature: DigitalSignature = DigitalSignature {
+                    signature_type: validator.validator_key_type.clone(),
+                    format_type: SignatureFormat::BYTES
+                };
+                Signature::verify_with_key(digital_signature,
+                                           certificate.certificate_proposal_signature.clone(),
+                                           certificate.certificate_proposal_hash.as_bytes(),
+                                           validator.validator_public_key).is_ok()
+            },
+            None => false
+        };
+        if !creator_signed {
+            return Err(String::from("verify_certificate, creator signature is not valid"))
+        }
+        let accepted: &str = Proposal::string_from_status(ProposalStatus::Accepted);
+        let mut signers: HashSet<i32> = HashSet::new();
+        signers.insert(certificate.certificate_proposal_creator);
+        for vote in certificate.certificate_votes.clone() {
+            if vote.vote_block_id != certificate.certificate_block_id
+                || vote.vote_proposal_id != certificate.certificate_proposal_id
+                || vote.vote_proposal_hash != certificate.certificate_proposal_hash
+                || vote.vote_status != accepted {
+                return Err(format!("verify_certificate, vote from {} is not an accept of this proposal", vote.vote_voter))
+            }
+            if !Evidence::verify_vote(vote.clone()) {
+                return Err(format!("verify_certificate, vote signature from {} is not valid", vote.vote_voter))
+            }
+            signers.insert(vote.vote_voter);
+        }
+        //the set that was active at the block, changes after it do not count
+        let validator_count: usize = Validator::get_validators_at(certificate.certificate_block_id).len();
+        if Self::has_quorum(signers.len(), validator_count) {
+            Ok(())
+        } else {
+            Err(format!("verify_certificate, {} of {} validators signed, no quorum", signers.len(), validator_count))
+        }
+    }
+}
+
+/*
+@name StoreCertificate
+@desc
+*/
+pub trait StoreCertificate {
+    fn store_certificate(certificate: Certificate) -> Result<String, String>;
+}
+
+impl StoreCertificate for Certificate {
+    fn store_certificate(certificate: Certificate) -> Result<String, String> {
+        let block_hash: String = certificate.certificate_block_hash.clone();
+        match DB::write_certificate(block_hash, Self::to_json(certificate).dump()) {
+            Ok(_) => Ok(String::from("store_certificate, certificate stored")),
+            Err(_) => Err(String::from("store_certificate ERROR: could not write certificate"))
+        }
+    }
+}
+
+/*
+@name ReadCertificate
+@desc
+*/
+pub trait ReadCertificate {
+    fn get_certificate_by_block_hash(block_hash: String) -> Option<Certificate>;
+    fn get_certificate_by_block_id(block_id: i64) -> Option<Certificate>;
+}
+
+impl ReadCertificate for Certificate {
+    fn get_certificate_by_block_hash(block_hash: String) -> Option<Certificate> {
+        Self::from_json_string( DB::read_certificate(block_hash)? ).ok()
+    }
+
+    /*
+    @name get_certificate_by_block_id
+    @desc the certificate of the canonical block at this height
+    */
+    fn get_certificate_by_block_id(block_id: i64) -> Option<Certificate> {
+        let block: Block = Block::get_block_by_block_id(block_id)?;
+        Self::get_certificate_by_block_hash(block.block_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Certificate,
+                CertificateToJson,
+                JsonToCertificate,
+                Quorum};
+    use evidence::{Vote};
+    use timestamp::{Timestamp};
+
+    fn test_certificate() -> Certificate {
+        Certificate {
+            certificate_block_id: 4,
+            certificate_block_hash: String::from("block hash"),
+            certificate_proposal_id: 7,
+            certificate_proposal_hash: String::from("proposal hash"),
+            certificate_proposal_sender: String::from("127.0.0.1:8081"),
+            certificate_proposal_time: Timestamp{timestamp: String::from("100")},
+            certificate_proposal_creator: 1,
+            certificate_chain_id: String::from("africaos-test"),
+            certificate_proposal_signature: String::from("creator signature"),
+            certificate_votes: vec![Vote {
+                vote_voter: 2,
+                vote_block_id: 4,
+                vote_proposal_id: 7,
+                vote_proposal_hash: String::from("proposal hash"),
+                vote_status: String::from("accepted"),
+                vote_signature: String::from("voter signature")
+            }]
+        }
+    }
+
+    #[test]
+    fn test_certificate_json_round_trip() {
+        let certificate: Certificate = test_certificate();
+        let converted: Result<Certificate, String> = Certificate::from_json_string( Certificate::to_json(certificate.clone()).dump() );
+        assert_eq!(converted.unwrap(), certificate);
+    }
+
+    #[test]
+    fn test_quorum_needs_more_than_two_thirds() {
+        assert!(!Certificate::has_quorum(2, 3));
+        assert!(Certificate::has_quorum(3, 3));
+        assert!(Certificate::has_quorum(3, 4));
+        assert!(!Certificate::has_quorum(0, 0));
+    }
+}
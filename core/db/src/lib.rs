@@ -82,6 +82,57 @@ const STATES_DB_LOC: &str = "storage/states.db";
 const BLOCKS_LOC: &str = "storage/chain/";
 const BLOCKS_DB_LOC: &str = "storage/chain.db";
 
+/*
+@desc BLOCKS_BY_HASH_LOC stores every block received, canonical or not, by block hash
+*/
+const BLOCKS_BY_HASH_LOC: &str = "storage/chain/hash/";
+const BLOCK_TREE_DB_LOC: &str = "storage/block_tree.db";
+
+/*
+@desc VALIDATORS_DB_LOC stores the registered validators and their public keys
+*/
+const VALIDATORS_DB_LOC: &str = "storage/validators.db";
+
+/*
+@desc IDENTITY_DB_LOC stores this node's validator id and signing key location
+*/
+const IDENTITY_DB_LOC: &str = "storage/identity.db";
+
+/*
+@desc KEYSTORE_DB_LOC stores this node's private keys, encrypted with its passphrase
+*/
+const KEYSTORE_DB_LOC: &str = "storage/keystore.db";
+
+/*
+@desc EVIDENCE_DB_LOC stores proof of validators signing two conflicting messages
+*/
+const EVIDENCE_DB_LOC: &str = "storage/evidence.db";
+
+/*
+@desc VOTES_DB_LOC stores the signed votes received from peers, per block id and proposal id
+*/
+const VOTES_DB_LOC: &str = "storage/votes.db";
+
+/*
+@desc CERTIFICATES_LOC stores the commit certificate of each committed block, by block hash
+*/
+const CERTIFICATES_LOC: &str = "storage/chain/certificate/";
+
+/*
+@desc SYNC_DB_LOC stores the latest height reported by each peer, to know when we fall behind
+*/
+const SYNC_DB_LOC: &str = "storage/sync.db";
+
+/*
+@desc HEADERS_DB_LOC stores the certified block headers downloaded during snapshot sync
+*/
+const HEADERS_DB_LOC: &str = "storage/headers.db";
+
+/*
+@desc GENESIS_DB_LOC stores the genesis this node started from, and its hash
+*/
+const GENESIS_DB_LOC: &str = "storage/genesis.db";
+
 
 pub trait DBInit{
     fn create_sql_databases() -> Result<(), std::io::Error>;
@@ -385,7 +436,7 @@ impl DBStateManager for DB {
 pub trait DBReadTransaction {
     fn read_transaction_index() -> Option<String>;
     fn write_transaction_index(db_json_string: String) -> Result<String, Error>;
-    fn read_transaction(transaction_id: i64) -> Option<String>;
+    fn read_transaction(transaction_hash: String) -> Option<String>;
 }
 
 impl DBReadTransaction for DB {
@@ -414,8 +465,14 @@ impl DBReadTransaction for DB {
         Ok(String::from("Ok, Successfully wrote DB JSON index FOR TRANSACTION"))
     }
 
-    fn read_transaction(transaction_id: i64) -> Option<String> {
-        let file_location: String = format!("{}",TRANSACTIONS_DB_LOC);
+    /*
+    @name read_transaction
+    @desc read a transaction by its hash
+    */
+    fn read_transaction(transaction_hash: String) -> Option<String> {
+        let file_location: String = format!("{}{}",
+                                    TRANSACTIONS_LOC,
+                                    format!("transaction_{}.dat", transaction_hash));
         match Self::read(file_location) {
             Some(p) => Some(p),
             None => None
@@ -429,19 +486,19 @@ impl DBReadTransaction for DB {
 @desc
 */
 pub trait DBWriteTransaction {
-    fn write_transaction_to_sql(tid: i32, transaction_string: String) -> Result<String,std::io::Error>;
+    fn write_transaction_to_sql(transaction_hash: String, transaction_string: String) -> Result<String,std::io::Error>;
 }
 
 impl DBWriteTransaction for DB {
     /*
     pass string to write into db
     */
-    fn write_transaction_to_sql(tid: i32, transaction_string: String) -> Result<String,std::io::Error>{
+    fn write_transaction_to_sql(transaction_hash: String, transaction_string: String) -> Result<String,std::io::Error>{
         //write to transactions file
         println!("Writing TRANSACTION to DB");
         let file_location: String = format!("{}{}",
                                     TRANSACTIONS_LOC,
-                                    format!("transaction_{}.dat", tid));
+                                    format!("transaction_{}.dat", transaction_hash));
         let mut file = fs::File::create(file_location.to_string())?;
         file.write( transaction_string.as_bytes() )?;
         println!("Wrote TRANSACTION");
@@ -579,6 +636,384 @@ impl DBWriteBlock for DB {
     }
 }
 
+/*
+@name DBBlockTree
+@desc blocks by hash, the block tree index, and the state snapshot after each block
+*/
+pub trait DBBlockTree {
+    fn read_block_tree() -> Option<String>;
+    fn write_block_tree(db_json_string: String) -> Result<String, Error>;
+    fn read_block_by_hash(block_hash: String) -> Option<String>;
+    fn write_block_by_hash(block_hash: String, block_string: String) -> Result<String, Error>;
+    fn read_state_snapshot(block_hash: String) -> Option<String>;
+    fn write_state_snapshot(block_hash: String, state_string: String) -> Result<String, Error>;
+}
+
+impl DBBlockTree for DB {
+    /*
+    @name read_block_tree
+    @desc read and return JSON DB map
+    */
+    fn read_block_tree() -> Option<String> {
+        let file_location: String = format!("{}",BLOCK_TREE_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_block_tree
+    @desc write JSON db map to disk, the tree is never pruned
+    */
+    fn write_block_tree(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_block_tree: Attempting to Write DB JSON INDEX FOR BLOCK TREE");
+        let file_location: String = format!("{}",BLOCK_TREE_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR BLOCK TREE"))
+    }
+
+    fn read_block_by_hash(block_hash: String) -> Option<String> {
+        let file_location: String = format!("{}{}",
+                                            BLOCKS_BY_HASH_LOC,
+                                            format!("block_{}.dat", block_hash));
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    fn write_block_by_hash(block_hash: String, block_string: String) -> Result<String, Error> {
+        println!("Writing BLOCK to DB by hash: {}", block_hash);
+        let file_location: String = format!("{}{}",
+                                            BLOCKS_BY_HASH_LOC,
+                                            format!("block_{}.dat", block_hash));
+        Self::write(block_string, file_location)
+    }
+
+    /*
+    @name read_state_snapshot
+    @desc read the state as it was right after the block with this hash executed
+    */
+    fn read_state_snapshot(block_hash: String) -> Option<String> {
+        let file_location: String = format!("{}{}",
+                                            STATES_LOC,
+                                            format!("state_{}.state", block_hash));
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    fn write_state_snapshot(block_hash: String, state_string: String) -> Result<String, Error> {
+        println!("DB, write_state_snapshot: Writing state snapshot for block: {}", block_hash);
+        let file_location: String = format!("{}{}",
+                                            STATES_LOC,
+                                            format!("state_{}.state", block_hash));
+        Self::write(state_string, file_location)
+    }
+}
+
+/*
+@name DBReadValidator
+@desc
+*/
+pub trait DBReadValidator {
+    fn read_validator_index() -> Option<String>;
+    fn write_validator_index(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBReadValidator for DB {
+    /*
+    @name read_validator_index
+    @desc read and return JSON DB map
+    */
+    fn read_validator_index() -> Option<String> {
+        let file_location: String = format!("{}",VALIDATORS_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_validator_index
+    @desc write JSON db map to disk
+    */
+    fn write_validator_index(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_validator_index: Attempting to Write DB JSON INDEX FOR VALIDATORS");
+        let file_location: String = format!("{}",VALIDATORS_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR VALIDATORS"))
+    }
+}
+
+/*
+@name DBIdentityManager
+@desc
+*/
+pub trait DBIdentityManager {
+    fn read_identity() -> Option<String>;
+    fn write_identity(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBIdentityManager for DB {
+    /*
+    @name read_identity
+    @desc read and return this node's identity JSON
+    */
+    fn read_identity() -> Option<String> {
+        let file_location: String = format!("{}",IDENTITY_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_identity
+    @desc write this node's identity JSON to disk
+    */
+    fn write_identity(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_identity: Attempting to Write node identity");
+        let file_location: String = format!("{}",IDENTITY_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote node identity"))
+    }
+}
+
+/*
+@name DBKeystore
+@desc
+*/
+pub trait DBKeystore {
+    fn read_keystore() -> Option<String>;
+    fn write_keystore(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBKeystore for DB {
+    /*
+    @name read_keystore
+    @desc read and return this node's encrypted keystore JSON
+    */
+    fn read_keystore() -> Option<String> {
+        let file_location: String = format!("{}",KEYSTORE_DB_LOC);
+        Self::read(file_location)
+    }
+
+    /*
+    @name write_keystore
+    @desc write this node's encrypted keystore JSON to disk
+    */
+    fn write_keystore(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_keystore: Attempting to Write node keystore");
+        let file_location: String = format!("{}",KEYSTORE_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote node keystore"))
+    }
+}
+
+/*
+@name DBReadEvidence
+@desc
+*/
+pub trait DBReadEvidence {
+    fn read_evidence_index() -> Option<String>;
+    fn write_evidence_index(db_json_string: String) -> Result<String, Error>;
+    fn read_vote_index() -> Option<String>;
+    fn write_vote_index(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBReadEvidence for DB {
+    /*
+    @name read_evidence_index
+    @desc read and return JSON DB map
+    */
+    fn read_evidence_index() -> Option<String> {
+        let file_location: String = format!("{}",EVIDENCE_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_evidence_index
+    @desc write JSON db map to disk
+    */
+    fn write_evidence_index(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_evidence_index: Attempting to Write DB JSON INDEX FOR EVIDENCE");
+        let file_location: String = format!("{}",EVIDENCE_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR EVIDENCE"))
+    }
+
+    /*
+    @name read_vote_index
+    @desc read and return JSON DB map
+    */
+    fn read_vote_index() -> Option<String> {
+        let file_location: String = format!("{}",VOTES_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_vote_index
+    @desc write JSON db map to disk
+    */
+    fn write_vote_index(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_vote_index: Attempting to Write DB JSON INDEX FOR VOTES");
+        let file_location: String = format!("{}",VOTES_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR VOTES"))
+    }
+}
+
+/*
+@name DBCertificate
+@desc commit certificates, stored alongside the block they finalize
+*/
+pub trait DBCertificate {
+    fn read_certificate(block_hash: String) -> Option<String>;
+    fn write_certificate(block_hash: String, certificate_string: String) -> Result<String, Error>;
+}
+
+impl DBCertificate for DB {
+    fn read_certificate(block_hash: String) -> Option<String> {
+        let file_location: String = format!("{}{}",
+                                            CERTIFICATES_LOC,
+                                            format!("certificate_{}.dat", block_hash));
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    fn write_certificate(block_hash: String, certificate_string: String) -> Result<String, Error> {
+        println!("DB, write_certificate: Writing commit certificate for block: {}", block_hash);
+        let file_location: String = format!("{}{}",
+                                            CERTIFICATES_LOC,
+                                            format!("certificate_{}.dat", block_hash));
+        Self::write(certificate_string, file_location)
+    }
+}
+
+/*
+@name DBSync
+@desc
+*/
+pub trait DBSync {
+    fn read_sync_index() -> Option<String>;
+    fn write_sync_index(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBSync for DB {
+    /*
+    @name read_sync_index
+    @desc read and return JSON DB map
+    */
+    fn read_sync_index() -> Option<String> {
+        let file_location: String = format!("{}",SYNC_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_sync_index
+    @desc write JSON db map to disk
+    */
+    fn write_sync_index(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_sync_index: Attempting to Write DB JSON INDEX FOR SYNC");
+        let file_location: String = format!("{}",SYNC_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR SYNC"))
+    }
+}
+
+/*
+@name DBHeader
+@desc
+*/
+pub trait DBHeader {
+    fn read_header_index() -> Option<String>;
+    fn write_header_index(db_json_string: String) -> Result<String, Error>;
+}
+
+impl DBHeader for DB {
+    /*
+    @name read_header_index
+    @desc read and return JSON DB map
+    */
+    fn read_header_index() -> Option<String> {
+        let file_location: String = format!("{}",HEADERS_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_header_index
+    @desc write JSON db map to disk
+    */
+    fn write_header_index(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_header_index: Attempting to Write DB JSON INDEX FOR HEADERS");
+        let file_location: String = format!("{}",HEADERS_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON index FOR HEADERS"))
+    }
+}
+
+/*
+@name DBGenesis
+@desc
+*/
+pub trait DBGenesis {
+    fn read_genesis() -> Option<String>;
+    fn write_genesis(db_json_string: String) -> Result<String, Error>;
+    fn read_chain_id() -> Option<String>;
+}
+
+impl DBGenesis for DB {
+    /*
+    @name read_genesis
+    @desc read and return JSON DB map
+    */
+    fn read_genesis() -> Option<String> {
+        let file_location: String = format!("{}",GENESIS_DB_LOC);
+        match Self::read(file_location) {
+            Some(p) => Some(p),
+            None => None
+        }
+    }
+
+    /*
+    @name write_genesis
+    @desc write JSON db map to disk
+    */
+    fn write_genesis(db_json_string: String) -> Result<String, Error> {
+        println!("DB, write_genesis: Attempting to Write DB JSON FOR GENESIS");
+        let file_location: String = format!("{}",GENESIS_DB_LOC);
+        Self::write(db_json_string, file_location)?;
+        Ok(String::from("Ok, Successfully wrote DB JSON FOR GENESIS"))
+    }
+
+    /*
+    @name read_chain_id
+    @desc the chain id recorded with the genesis, crates below genesis
+          read it from here
+    */
+    fn read_chain_id() -> Option<String> {
+        let genesis_record = json::parse(&Self::read_genesis()?).ok()?;
+        genesis_record["chain_id"].as_str().map(String::from)
+    }
+}
+
 /*
 @name FileDirectoryReader
 @desc this trait handles all disk-bound file directory reading
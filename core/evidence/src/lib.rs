@@ -0,0 +1,789 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
+use db::{DB, DBReadEvidence, DBGenesis};
+use hash::{Hasher, CalculateSHA256Hash};
+use block::{Block, HashBlock};
+use proposal::{Proposal,
+               ProposalStatus,
+               JsonConverter,
+               StatusToString,
+               GetProposals,
+               HashProposal,
+               VerifyProposalSignature};
+use validator::{Validator, Identity, IdentityManager, ValidatorSchedule};
+use keystore::{Keystore, KeystoreSigner};
+use signature::{DigitalSignature,
+                SignatureType,
+                SignatureFormat,
+                Verifier,
+                Signature};
+
+/*
+@name Vote
+@desc a signed response of a validator to a proposal.
+      A proposal id acts as the round for a block id, so
+      an honest voter accepts at most one proposal hash per pair
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Vote {
+    pub vote_voter: i32,
+    pub vote_block_id: i64,
+    pub vote_proposal_id: i32,
+    pub vote_proposal_hash: String,
+    pub vote_status: String,
+    pub vote_signature: String
+}
+
+/*
+@name EvidenceType
+@desc the kind of conflicting messages held by an evidence record
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub enum EvidenceType {
+    ConflictingProposals,   //two signed proposals for the same block id and proposal id
+    ConflictingVotes,   //two signed accept votes for the same block id and proposal id
+    EvidenceTypeError   //DEFAULT ENUM ERROR
+}
+
+/*
+@name EvidenceStatus
+@desc whether this node has gossiped an evidence record to its peers yet
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub enum EvidenceStatus {
+    Pending,    //for evidence just detected or received
+    Broadcasted,    //for evidence sent to all peers
+    EvidenceStatusError   //DEFAULT ENUM ERROR
+}
+
+/*
+@name Evidence
+@desc proof that a validator signed two conflicting messages
+      for the same height. evidence_first and evidence_second
+      hold the JSON of the two messages, ordered by message hash
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Evidence {
+    pub evidence_hash: String,
+    pub evidence_type: EvidenceType,
+    pub evidence_offender: i32,
+    pub evidence_block_id: i64,
+    pub evidence_proposal_id: i32,
+    pub evidence_first: String,
+    pub evidence_second: String,
+    pub evidence_status: EvidenceStatus
+}
+
+/*
+@name EvidenceStringConverter
+@desc convert evidence enums to and from their stored strings
+*/
+pub trait EvidenceStringConverter {
+    fn string_from_type(evidence_type: EvidenceType) -> &'static str;
+    fn type_from_string(string_representation: &str) -> EvidenceType;
+    fn string_from_status(status: EvidenceStatus) -> &'static str;
+    fn status_from_string(string_representation: &str) -> EvidenceStatus;
+}
+
+impl EvidenceStringConverter for Evidence {
+    fn string_from_type(evidence_type: EvidenceType) -> &'static str {
+        match evidence_type {
+            EvidenceType::ConflictingProposals => "conflicting_proposals",
+            EvidenceType::ConflictingVotes => "conflicting_votes",
+            _ => "evidence_type_error"
+        }
+    }
+
+    fn type_from_string(string_representation: &str) -> EvidenceType {
+        match string_representation {
+            "conflicting_proposals" => EvidenceType::ConflictingProposals,
+            "conflicting_votes" => EvidenceType::ConflictingVotes,
+            _ => EvidenceType::EvidenceTypeError
+        }
+    }
+
+    fn string_from_status(status: EvidenceStatus) -> &'static str {
+        match status {
+            EvidenceStatus::Pending => "pending",
+            EvidenceStatus::Broadcasted => "broadcasted",
+            _ => "evidence_status_error"
+        }
+    }
+
+    fn status_from_string(string_representation: &str) -> EvidenceStatus {
+        match string_representation {
+            "pending" => EvidenceStatus::Pending,
+            "broadcasted" => EvidenceStatus::Broadcasted,
+            _ => EvidenceStatus::EvidenceStatusError
+        }
+    }
+}
+
+/*
+@name VoteToJson
+@desc
+*/
+pub trait VoteToJson {
+    fn to_json(vote: Vote) -> JsonValue;
+}
+
+impl VoteToJson for Vote {
+    fn to_json(vote: Vote) -> JsonValue {
+        object!{
+            "vote_voter" => vote.vote_voter,
+            "vote_block_id" => vote.vote_block_id,
+            "vote_proposal_id" => vote.vote_proposal_id,
+            "vote_proposal_hash" => vote.vote_proposal_hash,
+            "vote_status" => vote.vote_status,
+            "vote_signature" => vote.vote_signature
+        }
+    }
+}
+
+/*
+@name JsonToVote
+@desc
+*/
+pub trait JsonToVote {
+    fn from_json(payload: JsonValue) -> Result<Vote, String>;
+}
+
+impl JsonToVote for Vote {
+    fn from_json(payload: JsonValue) -> Result<Vote, String> {
+        match (payload["vote_voter"].as_i32(),
+               payload["vote_block_id"].as_i64(),
+               payload["vote_proposal_id"].as_i32(),
+               payload["vote_proposal_hash"].as_str(),
+               payload["vote_status"].as_str(),
+               payload["vote_signature"].as_str()) {
+            (Some(voter), Some(block_id), Some(proposal_id), Some(proposal_hash), Some(status), Some(signature)) => {
+                Ok(Vote {
+                    vote_voter: voter,
+                    vote_block_id: block_id,
+                    vote_proposal_id: proposal_id,
+                    vote_proposal_hash: String::from(proposal_hash),
+                    vote_status: String::from(status),
+                    vote_signature: String::from(signature)
+                })
+            },
+            _ => {
+                Err(String::from("Vote, ERROR: from_json, vote is missing a field"))
+            }
+        }
+    }
+}
+
+/*
+@name EvidenceToJson
+@desc
+*/
+pub trait EvidenceToJson {
+    fn to_json(evidence: Evidence) -> JsonValue;
+}
+
+impl EvidenceToJson for Evidence {
+    fn to_json(evidence: Evidence) -> JsonValue {
+        object!{
+            "evidence_hash" => evidence.evidence_hash,
+            "evidence_type" => Self::string_from_type(evidence.evidence_type),
+            "evidence_offender" => evidence.evidence_offender,
+            "evidence_block_id" => evidence.evidence_block_id,
+            "evidence_proposal_id" => evidence.evidence_proposal_id,
+            "evidence_first" => evidence.evidence_first,
+            "evidence_second" => evidence.evidence_second,
+            "evidence_status" => Self::string_from_status(evidence.evidence_status)
+        }
+    }
+}
+
+/*
+@name JsonToEvidence
+@desc
+*/
+pub trait JsonToEvidence {
+    fn from_json(payload: JsonValue) -> Result<Evidence, String>;
+    fn from_json_string(json_string: String) -> Result<Evidence, String>;
+}
+
+impl JsonToEvidence for Evidence {
+    fn from_json(payload: JsonValue) -> Result<Evidence, String> {
+        match (payload["evidence_hash"].as_str(),
+               payload["evidence_type"].as_str(),
+               payload["evidence_offender"].as_i32(),
+               payload["evidence_block_id"].as_i64(),
+               payload["evidence_proposal_id"].as_i32(),
+               payload["evidence_first"].as_str(),
+               payload["evidence_second"].as_str()) {
+            (Some(hash), Some(evidence_type), Some(offender), Some(block_id), Some(proposal_id), Some(first), Some(second)) => {
+                //a record without a status was received from a peer, and has not been gossiped by us yet
+                let evidence_status: EvidenceStatus = match payload["evidence_status"].as_str() {
+                    Some(status) => Self::status_from_string(status),
+                    None => EvidenceStatus::Pending
+                };
+                Ok(Evidence {
+                    evidence_hash: String::from(hash),
+                    evidence_type: Self::type_from_string(evidence_type),
+                    evidence_offender: offender,
+                    evidence_block_id: block_id,
+                    evidence_proposal_id: proposal_id,
+                    evidence_first: String::from(first),
+                    evidence_second: String::from(second),
+                    evidence_status: evidence_status
+                })
+            },
+            _ => {
+                Err(String::from("Evidence, ERROR: from_json, evidence is missing a field"))
+            }
+        }
+    }
+
+    fn from_json_string(json_string: String) -> Result<Evidence, String> {
+        match json::parse( &format!(r#"{}"#, json_string) ) {
+            Ok(parsed) => Self::from_json(parsed),
+            Err(_) => Err(String::from("Evidence, ERROR: from_json_string, could not parse evidence JSON"))
+        }
+    }
+}
+
+/*
+@name CreateEvidenceIndex
+@desc to initially create the evidence and vote indexes,
+      existing evidence is kept across restarts
+*/
+pub trait CreateEvidenceIndex {
+    fn create_evidence_index() -> ();
+}
+
+impl CreateEvidenceIndex for Evidence {
+    fn create_evidence_index() -> () {
+        if DB::read_evidence_index().is_none() {
+            let new_evidence_index = object!{
+                "evidence" => object!{}
+            };
+            match DB::write_evidence_index(json::stringify(new_evidence_index)) {
+                Ok(_) => println!("Successfully wrote evidence index"),
+                Err(_) => println!("Failure writing evidence index")
+            }
+        }
+        if DB::read_vote_index().is_none() {
+            let new_vote_index = object!{
+                "votes" => object!{}
+            };
+            match DB::write_vote_index(json::stringify(new_vote_index)) {
+                Ok(_) => println!("Successfully wrote vote index"),
+                Err(_) => println!("Failure writing vote index")
+            }
+        }
+    }
+}
+
+/*
+@name HashVote
+@desc the hash a voter signs, over the vote fields and the chain id
+      as a JSON object with keys inserted in this order
+*/
+pub trait HashVote {
+    fn hash_vote(voter: i32, block_id: i64, proposal_id: i32, proposal_hash: String, status: String, chain_id: String) -> String;
+}
+
+impl HashVote for Vote {
+    fn hash_vote(voter: i32, block_id: i64, proposal_id: i32, proposal_hash: String, status: String, chain_id: String) -> String {
+        let content: JsonValue = object!{
+            "voter" => voter,
+            "block_id" => block_id,
+            "proposal_id" => proposal_id,
+            "proposal_hash" => proposal_hash,
+            "status" => status,
+            "chain_id" => chain_id
+        };
+        Hasher::calculate_sha256( content.dump() )
+    }
+}
+
+/*
+@name CreateVote
+@desc sign a response to a proposal with this node's identity
+*/
+pub trait CreateVote {
+    fn create_vote(proposal: Proposal, status: ProposalStatus) -> Option<Vote>;
+}
+
+impl CreateVote for Vote {
+    fn create_vote(proposal: Proposal, status: ProposalStatus) -> Option<Vote> {
+        let identity: Identity = match Identity::read_identity() {
+            Some(identity) => identity,
+            None => {
+                println!("create_vote ERROR: no node identity to sign the vote with");
+                return None
+            }
+        };
+        let vote_status: String = String::from( Proposal::string_from_status(status) );
+        let vote_hash: String = Self::hash_vote(identity.identity_id,
+                                                proposal.proposal_block.block_id,
+                                                proposal.proposal_id,
+                                                proposal.proposal_hash.clone(),
+                                                vote_status.clone(),
+                                                DB::read_chain_id().unwrap_or(String::from("")));
+        match Keystore::sign(vote_hash.as_bytes()) {
+            Ok(signature) => {
+                Some(Vote {
+                    vote_voter: identity.identity_id,
+                    vote_block_id: proposal.proposal_block.block_id,
+                    vote_proposal_id: proposal.proposal_id,
+                    vote_proposal_hash: proposal.proposal_hash,
+                    vote_status: vote_status,
+                    vote_signature: signature
+                })
+            },
+            Err(err) => {
+                println!("create_vote ERROR: could not sign vote: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/*
+@name VerifySignedMessage
+@desc verify the signature of a vote or proposal against the
+      registered key of its author
+*/
+pub trait VerifySignedMessage {
+    fn verify_vote(vote: Vote) -> bool;
+    fn verify_proposal(proposal: Proposal) -> bool;
+}
+
+impl VerifySignedMessage for Evidence {
+    fn verify_vote(vote: Vote) -> bool {
+        match Validator::get_validator_at(vote.vote_block_id, vote.vote_voter) {
+            Some(validator) => {
+                let vote_hash: String = Vote::hash_vote(vote.vote_voter,
+                                                        vote.vote_block_id,
+                                                        vote.vote_proposal_id,
+                                                        vote.vote_proposal_hash.clone(),
+                                                        vote.vote_status.clone(),
+                                                        DB::read_chain_id().unwrap_or(String::from("")));
+                let digital_signature: DigitalSignature = DigitalSignature {
+                    signature_type: validator.validator_key_type.clone(),
+                    format_type: SignatureFormat::BYTES
+                };
+                Signature::verify_with_key(digital_signature,
+                                           vote.vote_signature,
+                                           vote_hash.as_bytes(),
+                                           validator.validator_public_key).is_ok()
+            },
+            None => {
+                println!("verify_vote, no registered validator for voter: {}", vote.vote_voter);
+                false
+            }
+        }
+    }
+
+    /*
+    @name verify_proposal
+    @desc the proposal hash must cover its block, so a signature
+          over the hash binds the creator to the block id
+    */
+    fn verify_proposal(proposal: Proposal) -> bool {
+        let block_hash: String = Block::hash_block(proposal.proposal_block.block_id,
+                                                   proposal.proposal_block.block_parent_hash.clone(),
+                                                   proposal.proposal_block.block_time.clone(),
+                                                   proposal.proposal_block.transactions.clone(),
+                                                   proposal.proposal_block.block_state_root.clone());
+        let proposal_hash: String = Proposal::hash_proposal(proposal.proposal_id,
+                                                            proposal.proposal_sender.clone(),
+                                                            proposal.proposal_creator,
+                                                            proposal.proposal_time.clone(),
+                                                            proposal.proposal_chain_id.clone(),
+                                                            proposal.proposal_block.block_hash.clone());
+        block_hash == proposal.proposal_block.block_hash
+            && proposal_hash == proposal.proposal_hash
+            && Proposal::verify_proposal_signature(proposal)
+    }
+}
+
+/*
+@name NewEvidence
+@desc build an evidence record from two conflicting messages
+*/
+pub trait NewEvidence {
+    fn new(evidence_type: EvidenceType,
+           offender: i32,
+           block_id: i64,
+           proposal_id: i32,
+           message_left: (String, JsonValue),
+           message_right: (String, JsonValue)) -> Evidence;
+}
+
+impl NewEvidence for Evidence {
+    /*
+    @name new
+    @desc messages are (message hash, message json) pairs. they are ordered
+          by hash, so both sides of the conflict produce the same record
+    */
+    fn new(evidence_type: EvidenceType,
+           offender: i32,
+           block_id: i64,
+           proposal_id: i32,
+           message_left: (String, JsonValue),
+           message_right: (String, JsonValue)) -> Evidence {
+        let (first, second) = if message_left.0 <= message_right.0 {
+            (message_left, message_right)
+        } else {
+            (message_right, message_left)
+        };
+        let raw_str: String = format!("{}{}{}{}{}{}",
+                                      Self::string_from_type(evidence_type.clone()),
+                                      offender,
+                                      block_id,
+                                      proposal_id,
+                                      first.0,
+                                      second.0);
+        Evidence {
+            evidence_hash: Hasher::calculate_sha256( raw_str ),
+            evidence_type: evidence_type,
+            evidence_offender: offender,
+            evidence_block_id: block_id,
+            evidence_proposal_id: proposal_id,
+            evidence_first: first.1.dump(),
+            evidence_second: second.1.dump(),
+            evidence_status: EvidenceStatus::Pending
+        }
+    }
+}
+
+/*
+@name DetectEquivocation
+@desc compare a received proposal or vote against the ones
+      this node has already seen for the same height
+*/
+pub trait DetectEquivocation {
+    fn check_proposal(proposal: Proposal) -> Option<Evidence>;
+    fn check_vote(vote: Vote) -> Option<Evidence>;
+}
+
+impl DetectEquivocation for Evidence {
+    fn check_proposal(proposal: Proposal) -> Option<Evidence> {
+        let local_proposals: Vec<Proposal> = Proposal::get_all_proposals()?;
+        for local_proposal in local_proposals {
+            if local_proposal.proposal_creator == proposal.proposal_creator
+                && local_proposal.proposal_id == proposal.proposal_id
+                && local_proposal.proposal_block.block_id == proposal.proposal_block.block_id
+                && local_proposal.proposal_hash != proposal.proposal_hash {
+                if Self::verify_proposal(local_proposal.clone()) && Self::verify_proposal(proposal.clone()) {
+                    println!("check_proposal, creator {} signed two proposals for block {}",
+                             proposal.proposal_creator,
+                             proposal.proposal_block.block_id);
+                    let local_proposal_json: JsonValue = json::parse( &Proposal::to_json(local_proposal.clone()) ).ok()?;
+                    let proposal_json: JsonValue = json::parse( &Proposal::to_json(proposal.clone()) ).ok()?;
+                    return Some(Self::new(EvidenceType::ConflictingProposals,
+                                          proposal.proposal_creator,
+                                          proposal.proposal_block.block_id,
+                                          proposal.proposal_id,
+                                          (local_proposal.proposal_hash.clone(), local_proposal_json),
+                                          (proposal.proposal_hash.clone(), proposal_json)))
+                }
+            }
+        }
+        None
+    }
+
+    /*
+    @name check_vote
+    @desc only accept votes are compared, a validator may reject
+          one proposal and accept another for the same height
+    */
+    fn check_vote(vote: Vote) -> Option<Evidence> {
+        if vote.vote_status != Proposal::string_from_status(ProposalStatus::Accepted) {
+            return None
+        }
+        if !Self::verify_vote(vote.clone()) {
+            println!("check_vote, vote signature from voter {} is not valid", vote.vote_voter);
+            return None
+        }
+        let mut vote_index: JsonValue = json::parse( &DB::read_vote_index()? ).ok()?;
+        let vote_key: String = format!("{}:{}:{}", vote.vote_block_id, vote.vote_proposal_id, vote.vote_voter);
+        let stored_vote_json: JsonValue = vote_index["votes"][vote_key.as_str()].clone();
+        match Vote::from_json(stored_vote_json.clone()) {
+            Ok(stored_vote) => {
+                if stored_vote.vote_proposal_hash != vote.vote_proposal_hash {
+                    println!("check_vote, voter {} accepted two proposals for block {}",
+                             vote.vote_voter,
+                             vote.vote_block_id);
+                    Some(Self::new(EvidenceType::ConflictingVotes,
+                                   vote.vote_voter,
+                                   vote.vote_block_id,
+                                   vote.vote_proposal_id,
+                                   (stored_vote.vote_proposal_hash.clone(), stored_vote_json),
+                                   (vote.vote_proposal_hash.clone(), Vote::to_json(vote.clone()))))
+                } else {
+                    None
+                }
+            },
+            Err(_) => {
+                vote_index["votes"][vote_key.as_str()] = Vote::to_json(vote);
+                match DB::write_vote_index(vote_index.dump()) {
+                    Ok(_) => {},
+                    Err(_) => println!("check_vote ERROR: could not write vote index")
+                }
+                None
+            }
+        }
+    }
+}
+
+/*
+@name VerifyEvidence
+@desc verify evidence received from a peer, before storing it
+*/
+pub trait VerifyEvidence {
+    fn verify_evidence(evidence: Evidence) -> bool;
+}
+
+impl VerifyEvidence for Evidence {
+    fn verify_evidence(evidence: Evidence) -> bool {
+        let first_json: JsonValue = match json::parse( &evidence.evidence_first ) {
+            Ok(parsed) => parsed,
+            Err(_) => return false
+        };
+        let second_json: JsonValue = match json::parse( &evidence.evidence_second ) {
+            Ok(parsed) => parsed,
+            Err(_) => return false
+        };
+        let rebuilt_evidence: Option<Evidence> = match evidence.evidence_type {
+            EvidenceType::ConflictingProposals => {
+                match (Proposal::from_json(first_json.clone()), Proposal::from_json(second_json.clone())) {
+                    (Ok(first), Ok(second)) => {
+                        let is_conflicting: bool = first.proposal_creator == second.proposal_creator
+                            && first.proposal_id == second.proposal_id
+                            && first.proposal_block.block_id == second.proposal_block.block_id
+                            && first.proposal_hash != second.proposal_hash;
+                        if is_conflicting && Self::verify_proposal(first.clone()) && Self::verify_proposal(second.clone()) {
+                            Some(Self::new(EvidenceType::ConflictingProposals,
+                                           first.proposal_creator,
+                                           first.proposal_block.block_id,
+                                           first.proposal_id,
+                                           (first.proposal_hash.clone(), first_json),
+                                           (second.proposal_hash.clone(), second_json)))
+                        } else {
+                            None
+                        }
+                    },
+                    _ => None
+                }
+            },
+            EvidenceType::ConflictingVotes => {
+                match (Vote::from_json(first_json.clone()), Vote::from_json(second_json.clone())) {
+                    (Ok(first), Ok(second)) => {
+                        let is_conflicting: bool = first.vote_voter == second.vote_voter
+                            && first.vote_block_id == second.vote_block_id
+                            && first.vote_proposal_id == second.vote_proposal_id
+                            && first.vote_status == second.vote_status
+                            && first.vote_proposal_hash != second.vote_proposal_hash;
+                        if is_conflicting && Self::verify_vote(first.clone()) && Self::verify_vote(second.clone()) {
+                            Some(Self::new(EvidenceType::ConflictingVotes,
+                                           first.vote_voter,
+                                           first.vote_block_id,
+                                           first.vote_proposal_id,
+                                           (first.vote_proposal_hash.clone(), first_json),
+                                           (second.vote_proposal_hash.clone(), second_json)))
+                        } else {
+                            None
+                        }
+                    },
+                    _ => None
+                }
+            },
+            _ => None
+        };
+        //the claimed offender, height and hash must match what the messages prove
+        match rebuilt_evidence {
+            Some(rebuilt) => {
+                rebuilt.evidence_hash == evidence.evidence_hash
+                    && rebuilt.evidence_offender == evidence.evidence_offender
+                    && rebuilt.evidence_block_id == evidence.evidence_block_id
+                    && rebuilt.evidence_proposal_id == evidence.evidence_proposal_id
+            },
+            None => false
+        }
+    }
+}
+
+/*
+@name StoreEvidence
+@desc persist evidence records in the evidence index, keyed by evidence hash
+*/
+pub trait StoreEvidence {
+    fn store_evidence(evidence: Evidence) -> Result<String, String>;
+    fn update_evidence_status(evidence: Evidence, status: EvidenceStatus) -> Result<String, String>;
+}
+
+impl StoreEvidence for Evidence {
+    /*
+    @name store_evidence
+    @desc store a new evidence record, records we already hold are left untouched
+    */
+    fn store_evidence(evidence: Evidence) -> Result<String, String> {
+        let evidence_index_string: String = match DB::read_evidence_index() {
+            Some(index) => index,
+            None => return Err(String::from("store_evidence ERROR: evidence index could not be read"))
+        };
+        let mut evidence_index: JsonValue = match json::parse( &evidence_index_string ) {
+            Ok(parsed) => parsed,
+            Err(_) => return Err(String::from("store_evidence ERROR: evidence index could not be parsed"))
+        };
+        if evidence_index["evidence"].has_key(evidence.evidence_hash.as_str()) {
+            return Err(String::from("store_evidence, evidence already stored"))
+        }
+        evidence_index["evidence"][evidence.evidence_hash.as_str()] = Self::to_json(evidence.clone());
+        match DB::write_evidence_index(evidence_index.dump()) {
+            Ok(_) => Ok(format!("store_evidence, Successfully stored evidence against {}", evidence.evidence_offender)),
+            Err(_) => Err(String::from("store_evidence ERROR: could not write evidence index"))
+        }
+    }
+
+    fn update_evidence_status(evidence: Evidence, status: EvidenceStatus) -> Result<String, String> {
+        let evidence_index_string: String = match DB::read_evidence_index() {
+            Some(index) => index,
+            None => return Err(String::from("update_evidence_status ERROR: evidence index could not be read"))
+        };
+        let mut evidence_index: JsonValue = match json::parse( &evidence_index_string ) {
+            Ok(parsed) => parsed,
+            Err(_) => return Err(String::from("update_evidence_status ERROR: evidence index could not be parsed"))
+        };
+        evidence_index["evidence"][evidence.evidence_hash.as_str()]["evidence_status"] = JsonValue::from( Self::string_from_status(status) );
+        match DB::write_evidence_index(evidence_index.dump()) {
+            Ok(_) => Ok(String::from("update_evidence_status, Successfully updated evidence")),
+            Err(_) => Err(String::from("update_evidence_status ERROR: could not write evidence index"))
+        }
+    }
+}
+
+/*
+@name ReadEvidence
+@desc
+*/
+pub trait ReadEvidence {
+    fn get_all_evidence() -> Vec<Evidence>;
+    fn get_pending_evidence() -> Vec<Evidence>;
+}
+
+impl ReadEvidence for Evidence {
+    fn get_all_evidence() -> Vec<Evidence> {
+        let mut all_evidence: Vec<Evidence> = Vec::new();
+        let evidence_index: Option<JsonValue> = match DB::read_evidence_index() {
+            Some(index) => json::parse( &index ).ok(),
+            None => None
+        };
+        match evidence_index {
+            Some(index) => {
+                for (_hash, evidence_json) in index["evidence"].entries() {
+                    match Self::from_json(evidence_json.clone()) {
+                        Ok(evidence) => all_evidence.push(evidence),
+                        Err(err) => println!("get_all_evidence ERROR: {}", err)
+                    }
+                }
+            },
+            None => {
+                println!("get_all_evidence ERROR: evidence index could not be read");
+            }
+        }
+        all_evidence.sort_by_key(|evidence| evidence.evidence_block_id);
+        all_evidence
+    }
+
+    fn get_pending_evidence() -> Vec<Evidence> {
+        Self::get_all_evidence().into_iter()
+                                .filter(|evidence| evidence.evidence_status == EvidenceStatus::Pending)
+                                .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Vote,
+                Evidence,
+                EvidenceType,
+                EvidenceStatus,
+                VoteToJson,
+                JsonToVote,
+                EvidenceToJson,
+                JsonToEvidence,
+                HashVote,
+                NewEvidence};
+
+    fn test_vote(proposal_hash: &str) -> Vote {
+        Vote {
+            vote_voter: 2,
+            vote_block_id: 4,
+            vote_proposal_id: 7,
+            vote_proposal_hash: String::from(proposal_hash),
+            vote_status: String::from("accepted"),
+            vote_signature: String::from("test signature")
+        }
+    }
+
+    #[test]
+    fn test_vote_json_round_trip() {
+        let vote: Vote = test_vote("hash a");
+        assert_eq!(Vote::from_json(Vote::to_json(vote.clone())).unwrap(), vote);
+    }
+
+    #[test]
+    fn test_vote_hash_covers_status() {
+        let chain_id: String = String::from("africaos-test");
+        let accepted: String = Vote::hash_vote(2, 4, 7, String::from("hash a"), String::from("accepted"), chain_id.clone());
+        let rejected: String = Vote::hash_vote(2, 4, 7, String::from("hash a"), String::from("rejected"), chain_id.clone());
+        let other_chain: String = Vote::hash_vote(2, 4, 7, String::from("hash a"), String::from("accepted"), String::from("africaos-other"));
+        assert_ne!(accepted, rejected);
+        assert_ne!(accepted, other_chain);
+    }
+
+    #[test]
+    fn test_vote_hash_fields_do_not_run_together() {
+        //voter 1 on block 23 and voter 12 on block 3 used to hash the same string
+        let chain_id: String = String::from("africaos-test");
+        let left: String = Vote::hash_vote(1, 23, 7, String::from("hash a"), String::from("accepted"), chain_id.clone());
+        let right: String = Vote::hash_vote(12, 3, 7, String::from("hash a"), String::from("accepted"), chain_id);
+        assert_ne!(left, right);
+    }
+
+    #[test]
+    fn test_evidence_is_independent_of_message_order() {
+        let vote_a: Vote = test_vote("hash a");
+        let vote_b: Vote = test_vote("hash b");
+        let evidence_ab: Evidence = Evidence::new(EvidenceType::ConflictingVotes, 2, 4, 7,
+                                                  (String::from("hash a"), Vote::to_json(vote_a.clone())),
+                                                  (String::from("hash b"), Vote::to_json(vote_b.clone())));
+        let evidence_ba: Evidence = Evidence::new(EvidenceType::ConflictingVotes, 2, 4, 7,
+                                                  (String::from("hash b"), Vote::to_json(vote_b)),
+                                                  (String::from("hash a"), Vote::to_json(vote_a)));
+        assert_eq!(evidence_ab, evidence_ba);
+        assert_eq!(evidence_ab.evidence_status, EvidenceStatus::Pending);
+    }
+
+    #[test]
+    fn test_evidence_json_round_trip() {
+        let evidence: Evidence = Evidence::new(EvidenceType::ConflictingVotes, 2, 4, 7,
+                                               (String::from("hash a"), Vote::to_json(test_vote("hash a"))),
+                                               (String::from("hash b"), Vote::to_json(test_vote("hash b"))));
+        let converted: Result<Evidence, String> = Evidence::from_json_string( Evidence::to_json(evidence.clone()).dump() );
+        assert_eq!(converted.unwrap(), evidence);
+    }
+}
@@ -22,36 +22,86 @@ use json::{JsonValue};
 use timestamp::{Timestamp};
 use macros::{transaction_output_logic,
              proposal_creator_election,
-             transaction_input_logic};
+             transaction_input_logic,
+             input_signing_content,
+             output_signing_content,
+             output_lock,
+             output_timelock,
+             output_unlocked,
+             input_authorization,
+             output_authorization,
+             spend_output,
+             transaction_fee_logic};
+pub use macros::{SPENT_OUTPUTS_KEY, OUTPUT_SENDERS_KEY};
+use amount::{Amount, StringToAmount, CheckedAmount};
 
 use signature::{DigitalSignature,
                 Verifier,
                 SignatureType,
                 SignatureFormat,
                 SignatureError,
+                SignatureTypeString,
                 Signature};
 
 use encode::{Encoder, RawBytesEncode, RawBytesDecode, Base64Decode, Base64Encode};
+use hash::{Hasher, CalculateSHA256Hash};
 
 
 pub struct Executor {}
 pub trait ExecuteMacro {
     /*
     @name execute_transaction_output_logic
-    @desc macro for tx output
+    @desc macro for tx output, the sender signs its fee, nonce and chain id.
+          The fee goes from the sender to the fee recipient, when there is one
     */
     fn execute_transaction_output_logic(state: JsonValue,
                                         transaction_timestamp: Timestamp,
                                         transaction_sender: String,
                                         transaction_hash: String,
-                                        transaction_data: String) -> JsonValue;
+                                        transaction_data: String,
+                                        transaction_nonce: i64,
+                                        transaction_chain_id: String,
+                                        transaction_fee: Amount,
+                                        fee_recipient: Option<String>) -> JsonValue;
 
 
+    /*
+    @name execute_transaction_input_logic
+    @desc macro for tx input, the fee, nonce and chain id are part of the signed content,
+          the block id and block time unlock timelocked outputs
+    */
     fn execute_transaction_input_logic(state: JsonValue,
                                        transaction_timestamp: Timestamp,
                                        transaction_sender: String,
                                        transaction_hash: String,
-                                       transaction_data: String) -> JsonValue;
+                                       transaction_data: String,
+                                       transaction_nonce: i64,
+                                       transaction_chain_id: String,
+                                       block_id: i64,
+                                       block_time: Timestamp,
+                                       transaction_fee: Amount,
+                                       fee_recipient: Option<String>) -> JsonValue;
+
+    /*
+    @name execute_input_signing_content
+    @desc macro for the content a receiver signs to claim an output
+    */
+    fn execute_input_signing_content(partner_hash: String,
+                                     recipient: String,
+                                     amount: Amount,
+                                     transaction_fee: Amount,
+                                     transaction_nonce: i64,
+                                     transaction_chain_id: String) -> String;
+
+    /*
+    @name execute_output_signing_content
+    @desc macro for the content a sender signs to make an output
+    */
+    fn execute_output_signing_content(output: String,
+                                      sender: String,
+                                      transaction_fee: Amount,
+                                      transaction_nonce: i64,
+                                      transaction_chain_id: String) -> String;
 
     /*
     @name execute_proposal_creator_election
@@ -67,26 +117,93 @@ impl ExecuteMacro for Executor {
                                         transaction_timestamp: Timestamp,
                                         transaction_sender: String,
                                         transaction_hash: String,
-                                        transaction_data: String) -> JsonValue {
-        transaction_output_logic!(state.clone(),
-                                  transaction_timestamp,
-                                  transaction_sender,
-                                  transaction_hash,
-                                  transaction_data)
+                                        transaction_data: String,
+                                        transaction_nonce: i64,
+                                        transaction_chain_id: String,
+                                        transaction_fee: Amount,
+                                        fee_recipient: Option<String>) -> JsonValue {
+        match fee_recipient {
+            Some(fee_recipient) => transaction_output_logic!(state.clone(),
+                                                             transaction_timestamp,
+                                                             transaction_sender,
+                                                             transaction_hash,
+                                                             transaction_data,
+                                                             transaction_nonce,
+                                                             transaction_chain_id,
+                                                             transaction_fee.value,
+                                                             fee_recipient),
+            None => transaction_output_logic!(state.clone(),
+                                              transaction_timestamp,
+                                              transaction_sender,
+                                              transaction_hash,
+                                              transaction_data,
+                                              transaction_nonce,
+                                              transaction_chain_id,
+                                              transaction_fee.value)
+        }
     }
 
     fn execute_transaction_input_logic(state: JsonValue,
                                        transaction_timestamp: Timestamp,
                                        transaction_sender: String,
                                        transaction_hash: String,
-                                       transaction_data: String) -> JsonValue{
+                                       transaction_data: String,
+                                       transaction_nonce: i64,
+                                       transaction_chain_id: String,
+                                       block_id: i64,
+                                       block_time: Timestamp,
+                                       transaction_fee: Amount,
+                                       fee_recipient: Option<String>) -> JsonValue{
+        let block_time: i64 = block_time.timestamp.parse::<i64>().unwrap_or(0);
+        match fee_recipient {
+            Some(fee_recipient) => transaction_input_logic!(state.clone(),
+                                                            transaction_timestamp,
+                                                            transaction_sender,
+                                                            transaction_hash,
+                                                            transaction_data,
+                                                            transaction_nonce,
+                                                            transaction_chain_id,
+                                                            block_id,
+                                                            block_time,
+                                                            transaction_fee.value,
+                                                            fee_recipient),
+            None => transaction_input_logic!(state.clone(),
+                                             transaction_timestamp,
+                                             transaction_sender,
+                                             transaction_hash,
+                                             transaction_data,
+                                             transaction_nonce,
+                                             transaction_chain_id,
+                                             block_id,
+                                             block_time,
+                                             transaction_fee.value)
+        }
+    }
 
-       transaction_input_logic!(state.clone(),
-                                transaction_timestamp,
-                                transaction_sender,
-                                transaction_hash,
-                                transaction_data)
+    fn execute_input_signing_content(partner_hash: String,
+                                     recipient: String,
+                                     amount: Amount,
+                                     transaction_fee: Amount,
+                                     transaction_nonce: i64,
+                                     transaction_chain_id: String) -> String {
+        input_signing_content!(partner_hash,
+                               recipient,
+                               amount.value,
+                               transaction_fee.value,
+                               transaction_nonce,
+                               transaction_chain_id)
+    }
 
+    fn execute_output_signing_content(output: String,
+                                      sender: String,
+                                      transaction_fee: Amount,
+                                      transaction_nonce: i64,
+                                      transaction_chain_id: String) -> String {
+        output_signing_content!(output,
+                                sender,
+                                transaction_fee.value,
+                                transaction_nonce,
+                                transaction_chain_id)
     }
 
     fn execute_proposal_creator_election(peer_length: usize,
@@ -0,0 +1,424 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
+use db::{DB, DBGenesis};
+use hash::{Hasher, CalculateSHA256Hash};
+use timestamp::{Timestamp, StringToTimestamp};
+use block::{Block,
+            GENESIS_PARENT_HASH,
+            DEFAULT_MAX_BLOCK_BYTES,
+            DEFAULT_MAX_BLOCK_TRANSACTIONS,
+            HashBlock,
+            ReadBlock,
+            InstallCheckpoint};
+use transaction::{State, StateRoot, DEFAULT_MIN_TRANSACTION_FEE};
+use amount::{Amount};
+use validator::{Validator,
+                JsonConverter,
+                WriteValidatorToDB};
+use std::fs;
+
+/*
+@desc how many hex characters of the genesis hash are appended
+      to the chain name to make the chain id
+*/
+pub const CHAIN_ID_HASH_LENGTH: usize = 16;
+
+/*
+@name ConsensusParams
+@desc consensus timing and block limits every node of a chain has to agree on
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct ConsensusParams {
+    pub block_window_length: i64,
+    pub proposal_iteration_delay: u32,
+    pub proposal_creation_delay: u32,
+    pub max_block_bytes: usize,
+    pub max_block_transactions: usize,
+    pub min_transaction_fee: Amount
+}
+
+/*
+@name Genesis
+@desc everything block 0 is built from. Two nodes share a chain
+      only if they started from the same genesis
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Genesis {
+    pub genesis_chain_id: String,
+    pub genesis_time: Timestamp,
+    pub genesis_validators: Vec<Validator>,
+    pub genesis_balances: Vec<(String, i64)>,
+    pub genesis_consensus: ConsensusParams
+}
+
+/*
+@name GenesisJson
+@desc
+*/
+pub trait GenesisJson {
+    fn to_json(genesis: Genesis) -> JsonValue;
+    fn from_json(payload: JsonValue) -> Result<Genesis, String>;
+    fn from_file(genesis_path: String) -> Result<Genesis, String>;
+}
+
+impl GenesisJson for Genesis {
+    fn to_json(genesis: Genesis) -> JsonValue {
+        let mut validators: JsonValue = JsonValue::new_array();
+        for validator in genesis.genesis_validators {
+            let _ = validators.push(Validator::to_json(validator));
+        }
+        let mut balances: JsonValue = object!{};
+        for (account, amount) in genesis.genesis_balances {
+            balances[account.as_str()] = JsonValue::from(amount);
+        }
+        object!{
+            "chain_id" => genesis.genesis_chain_id,
+            "genesis_time" => genesis.genesis_time.timestamp,
+            "validators" => validators,
+            "balances" => balances,
+            "consensus" => object!{
+                "block_window_length" => genesis.genesis_consensus.block_window_length,
+                "proposal_iteration_delay" => genesis.genesis_consensus.proposal_iteration_delay,
+                "proposal_creation_delay" => genesis.genesis_consensus.proposal_creation_delay,
+                "max_block_bytes" => genesis.genesis_consensus.max_block_bytes,
+                "max_block_transactions" => genesis.genesis_consensus.max_block_transactions,
+                "min_transaction_fee" => genesis.genesis_consensus.min_transaction_fee.value
+            }
+        }
+    }
+
+    fn from_json(payload: JsonValue) -> Result<Genesis, String> {
+        let chain_id: &str = match payload["chain_id"].as_str() {
+            Some(chain_id) if !chain_id.is_empty() => chain_id,
+            _ => return Err(String::from("Genesis, ERROR: from_json, genesis has no chain id"))
+        };
+        let genesis_time: Timestamp = match payload["genesis_time"].as_str().map(String::from).and_then(Timestamp::string_to_timestamp) {
+            Some(genesis_time) => genesis_time,
+            None => return Err(String::from("Genesis, ERROR: from_json, genesis time is not a timestamp"))
+        };
+        let validators: Vec<Validator> = Validator::validators_from_json(payload.clone())?;
+        if validators.is_empty() {
+            return Err(String::from("Genesis, ERROR: from_json, genesis has no validators"))
+        }
+        // every validator signs with its own key, from its node's /API/node/identity/
+        for (index, validator) in validators.iter().enumerate() {
+            if validator.validator_public_key.is_empty() {
+                return Err(format!("Genesis, ERROR: from_json, validator {} has no public key, its node prints it when started with identity=true", validator.validator_id))
+            }
+            if validators[..index].iter().any(|other| other.validator_public_key == validator.validator_public_key) {
+                return Err(format!("Genesis, ERROR: from_json, validator {} has the public key of another validator", validator.validator_id))
+            }
+        }
+        let mut balances: Vec<(String, i64)> = Vec::new();
+        for (account, amount) in payload["balances"].entries() {
+            // outputs are only signed from the hash of a public key, any other account could never be spent
+            if account.len() != 64 || !account.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) {
+                return Err(format!("Genesis, ERROR: from_json, balance account {} is not a public key hash, 64 lowercase hex characters", account))
+            }
+            match amount.as_i64() {
+                Some(amount) if amount >= 0 => balances.push((String::from(account), amount)),
+                _ => return Err(format!("Genesis, ERROR: from_json, balance of {} is not a positive integer", account))
+            }
+        }
+        balances.sort();
+        let consensus: &JsonValue = &payload["consensus"];
+        match (consensus["block_window_length"].as_i64(),
+               consensus["proposal_iteration_delay"].as_u32(),
+               consensus["proposal_creation_delay"].as_u32()) {
+            (Some(block_window_length), Some(proposal_iteration_delay), Some(proposal_creation_delay)) => {
+                Ok(Genesis {
+                    genesis_chain_id: String::from(chain_id),
+                    genesis_time: genesis_time,
+                    genesis_validators: validators,
+                    genesis_balances: balances,
+                    genesis_consensus: ConsensusParams {
+                        block_window_length: block_window_length,
+                        proposal_iteration_delay: proposal_iteration_delay,
+                        proposal_creation_delay: proposal_creation_delay,
+                        max_block_bytes: consensus["max_block_bytes"].as_usize().unwrap_or(DEFAULT_MAX_BLOCK_BYTES),
+                        max_block_transactions: consensus["max_block_transactions"].as_usize().unwrap_or(DEFAULT_MAX_BLOCK_TRANSACTIONS),
+                        min_transaction_fee: match consensus["min_transaction_fee"].as_u64() {
+                            Some(min_fee) => Amount { value: min_fee },
+                            None => DEFAULT_MIN_TRANSACTION_FEE
+                        }
+                    }
+                })
+            },
+            _ => Err(String::from("Genesis, ERROR: from_json, consensus parameters are missing"))
+        }
+    }
+
+    fn from_file(genesis_path: String) -> Result<Genesis, String> {
+        let genesis_string: String = match fs::read_to_string(genesis_path.clone()) {
+            Ok(genesis_string) => genesis_string,
+            Err(_) => return Err(format!("Genesis, ERROR: could not read genesis file {}", genesis_path))
+        };
+        match json::parse(&genesis_string) {
+            Ok(payload) => Self::from_json(payload),
+            Err(_) => Err(format!("Genesis, ERROR: genesis file {} is not valid JSON", genesis_path))
+        }
+    }
+}
+
+/*
+@name GenesisHash
+@desc
+*/
+pub trait GenesisHash {
+    fn genesis_hash(genesis: Genesis) -> String;
+    fn chain_id(genesis: Genesis) -> String;
+}
+
+impl GenesisHash for Genesis {
+    /*
+    @name genesis_hash
+    @desc hash of the canonical genesis, covering validators and parameters,
+          which block 0 alone does not
+    */
+    fn genesis_hash(genesis: Genesis) -> String {
+        Hasher::calculate_sha256( State::canonical_state(Self::to_json(genesis)) )
+    }
+
+    /*
+    @name chain_id
+    @desc the chain name followed by the start of the genesis hash, carried
+          by handshakes, transactions and proposals so messages from
+          another chain can be refused
+    */
+    fn chain_id(genesis: Genesis) -> String {
+        let genesis_hash: String = Self::genesis_hash(genesis.clone());
+        format!("{}-{}", genesis.genesis_chain_id, &genesis_hash[..CHAIN_ID_HASH_LENGTH])
+    }
+}
+
+/*
+@name GenesisBlock
+@desc
+*/
+pub trait GenesisBlock {
+    fn initial_state(genesis: Genesis) -> JsonValue;
+    fn genesis_block(genesis: Genesis) -> Block;
+}
+
+impl GenesisBlock for Genesis {
+    /*
+    @name initial_state
+    @desc account balances are stored as strings, like the transaction logic writes them
+    */
+    fn initial_state(genesis: Genesis) -> JsonValue {
+        let mut state: JsonValue = object!{};
+        for (account, amount) in genesis.genesis_balances {
+            state[account.as_str()] = JsonValue::from(format!("{}", amount));
+        }
+        state
+    }
+
+    /*
+    @name genesis_block
+    @desc block 0 has no transactions, no proposal, and the same hash on every node
+    */
+    fn genesis_block(genesis: Genesis) -> Block {
+        let state_root: String = State::state_root( Self::initial_state(genesis.clone()) );
+        Block {
+            block_id: 0,
+            block_hash: Block::hash_block(0,
+                                          String::from(GENESIS_PARENT_HASH),
+                                          genesis.genesis_time.clone(),
+                                          Vec::new(),
+                                          state_root.clone()),
+            block_parent_hash: String::from(GENESIS_PARENT_HASH),
+            block_time: genesis.genesis_time.clone(),
+            block_state_root: state_root,
+            proposal_hash: String::from(""),
+            block_data: Self::genesis_hash(genesis),
+            transactions: Vec::new()
+        }
+    }
+}
+
+/*
+@name InitGenesis
+@desc
+*/
+pub trait InitGenesis {
+    fn init_genesis(genesis: Genesis) -> Result<String, String>;
+}
+
+impl InitGenesis for Genesis {
+    /*
+    @name init_genesis
+    @desc register the genesis validators, and install block 0 on first start.
+          Storage created from another genesis is refused, not overwritten
+    */
+    fn init_genesis(genesis: Genesis) -> Result<String, String> {
+        let genesis_hash: String = Self::genesis_hash(genesis.clone());
+        match Self::local_genesis_hash() {
+            Some(local_genesis_hash) if local_genesis_hash != genesis_hash => {
+                return Err(format!("init_genesis ERROR: storage was created from genesis {}, not {}", local_genesis_hash, genesis_hash))
+            },
+            _ => {}
+        }
+        for validator in genesis.genesis_validators.clone() {
+            DB::write_validator(validator)?;
+        }
+        let genesis_block: Block = Self::genesis_block(genesis.clone());
+        match Block::get_block_by_block_id(0) {
+            Some(local_genesis_block) => {
+                if local_genesis_block.block_hash != genesis_block.block_hash {
+                    return Err(String::from("init_genesis ERROR: block 0 in storage does not match the genesis"))
+                }
+            },
+            None => {
+                Block::install_checkpoint(genesis_block.clone(), Self::initial_state(genesis.clone()))?;
+            }
+        }
+        let chain_id: String = Self::chain_id(genesis.clone());
+        let genesis_record: JsonValue = object!{
+            "genesis" => Self::to_json(genesis),
+            "genesis_hash" => genesis_hash.clone(),
+            "chain_id" => chain_id,
+            "genesis_block_hash" => genesis_block.block_hash
+        };
+        match DB::write_genesis(genesis_record.dump()) {
+            Ok(_) => Ok(genesis_hash),
+            Err(_) => Err(String::from("init_genesis ERROR: could not write genesis"))
+        }
+    }
+}
+
+/*
+@name ReadGenesis
+@desc
+*/
+pub trait ReadGenesis {
+    fn read_genesis() -> Option<Genesis>;
+    fn local_genesis_hash() -> Option<String>;
+    fn local_chain_id() -> Option<String>;
+    fn consensus_params() -> ConsensusParams;
+}
+
+impl ReadGenesis for Genesis {
+    fn read_genesis() -> Option<Genesis> {
+        let genesis_record: JsonValue = json::parse(&DB::read_genesis()?).ok()?;
+        Self::from_json(genesis_record["genesis"].clone()).ok()
+    }
+
+    fn local_genesis_hash() -> Option<String> {
+        let genesis_record: JsonValue = json::parse(&DB::read_genesis()?).ok()?;
+        genesis_record["genesis_hash"].as_str().map(String::from)
+    }
+
+    fn local_chain_id() -> Option<String> {
+        DB::read_chain_id()
+    }
+
+    /*
+    @name consensus_params
+    @desc the genesis parameters, or the former hardcoded ones before genesis is initialised
+    */
+    fn consensus_params() -> ConsensusParams {
+        match Self::read_genesis() {
+            Some(genesis) => genesis.genesis_consensus,
+            None => ConsensusParams {
+                block_window_length: 5,
+                proposal_iteration_delay: 5000,
+                proposal_creation_delay: 60000,
+                max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+                max_block_transactions: DEFAULT_MAX_BLOCK_TRANSACTIONS,
+                min_transaction_fee: DEFAULT_MIN_TRANSACTION_FEE
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Genesis,
+                GenesisJson,
+                GenesisHash,
+                GenesisBlock,
+                JsonValue,
+                CHAIN_ID_HASH_LENGTH};
+
+    fn test_genesis() -> Genesis {
+        let payload = json::parse(r#"{
+            "chain_id": "africaos-test",
+            "genesis_time": "1577836800",
+            "validators": [
+                {"validator_id": 1, "validator_location": "127.0.0.1:8081", "validator_public_key": "KEY1"}
+            ],
+            "balances": {"2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94": 1000,
+                         "7c8f1f6bd2bb2d4bd9a8ed1a0f5e3a0c44bb7f7e7bb2c2f27f8d2cbb2f3c84a1": 500},
+            "consensus": {"block_window_length": 5, "proposal_iteration_delay": 5000, "proposal_creation_delay": 60000}
+        }"#).unwrap();
+        Genesis::from_json(payload).unwrap()
+    }
+
+    #[test]
+    fn test_genesis_json_round_trip() {
+        let genesis: Genesis = test_genesis();
+        assert_eq!(Genesis::from_json(Genesis::to_json(genesis.clone())).unwrap(), genesis);
+        assert!(Genesis::from_json(object!{ "chain_id" => "africaos-test" }).is_err());
+
+        // validators cannot share a key, or go without one
+        let mut payload = Genesis::to_json(test_genesis());
+        let _ = payload["validators"].push(object!{ "validator_id" => 2, "validator_location" => "127.0.0.1:8082", "validator_public_key" => "KEY1" });
+        assert!(Genesis::from_json(payload.clone()).is_err());
+        payload["validators"][1]["validator_public_key"] = JsonValue::from("");
+        assert!(Genesis::from_json(payload.clone()).is_err());
+        payload["validators"][1]["validator_public_key"] = JsonValue::from("KEY2");
+        assert_eq!(Genesis::from_json(payload.clone()).unwrap().genesis_validators.len(), 2);
+
+        // balances belong to public key hashes, an account nobody can sign for is refused
+        payload["balances"]["127.0.0.1:8081"] = JsonValue::from(1000);
+        assert!(Genesis::from_json(payload.clone()).is_err());
+        payload["balances"].remove("127.0.0.1:8081");
+        payload["balances"]["2675E61EEC4AC990977EE70B7B27C22349597AD0381E6C7F02578278E3FDDD94"] = JsonValue::from(1000);
+        assert!(Genesis::from_json(payload).is_err());
+    }
+
+    #[test]
+    fn test_genesis_block_is_deterministic() {
+        let genesis: Genesis = test_genesis();
+        let genesis_block = Genesis::genesis_block(genesis.clone());
+        assert_eq!(genesis_block, Genesis::genesis_block(genesis.clone()));
+        assert_eq!(genesis_block.block_id, 0);
+
+        let mut richer: Genesis = genesis.clone();
+        richer.genesis_balances[0].1 = 2000;
+        assert_ne!(Genesis::genesis_block(richer).block_hash, genesis_block.block_hash);
+
+        let mut slower: Genesis = genesis.clone();
+        slower.genesis_consensus.proposal_creation_delay = 90000;
+        assert_eq!(Genesis::genesis_block(slower.clone()).block_hash, genesis_block.block_hash);
+        assert_ne!(Genesis::genesis_hash(slower), Genesis::genesis_hash(genesis));
+    }
+
+    #[test]
+    fn test_chain_id_follows_genesis_hash() {
+        let genesis: Genesis = test_genesis();
+        let chain_id: String = Genesis::chain_id(genesis.clone());
+        assert!(chain_id.starts_with("africaos-test-"));
+        assert_eq!(chain_id.len(), "africaos-test-".len() + CHAIN_ID_HASH_LENGTH);
+
+        let mut richer: Genesis = genesis.clone();
+        richer.genesis_balances[0].1 = 2000;
+        assert_ne!(Genesis::chain_id(richer), chain_id);
+    }
+}
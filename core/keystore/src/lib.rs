@@ -0,0 +1,338 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+#[macro_use]
+extern crate lazy_static;
+
+use json::{JsonValue};
+use std::sync::Mutex;
+use std::num::NonZeroU32;
+use ring::{aead, pbkdf2, rand::{SecureRandom, SystemRandom}};
+use db::{DB, DBKeystore};
+use encode::{Encoder, RawBytesEncode, RawBytesDecode};
+use timestamp::{Timestamp, NewTimestamp};
+use signature::{Signature,
+                SignatureType,
+                PrivateKey,
+                PublicKey,
+                Signer,
+                KeyGenerator,
+                SignatureTypeString};
+
+/*
+@desc KEYSTORE_PASSPHRASE_ENV names the environment variable the node reads its passphrase from
+*/
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "AOS_KEYSTORE_PASSPHRASE";
+
+/*
+@desc KEYSTORE_KDF_ITERATIONS is the PBKDF2-HMAC-SHA256 work factor for new keys,
+      each key records its own so it can be raised later
+*/
+pub const KEYSTORE_KDF_ITERATIONS: u32 = 100_000;
+
+const KEYSTORE_KDF: &str = "pbkdf2-hmac-sha256";
+const KEYSTORE_CIPHER: &str = "chacha20-poly1305";
+const KEYSTORE_SALT_LEN: usize = 16;
+
+lazy_static! {
+    // the node key, once unlocked with the passphrase at start
+    static ref UNLOCKED_KEY: Mutex<Option<PrivateKey>> = Mutex::new(None);
+}
+
+/*
+@name Keystore
+@desc this node's private keys, encrypted at rest. The current key signs,
+      rotated keys are kept as retired so old signatures can be traced
+*/
+pub struct Keystore {}
+
+/*
+@name EncryptKey
+@desc seal a private key with a key derived from the passphrase.
+      The key type and public key are bound to the ciphertext
+*/
+pub trait EncryptKey {
+    fn encrypt_key(private_key: &PrivateKey, passphrase: &str) -> Result<JsonValue, String>;
+    fn decrypt_key(entry: &JsonValue, passphrase: &str) -> Result<PrivateKey, String>;
+    fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<aead::LessSafeKey, String>;
+}
+
+impl EncryptKey for Keystore {
+    fn encrypt_key(private_key: &PrivateKey, passphrase: &str) -> Result<JsonValue, String> {
+        let rng = SystemRandom::new();
+        let mut salt: [u8; KEYSTORE_SALT_LEN] = [0u8; KEYSTORE_SALT_LEN];
+        let mut nonce: [u8; aead::NONCE_LEN] = [0u8; aead::NONCE_LEN];
+        rng.fill(&mut salt).map_err(|_| String::from("encrypt_key ERROR: could not make a salt"))?;
+        rng.fill(&mut nonce).map_err(|_| String::from("encrypt_key ERROR: could not make a nonce"))?;
+
+        let public_key: PublicKey = Signature::public_key(private_key).map_err(|err| format!("encrypt_key ERROR: {:?}", err))?;
+        let key_type: &str = Signature::type_to_string(private_key.key_type.clone());
+        let public_key_base64: String = Signature::public_key_to_base64(&public_key).map_err(|err| format!("encrypt_key ERROR: {:?}", err))?;
+
+        let sealing_key: aead::LessSafeKey = Self::derive_key(passphrase, &salt, KEYSTORE_KDF_ITERATIONS)?;
+        let mut ciphertext: Vec<u8> = private_key.key_bytes.clone();
+        sealing_key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce),
+                                             aead::Aad::from(format!("{} {}", key_type, public_key_base64).as_bytes()),
+                                             &mut ciphertext)
+                   .map_err(|_| String::from("encrypt_key ERROR: could not seal the key"))?;
+
+        Ok(object!{
+            "key_type" => key_type,
+            "public_key" => public_key_base64,
+            "kdf" => KEYSTORE_KDF,
+            "kdf_iterations" => KEYSTORE_KDF_ITERATIONS,
+            "salt" => Encoder::bytes_to_base64(salt.to_vec())?,
+            "cipher" => KEYSTORE_CIPHER,
+            "nonce" => Encoder::bytes_to_base64(nonce.to_vec())?,
+            "ciphertext" => Encoder::bytes_to_base64(ciphertext)?,
+            "created" => Timestamp::new().map(|ts| ts.timestamp).unwrap_or(String::from("0"))
+        })
+    }
+
+    fn decrypt_key(entry: &JsonValue, passphrase: &str) -> Result<PrivateKey, String> {
+        if entry["kdf"].as_str() != Some(KEYSTORE_KDF) || entry["cipher"].as_str() != Some(KEYSTORE_CIPHER) {
+            return Err(String::from("decrypt_key ERROR: unknown kdf or cipher"))
+        }
+        let key_type_string: &str = entry["key_type"].as_str().unwrap_or("");
+        let key_type: SignatureType = match Signature::type_from_string(key_type_string) {
+            Some(key_type) => key_type,
+            None => return Err(format!("decrypt_key ERROR: unknown key type {}", key_type_string))
+        };
+        let public_key_base64: &str = entry["public_key"].as_str().unwrap_or("");
+        let iterations: u32 = entry["kdf_iterations"].as_u32().unwrap_or(0);
+        let salt: Vec<u8> = Encoder::base64_to_bytes(String::from(entry["salt"].as_str().unwrap_or("")))?;
+        let nonce: Vec<u8> = Encoder::base64_to_bytes(String::from(entry["nonce"].as_str().unwrap_or("")))?;
+        let mut ciphertext: Vec<u8> = Encoder::base64_to_bytes(String::from(entry["ciphertext"].as_str().unwrap_or("")))?;
+
+        let opening_key: aead::LessSafeKey = Self::derive_key(passphrase, &salt, iterations)?;
+        let nonce: aead::Nonce = aead::Nonce::try_assume_unique_for_key(&nonce)
+                                             .map_err(|_| String::from("decrypt_key ERROR: bad nonce"))?;
+        let key_bytes: Vec<u8> = opening_key.open_in_place(nonce,
+                                                           aead::Aad::from(format!("{} {}", key_type_string, public_key_base64).as_bytes()),
+                                                           &mut ciphertext)
+                                            .map_err(|_| String::from("decrypt_key ERROR: wrong passphrase, or the keystore was altered"))?
+                                            .to_vec();
+        Ok(PrivateKey {
+            key_type: key_type,
+            key_bytes: key_bytes
+        })
+    }
+
+    fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<aead::LessSafeKey, String> {
+        let iterations: NonZeroU32 = match NonZeroU32::new(iterations) {
+            Some(iterations) => iterations,
+            None => return Err(String::from("derive_key ERROR: no kdf iterations"))
+        };
+        let mut key: [u8; 32] = [0u8; 32];
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+        let unbound_key: aead::UnboundKey = aead::UnboundKey::new(&aead::CHACHA20_POLY1305, &key)
+                                                             .map_err(|_| String::from("derive_key ERROR: could not make a key"))?;
+        Ok(aead::LessSafeKey::new(unbound_key))
+    }
+}
+
+/*
+@name ManageKeystore
+@desc create, unlock and rotate the node key
+*/
+pub trait ManageKeystore {
+    fn generate_private_key() -> Result<PrivateKey, String>;
+    fn add_key(keystore: JsonValue, entry: JsonValue) -> JsonValue;
+    fn init_keystore(passphrase: &str, import_key: Option<PrivateKey>) -> Result<PublicKey, String>;
+    fn unlock(passphrase: &str) -> Result<PublicKey, String>;
+    fn rotate_key(passphrase: &str) -> Result<PublicKey, String>;
+    fn read_keystore() -> Option<JsonValue>;
+}
+
+impl ManageKeystore for Keystore {
+    /*
+    @name generate_private_key
+    @desc node keys are Ed25519, RSA keys can only be imported
+    */
+    fn generate_private_key() -> Result<PrivateKey, String> {
+        let rng = SystemRandom::new();
+        let pkcs8_bytes = ring::signature::Ed25519KeyPair::generate_pkcs8(&rng)
+                                                          .map_err(|_| String::from("generate_private_key ERROR: could not generate a key"))?;
+        Ok(PrivateKey {
+            key_type: SignatureType::ED25519,
+            key_bytes: pkcs8_bytes.as_ref().to_vec()
+        })
+    }
+
+    /*
+    @name add_key
+    @desc make entry the current key, the previous one is retired
+    */
+    fn add_key(keystore: JsonValue, entry: JsonValue) -> JsonValue {
+        let mut retired: JsonValue = if keystore["retired"].is_array() {
+            keystore["retired"].clone()
+        } else {
+            JsonValue::new_array()
+        };
+        if keystore["current"].is_object() {
+            let _ = retired.push(keystore["current"].clone());
+        }
+        object!{
+            "current" => entry,
+            "retired" => retired
+        }
+    }
+
+    /*
+    @name init_keystore
+    @desc on first start, store the imported key, or a new one, and unlock it
+    */
+    fn init_keystore(passphrase: &str, import_key: Option<PrivateKey>) -> Result<PublicKey, String> {
+        if Self::read_keystore().is_none() {
+            let private_key: PrivateKey = match import_key {
+                Some(private_key) => private_key,
+                None => Self::generate_private_key()?
+            };
+            let entry: JsonValue = Self::encrypt_key(&private_key, passphrase)?;
+            let keystore: JsonValue = Self::add_key(JsonValue::Null, entry);
+            DB::write_keystore(keystore.dump()).map_err(|_| String::from("init_keystore ERROR: could not write keystore"))?;
+            println!("Keystore, stored a new {} node key", Signature::type_to_string(private_key.key_type));
+        }
+        Self::unlock(passphrase)
+    }
+
+    fn unlock(passphrase: &str) -> Result<PublicKey, String> {
+        let keystore: JsonValue = match Self::read_keystore() {
+            Some(keystore) => keystore,
+            None => return Err(String::from("unlock ERROR: no keystore"))
+        };
+        let private_key: PrivateKey = Self::decrypt_key(&keystore["current"], passphrase)?;
+        let public_key: PublicKey = Signature::public_key(&private_key).map_err(|err| format!("unlock ERROR: {:?}", err))?;
+        match UNLOCKED_KEY.lock() {
+            Ok(mut unlocked) => *unlocked = Some(private_key),
+            Err(_) => return Err(String::from("unlock ERROR: keystore lock is poisoned"))
+        }
+        Ok(public_key)
+    }
+
+    /*
+    @name rotate_key
+    @desc replace the current key with a new one. Peers only accept the new
+          key once a validator change registers it
+    */
+    fn rotate_key(passphrase: &str) -> Result<PublicKey, String> {
+        let keystore: JsonValue = match Self::read_keystore() {
+            Some(keystore) => keystore,
+            None => return Err(String::from("rotate_key ERROR: no keystore"))
+        };
+        // only the holder of the passphrase rotates
+        Self::decrypt_key(&keystore["current"], passphrase)?;
+        let entry: JsonValue = Self::encrypt_key(&Self::generate_private_key()?, passphrase)?;
+        DB::write_keystore(Self::add_key(keystore, entry).dump()).map_err(|_| String::from("rotate_key ERROR: could not write keystore"))?;
+        println!("Keystore, rotated the node key");
+        Self::unlock(passphrase)
+    }
+
+    fn read_keystore() -> Option<JsonValue> {
+        json::parse(&DB::read_keystore()?).ok()
+    }
+}
+
+/*
+@name KeystoreSigner
+@desc sign with the unlocked node key
+*/
+pub trait KeystoreSigner {
+    fn sign(content: &[u8]) -> Result<String, String>;
+    fn key_type() -> Option<SignatureType>;
+    fn identity_json() -> Option<JsonValue>;
+}
+
+impl KeystoreSigner for Keystore {
+    fn sign(content: &[u8]) -> Result<String, String> {
+        match UNLOCKED_KEY.lock() {
+            Ok(unlocked) => match &*unlocked {
+                Some(private_key) => Signature::sign(private_key, content).map_err(|err| format!("Keystore sign ERROR: {:?}", err)),
+                None => Err(String::from("Keystore sign ERROR: the keystore is locked"))
+            },
+            Err(_) => Err(String::from("Keystore sign ERROR: keystore lock is poisoned"))
+        }
+    }
+
+    fn key_type() -> Option<SignatureType> {
+        let keystore: JsonValue = Self::read_keystore()?;
+        Signature::type_from_string(keystore["current"]["key_type"].as_str()?)
+    }
+
+    /*
+    @name identity_json
+    @desc the public half of the keystore: the current key, and the retired ones
+    */
+    fn identity_json() -> Option<JsonValue> {
+        let keystore: JsonValue = Self::read_keystore()?;
+        let public_entry = |entry: &JsonValue| -> JsonValue {
+            object!{
+                "key_type" => entry["key_type"].clone(),
+                "public_key" => entry["public_key"].clone(),
+                "created" => entry["created"].clone()
+            }
+        };
+        let mut retired: JsonValue = JsonValue::new_array();
+        for entry in keystore["retired"].members() {
+            let _ = retired.push(public_entry(entry));
+        }
+        Some(object!{
+            "current" => public_entry(&keystore["current"]),
+            "retired" => retired
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Keystore,
+                EncryptKey,
+                ManageKeystore,
+                JsonValue};
+    use signature::{Signature, SignatureType, PrivateKey, KeyGenerator};
+    use std::path::Path;
+
+    #[test]
+    fn test_encrypted_key_round_trip() {
+        let private_key: PrivateKey = Keystore::generate_private_key().unwrap();
+        let entry: JsonValue = Keystore::encrypt_key(&private_key, "correct horse").unwrap();
+        assert_eq!(entry["key_type"].as_str(), Some("ed25519"));
+        assert_eq!(Keystore::decrypt_key(&entry, "correct horse").unwrap(), private_key);
+        assert!(Keystore::decrypt_key(&entry, "wrong horse").is_err());
+
+        // the public key is bound to the ciphertext
+        let mut altered: JsonValue = entry.clone();
+        altered["public_key"] = JsonValue::from("AAAA");
+        assert!(Keystore::decrypt_key(&altered, "correct horse").is_err());
+
+        // imported RSA keys are kept as they are
+        let rsa_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let rsa_entry: JsonValue = Keystore::encrypt_key(&rsa_key, "correct horse").unwrap();
+        assert_eq!(Keystore::decrypt_key(&rsa_entry, "correct horse").unwrap(), rsa_key);
+    }
+
+    #[test]
+    fn test_rotation_retires_the_current_key() {
+        let first: JsonValue = object!{ "public_key" => "FIRST" };
+        let second: JsonValue = object!{ "public_key" => "SECOND" };
+        let keystore: JsonValue = Keystore::add_key(JsonValue::Null, first.clone());
+        assert_eq!(keystore["retired"].len(), 0);
+        let keystore: JsonValue = Keystore::add_key(keystore, second.clone());
+        assert_eq!(keystore["current"], second);
+        assert_eq!(keystore["retired"][0], first);
+    }
+}
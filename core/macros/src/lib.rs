@@ -28,6 +28,17 @@ use signature::{DigitalSignature,
 
 use encode::{Encoder, RawBytesEncode, RawBytesDecode, Base64Encode, Base64Decode};
 
+/*
+@desc SPENT_OUTPUTS_KEY is where the state records every claimed output, by
+      its hash, with the hash of the input that claimed it
+*/
+pub const SPENT_OUTPUTS_KEY: &str = "spent_outputs";
+
+/*
+@desc OUTPUT_SENDERS_KEY is where the state records the sender of every
+      output, by its hash. The sender pays the input that claims it
+*/
+pub const OUTPUT_SENDERS_KEY: &str = "output_senders";
 
 macro_rules! block_validation {
     () => {
@@ -56,6 +67,361 @@ macro_rules! proposal_creator_election {
     }
 }
 
+/*
+@name input_signing_content
+@description the content a receiver signs to claim an output: the output's
+             hash, the receiver, the amount, the fee, the input's nonce and the chain id.
+             Keys are inserted in this order, so the dump is canonical
+*/
+#[macro_export]
+macro_rules! input_signing_content {
+    ($partner_hash: expr, $recipient: expr, $amount: expr, $fee: expr, $nonce: expr, $chain_id: expr) => {
+        {
+            let content: JsonValue = object!{
+                "partner_hash" => format!("{}", $partner_hash),
+                "recipient" => format!("{}", $recipient),
+                "amount" => $amount,
+                "fee" => $fee,
+                "nonce" => $nonce,
+                "chain_id" => format!("{}", $chain_id)
+            };
+            content.dump()
+        }
+    }
+}
+
+/*
+@name output_signing_content
+@description the content a sender signs to make an output: the output's
+             data without the sender_ sections, the sender, the fee, the
+             output's nonce and the chain id. Keys are inserted in this order
+*/
+#[macro_export]
+macro_rules! output_signing_content {
+    ($output: expr, $sender: expr, $fee: expr, $nonce: expr, $chain_id: expr) => {
+        {
+            let content: JsonValue = object!{
+                "output" => format!("{}", $output),
+                "sender" => format!("{}", $sender),
+                "fee" => $fee,
+                "nonce" => $nonce,
+                "chain_id" => format!("{}", $chain_id)
+            };
+            content.dump()
+        }
+    }
+}
+
+/*
+@name output_lock
+@description who can claim an output, from its decoded data. Either a single
+             key, <pkhash> <amount>, or M of N keys, multisig <M> <pkhash,...> <amount>.
+             Trailing not_before_ options belong to output_timelock, and sender_
+             options to output_authorization. The amount must be above 0.
+             Returns the threshold, the public key hashes and the amount
+*/
+#[macro_export]
+macro_rules! output_lock {
+    ($output_data: expr) => {
+        {
+            let output_data: String = format!("{}", $output_data);
+            let sections: Vec<&str> = output_data.split(" ")
+                                                 .filter(|section| !section.starts_with("not_before_") && !section.starts_with("sender_"))
+                                                 .collect::<Vec<_>>();
+            let lock: Result<(usize, Vec<String>, Amount), String> = match sections.as_slice() {
+                ["multisig", threshold, public_key_hashes, amount] => {
+                    let public_key_hashes: Vec<String> = public_key_hashes.split(",").map(String::from).collect::<Vec<_>>();
+                    let mut distinct_hashes: Vec<String> = public_key_hashes.clone();
+                    distinct_hashes.sort();
+                    distinct_hashes.dedup();
+                    match threshold.parse::<usize>() {
+                        Ok(threshold) if threshold >= 1
+                                         && threshold <= public_key_hashes.len()
+                                         && distinct_hashes.len() == public_key_hashes.len()
+                                         && !public_key_hashes.contains(&String::new()) => {
+                            Amount::positive_amount(String::from(*amount)).map(|amount| (threshold, public_key_hashes, amount))
+                        },
+                        _ => Err(format!("multisig output needs a threshold between 1 and its {} distinct public key hashes", public_key_hashes.len()))
+                    }
+                },
+                [public_key_hash, amount] => Amount::positive_amount(String::from(*amount)).map(|amount| (1, vec![String::from(*public_key_hash)], amount)),
+                _ => Err(String::from("output is neither <pkhash> <amount> nor multisig <M> <pkhash,...> <amount>"))
+            };
+            lock
+        }
+    }
+}
+
+/*
+@name output_timelock
+@description when an output can be claimed, from the trailing options of its
+             decoded data, not_before_block=<block id> and not_before_time=<unix seconds>.
+             Returns the earliest block id and block time, None when not locked
+*/
+#[macro_export]
+macro_rules! output_timelock {
+    ($output_data: expr) => {
+        {
+            let output_data: String = format!("{}", $output_data);
+            let mut not_before_block: Option<i64> = None;
+            let mut not_before_time: Option<i64> = None;
+            let mut refusal: Option<String> = None;
+            for section in output_data.split(" ").filter(|section| section.starts_with("not_before_")) {
+                let option: Vec<&str> = section.splitn(2, "=").collect::<Vec<_>>();
+                match (option.as_slice(), option.get(1).map(|value| value.parse::<i64>())) {
+                    (["not_before_block", _], Some(Ok(block_id))) if not_before_block.is_none() => not_before_block = Some(block_id),
+                    (["not_before_time", _], Some(Ok(block_time))) if not_before_time.is_none() => not_before_time = Some(block_time),
+                    _ => {
+                        refusal = Some(format!("output lock {} is not not_before_block=<block id> or not_before_time=<unix seconds>", section));
+                        break
+                    }
+                }
+            }
+            let timelock: Result<(Option<i64>, Option<i64>), String> = match refusal {
+                Some(refusal) => Err(refusal),
+                None => Ok((not_before_block, not_before_time))
+            };
+            timelock
+        }
+    }
+}
+
+/*
+@name output_unlocked
+@description an output with a timelock can only be claimed in a block at or
+             after its not_before_block, with a block time at or after its not_before_time
+*/
+#[macro_export]
+macro_rules! output_unlocked {
+    ($output_timelock: expr, $block_id: expr, $block_time: expr) => {
+        {
+            let (not_before_block, not_before_time): (Option<i64>, Option<i64>) = $output_timelock;
+            let block_id: i64 = $block_id;
+            let block_time: i64 = $block_time;
+            match (not_before_block, not_before_time) {
+                (Some(not_before_block), _) if block_id < not_before_block => {
+                    Err(format!("output is locked until block {}, this is block {}", not_before_block, block_id))
+                },
+                (_, Some(not_before_time)) if block_time < not_before_time => {
+                    Err(format!("output is locked until time {}, this block is at {}", not_before_time, block_time))
+                },
+                _ => Ok(())
+            }
+        }
+    }
+}
+
+/*
+@name input_authorization
+@description an input claims an output with one signature per key, the
+             signatures, public keys and key types are comma separated and in
+             the same order. Every signature must verify over the content, from
+             a distinct key of the output, and there must be at least the
+             output's threshold of them. Returns the number of approvals
+*/
+#[macro_export]
+macro_rules! input_authorization {
+    ($output_lock: expr, $signatures: expr, $public_keys: expr, $key_types: expr, $content: expr) => {
+        {
+            let (threshold, public_key_hashes, _): (usize, Vec<String>, Amount) = $output_lock;
+            let signatures: Vec<String> = format!("{}", $signatures).split(",").map(String::from).collect::<Vec<_>>();
+            let public_keys: Vec<String> = format!("{}", $public_keys).split(",").map(String::from).collect::<Vec<_>>();
+            // the key types are optional, and RSA when left out
+            let key_types: Vec<Option<signature::SignatureType>> = match $key_types {
+                Some(key_types) => format!("{}", key_types).split(",").map(|key_type| Signature::type_from_string(key_type)).collect::<Vec<_>>(),
+                None => vec![Some(signature::SignatureType::RSA); public_keys.len()]
+            };
+            let content: String = $content;
+
+            if signatures.len() != public_keys.len() || key_types.len() != public_keys.len() {
+                Err(format!("{} signatures, {} public keys and {} key types do not pair up", signatures.len(), public_keys.len(), key_types.len()))
+            } else {
+                let mut approvals: Vec<String> = Vec::new();
+                let mut refusal: Option<String> = None;
+                for ((tx_signature, tx_public_key), tx_key_type) in signatures.iter().zip(public_keys.iter()).zip(key_types.iter()) {
+                    // the receiver proves it holds a key the output was sent to
+                    let public_key_hash: String = Hasher::calculate_sha256(tx_public_key.clone());
+                    if !public_key_hashes.contains(&public_key_hash) || approvals.contains(&public_key_hash) {
+                        refusal = Some(format!("public key {} is not one of the output's, or signed twice", public_key_hash));
+                        break
+                    }
+                    let digital_signature: DigitalSignature = match tx_key_type {
+                        Some(key_type) => signature::DigitalSignature {
+                            signature_type: key_type.clone(),
+                            format_type: signature::SignatureFormat::BYTES
+                        },
+                        None => {
+                            refusal = Some(String::from("unknown key type"));
+                            break
+                        }
+                    };
+                    match Signature::verify_with_key(digital_signature, tx_signature.clone(), content.as_bytes(), tx_public_key.clone()) {
+                        Ok(_) => approvals.push(public_key_hash),
+                        Err(_) => {
+                            refusal = Some(format!("signature of {} does not verify", public_key_hash));
+                            break
+                        }
+                    }
+                }
+                match refusal {
+                    Some(refusal) => Err(refusal),
+                    None if approvals.len() < threshold => Err(format!("{} of the {} approvals the output needs", approvals.len(), threshold)),
+                    None => Ok(approvals.len())
+                }
+            }
+        }
+    }
+}
+
+/*
+@name output_authorization
+@description an output is signed by its sender, with trailing options
+             sender_signature=<sig> sender_public_key=<pk> [sender_key_type=<type>].
+             The sender's account is the hash of its public key, and the signature
+             covers the output_signing_content. Returns the output without the sender_ options
+*/
+#[macro_export]
+macro_rules! output_authorization {
+    ($output_data: expr, $tx_sender: expr, $tx_fee: expr, $tx_nonce: expr, $chain_id: expr) => {
+        {
+            let output_data: String = format!("{}", $output_data);
+            let output: String = output_data.split(" ")
+                                            .filter(|section| !section.starts_with("sender_"))
+                                            .collect::<Vec<_>>()
+                                            .join(" ");
+            let mut sender_signature: Option<String> = None;
+            let mut sender_public_key: Option<String> = None;
+            let mut sender_key_type: Option<String> = None;
+            for section in output_data.split(" ").filter(|section| section.starts_with("sender_")) {
+                match section.splitn(2, "=").collect::<Vec<_>>().as_slice() {
+                    ["sender_signature", signature] => sender_signature = Some(String::from(*signature)),
+                    ["sender_public_key", public_key] => sender_public_key = Some(String::from(*public_key)),
+                    ["sender_key_type", key_type] => sender_key_type = Some(String::from(*key_type)),
+                    _ => {}
+                }
+            }
+            let authorization: Result<String, String> = match (sender_signature, sender_public_key) {
+                (Some(sender_signature), Some(sender_public_key)) => {
+                    let content: String = output_signing_content!(output.clone(), $tx_sender, $tx_fee, $tx_nonce, $chain_id);
+                    let sender_lock: (usize, Vec<String>, Amount) = (1, vec![format!("{}", $tx_sender)], Amount { value: 0 });
+                    input_authorization!(sender_lock, sender_signature, sender_public_key, sender_key_type, content).map(|_| output)
+                },
+                _ => Err(String::from("output is not signed by its sender"))
+            };
+            authorization
+        }
+    }
+}
+
+/*
+@name spend_output
+@description record an output as claimed by an input, so no other input can claim it
+*/
+#[macro_export]
+macro_rules! spend_output {
+    ($state: expr, $output_hash: expr, $input_hash: expr) => {
+        {
+            let mut spent_state: JsonValue = $state;
+            if !spent_state[$crate::SPENT_OUTPUTS_KEY].is_object() {
+                spent_state[$crate::SPENT_OUTPUTS_KEY] = object!{};
+            }
+            spent_state[$crate::SPENT_OUTPUTS_KEY][format!("{}", $output_hash).as_str()] = JsonValue::from(format!("{}", $input_hash));
+            spent_state
+        }
+    }
+}
+
+/*
+@name record_output_sender
+@description record the sender of an output, the account that pays the input claiming it
+*/
+#[macro_export]
+macro_rules! record_output_sender {
+    ($state: expr, $output_hash: expr, $tx_sender: expr) => {
+        {
+            let mut recorded_state: JsonValue = $state;
+            if !recorded_state[$crate::OUTPUT_SENDERS_KEY].is_object() {
+                recorded_state[$crate::OUTPUT_SENDERS_KEY] = object!{};
+            }
+            recorded_state[$crate::OUTPUT_SENDERS_KEY][format!("{}", $output_hash).as_str()] = JsonValue::from(format!("{}", $tx_sender));
+            recorded_state
+        }
+    }
+}
+
+/*
+@name transfer_amount
+@description move an amount from one account to another, with checked
+             arithmetic. The receiver may not have an account yet. Fails,
+             leaving the state to the caller, when the sender has no account,
+             does not have the amount, or the receiver's balance would overflow
+*/
+#[macro_export]
+macro_rules! transfer_amount {
+    ($state: expr, $from_account: expr, $to_account: expr, $amount: expr) => {
+        {
+            let mut transfer_state: JsonValue = $state;
+            let from_account: String = format!("{}", $from_account);
+            let to_account: String = format!("{}", $to_account);
+            let amount: Amount = $amount;
+            let from_balance: Result<Amount, String> = Amount::string_to_amount( transfer_state[ from_account.as_str() ].to_string() )
+                                                              .and_then(|balance| balance.subtract_amount(amount));
+            match from_balance {
+                Ok(from_balance) => {
+                    transfer_state[ from_account.as_str() ] = JsonValue::from( format!("{}", from_balance) );
+                    let to_balance: Result<Amount, String> = if transfer_state.has_key( to_account.as_str() ) {
+                        Amount::string_to_amount( transfer_state[ to_account.as_str() ].to_string() )
+                    } else {
+                        Ok(Amount { value: 0 })
+                    };
+                    match to_balance.and_then(|balance| balance.add_amount(amount)) {
+                        Ok(to_balance) => {
+                            transfer_state[ to_account.as_str() ] = JsonValue::from( format!("{}", to_balance) );
+                            Ok(transfer_state)
+                        },
+                        Err(err) => Err(format!("{} cannot receive {}: {}", to_account, amount, err))
+                    }
+                },
+                Err(err) => Err(format!("{} cannot pay {}: {}", from_account, amount, err))
+            }
+        }
+    }
+}
+
+/*
+@name transaction_fee_logic
+@description move the fee from the sender to the fee recipient, once the
+             transaction has executed. A transaction the executor refused
+             pays nothing. One whose fee is not a whole amount above 0, or
+             whose sender cannot pay it, is refused
+*/
+#[macro_export]
+macro_rules! transaction_fee_logic {
+    ($state_before: expr, $executed_state: expr, $tx_sender: expr, $tx_fee: expr, $fee_recipient: expr) => {
+        {
+            let state_before: JsonValue = $state_before;
+            let executed_state: JsonValue = $executed_state;
+
+            if executed_state == state_before {
+                executed_state
+            } else {
+                let fee_paid: Result<JsonValue, String> = Amount::positive_amount(format!("{}", $tx_fee))
+                                                                 .and_then(|fee| transfer_amount!(executed_state, $tx_sender, $fee_recipient, fee));
+                match fee_paid {
+                    Ok(fee_paid_state) => {
+                        println!("TX execute fee {} from {} to {}", $tx_fee, $tx_sender, $fee_recipient);
+                        fee_paid_state
+                    },
+                    Err(fee_error) => {
+                        println!("TX execute ERROR: the fee of {} is refused: {}", $tx_fee, fee_error);
+                        state_before
+                    }
+                }
+            }
+        }
+    }
+}
+
 /*
 @name transaction_output_logic
 @description
@@ -64,9 +430,10 @@ macro_rules! proposal_creator_election {
 macro_rules! transaction_output_logic {
     /*
         @pattern StateJson, self
-        default output, to be customized
+        default output, to be customized. The output is signed by its
+        sender, over its fee, nonce and chain id
     */
-    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr) => {
+    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr, $tx_nonce: expr, $chain_id: expr, $tx_fee: expr) => {
         {
             println!("TX execute TX Output BEFORE: {} : ", $state.clone() );
             let mut state_as_json: JsonValue = $state;
@@ -76,8 +443,25 @@ macro_rules! transaction_output_logic {
             //let new_tx_data: String = format!("{} {}", $tx_sender, $tx_data);
             let new_tx_data: String = format!("{}", $tx_data); // do not prepend
 
-            // insert a new account into the state db
-            if state_as_json.has_key( &(format!("{}", $tx_sender).to_string()) ) {
+            // the sender signs the output. A multisig or timelocked output must be claimable,
+            // and an output to a single key must send a positive amount. Other data is stored as it is
+            let output_check: Option<Result<(usize, Vec<String>, Amount), String>> = match Encoder::decode_base64(new_tx_data.clone()) {
+                Ok(decoded) => match output_authorization!(decoded, $tx_sender, $tx_fee, $tx_nonce, $chain_id) {
+                    Ok(output) if output.starts_with("multisig ") || output.contains(" not_before_") => {
+                        Some(output_timelock!(output).and_then(|_| output_lock!(output)))
+                    },
+                    Ok(output) if output.split(" ").count() == 2 => Some(output_lock!(output)),
+                    Ok(_) => None,
+                    Err(authorization_error) => Some(Err(authorization_error))
+                },
+                Err(_) => Some(Err(String::from("output is not base64")))
+            };
+
+            if let Some(Err(lock_error)) = output_check {
+                println!("TX execute TX Output ERROR: {}", lock_error);
+                $state
+            } else if state_as_json.has_key( &(format!("{}", $tx_sender).to_string()) ) {
+                // insert a new account into the state db
                 //state already has the account
 
                 // check if code stored at the tx hash
@@ -103,7 +487,7 @@ macro_rules! transaction_output_logic {
                                  //TODO: after we insert the initial state for the sender
                                  //current_state_buffer
                                  println!("TX execute TX Output AFTER: {} : ",  state_as_json.clone()  );
-                                 state_as_json
+                                 record_output_sender!(state_as_json, $tx_hash, $tx_sender)
                              },
                              Err(_) => {
                                  // error on inserting, return current state
@@ -140,7 +524,7 @@ macro_rules! transaction_output_logic {
                                       //TODO: after we insert the initial state for the sender
                                       //current_state_buffer
                                       println!("TX execute TX Output AFTER ELSE: {} : ",  state_as_json.clone()  );
-                                      state_as_json
+                                      record_output_sender!(state_as_json, $tx_hash, $tx_sender)
                                   },
                                   Err(_) => {
                                       // error on inserting, return current state
@@ -162,6 +546,18 @@ macro_rules! transaction_output_logic {
             }
 
         }
+    };
+
+    /*
+        @pattern StateJson, self, fee
+        the output, then the fee paid by the sender to the fee recipient
+    */
+    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr, $tx_nonce: expr, $chain_id: expr, $tx_fee: expr, $fee_recipient: expr) => {
+        {
+            let state_before_fee: JsonValue = $state;
+            let executed_state: JsonValue = transaction_output_logic!(state_before_fee.clone(), $tx_timestamp, $tx_sender.clone(), $tx_hash, $tx_data, $tx_nonce, $chain_id.clone(), $tx_fee);
+            transaction_fee_logic!(state_before_fee, executed_state, $tx_sender, $tx_fee, $fee_recipient)
+        }
     }
 }
 
@@ -173,7 +569,16 @@ macro_rules! transaction_output_logic {
 */
 #[macro_export]
 macro_rules! transaction_input_logic {
-    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr) => {
+    /*
+        @pattern StateJson, self
+        the receiver's public key must hash to the public key hash of the
+        output, and its signature must cover the input_signing_content.
+        A timelocked output is checked against the id and time of the block executing the input.
+        An output is claimed once, it is then in the spent outputs. The sender
+        recorded with the output pays the amount. The receiver pays the fee
+        out of the amount, so the fee cannot be more than the amount
+    */
+    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr, $tx_nonce: expr, $chain_id: expr, $block_id: expr, $block_time: expr, $tx_fee: expr) => {
         {
             println!("TX execute TX INput BEFORE: {} : ", $state.clone() );
 
@@ -188,16 +593,25 @@ macro_rules! transaction_input_logic {
                     println!("INPUT: decoded 1: {}", decoded);
 
 
+                    // a block from a peer is not checked by the mempool, so a short input is refused here
                     let tx_sections: Vec<&str> = decoded.split(" ").collect::<Vec<_>>();
-                    let partner_tx_sender: String = String::from(tx_sections[0]);
-                    let tx_partner_hash: String = String::from(tx_sections[1]);
-                    let tx_signature: String = String::from(tx_sections[2]);
-                    let tx_public_key: String = String::from(tx_sections[3]);
+                    let tx_partner_hash: String = String::from(tx_sections.get(0).cloned().unwrap_or(""));
+                    let tx_signatures: String = String::from(tx_sections.get(1).cloned().unwrap_or(""));
+                    let tx_public_keys: String = String::from(tx_sections.get(2).cloned().unwrap_or(""));
+                    let tx_key_types: Option<&str> = tx_sections.get(3).cloned();
 
-                    // id state has the sender key
-                    if state_as_json.has_key( &(format!("{}", tx_partner_hash).to_string()) ) {
+                    // the output's recorded sender pays, never an account named by the input
+                    let recorded_sender: Option<String> = state_as_json[$crate::OUTPUT_SENDERS_KEY][tx_partner_hash.as_str()].as_str().map(String::from);
 
-                        let partner_tx: Option<&str> = state_as_json[ tx_partner_hash ].as_str();
+                    if tx_sections.len() < 3 {
+                        println!("INPUT: ERROR input needs an output hash, a signature and a public key");
+                        $state
+                    } else if state_as_json[$crate::SPENT_OUTPUTS_KEY].has_key( tx_partner_hash.as_str() ) {
+                        println!("INPUT: ERROR output {} is already spent by {}", tx_partner_hash, state_as_json[$crate::SPENT_OUTPUTS_KEY][tx_partner_hash.as_str()]);
+                        $state
+                    } else if let (Some(partner_tx_sender), true) = (recorded_sender, state_as_json.has_key( &(format!("{}", tx_partner_hash).to_string()) )) {
+
+                        let partner_tx: Option<&str> = state_as_json[ tx_partner_hash.clone() ].as_str();
 
                         // if partner hash exists
                         if partner_tx.clone().is_some() {
@@ -205,218 +619,65 @@ macro_rules! transaction_input_logic {
                             println!("INPUT: partner tx: {}", partner_tx.unwrap());
 
                             //let b64_decoded_partner: Result<String, String> = Encoder::decode_base64($tx_data);
-                            let b64_decoded_partner: String = Encoder::decode_base64( String::from( partner_tx.clone().unwrap() ) ).unwrap();
+                            // an account or anything else in the state is not an output
+                            let b64_decoded_partner: String = Encoder::decode_base64( String::from( partner_tx.clone().unwrap() ) ).unwrap_or(String::new());
 
                             println!("INPUT: b64_decoded_partner: {}", b64_decoded_partner);
 
-                            let partner_tx_sections: Vec<&str> = b64_decoded_partner.split(" ").collect::<Vec<_>>();
                             // let partner_tx_sender: String = String::from(partner_tx_sections[0]);
-                            let public_key_hash_section: String = String::from(partner_tx_sections[0]);
-                            let partner_amount_section: String = String::from(partner_tx_sections[1]);
-
-
-                            //TODO: VERIFY RECEIVERS SUBMITTED HASH HASHES TO BE WHAT THE RECEIVER SUBMITTED
-                            //
-
-                            // TODO: check signature
-                            let digital_signature: DigitalSignature = signature::DigitalSignature {
-                                signature_type: signature::SignatureType::RSA,
-                                format_type: signature::SignatureFormat::DISK
-                            };
-
-                            // TODO: sign transaction has instead of static str
-                            let content: &str = "TEST";
-                            let signature_result: &str = tx_signature.as_str();
-                            let public_key_base64: &str = tx_public_key.as_str();
-
-                            //convert base64 public key to bytes
-                            match encode::Encoder::base64_to_bytes( String::from(public_key_base64) ) {
-                                Ok(public_key_bytes) => {
-
-                                    let verification_result: Result<String, signature::SignatureError> = Signature::verify(digital_signature, String::from(signature_result), content);
-
-                                    match verification_result {
-
-                                        Ok(result) => {
-
-                                            // if input sender has account
-                                            if state_as_json.has_key( &(format!("{}", $tx_sender.clone()).to_string()) ) {
-
-                                                // grab amount for sender
-                                                // parse
-                                                //let state_account_amount: Option<i32> = state_as_json[ $tx_sender.clone() ].as_i32();
-                                                let state_account_amount: &JsonValue = &state_as_json[ $tx_sender.clone() ];
-                                                let parsed_string_state_account_amount: String = state_account_amount.to_string();
-                                                println!("INPUT: parsed_string_state_account_amount: {}", parsed_string_state_account_amount);
-                                                let amount_i32_account: Result<i32, std::num::ParseIntError> = parsed_string_state_account_amount.parse::<i32>();
-
-                                                //parse partner amount
-                                                let partner_tx_amount_parse_result: Result<i32, std::num::ParseIntError> = partner_amount_section.parse::<i32>();
-
-                                                // parse partner sender amount
-                                                //let state_partner_amount: Option<i32> = state_as_json[ partner_tx_sender.clone() ].as_i32();
-
-                                                ////////////
-                                                let state_partner_amount: &JsonValue = &state_as_json[ partner_tx_sender.clone() ];
-                                                let parsed_string_state_partner_amount: String = state_partner_amount.to_string();
-                                                println!("INPUT: parsed_string_state_partner_amount: {}", parsed_string_state_partner_amount);
-                                                let amount_i32_partner: Result<i32, std::num::ParseIntError> = parsed_string_state_partner_amount.parse::<i32>();
-                                                ///////////
-
-
-                                                println!("input: {} {} {} {} {} {}",
-                                                                            state_as_json.dump(),
-                                                                            partner_tx_sender,
-                                                                            $tx_sender,
-                                                                            // state_account_amount.is_some(),
-                                                                            // partner_tx_amount_parse_result.is_ok(),
-                                                                            // state_partner_amount.is_some()
-                                                                            amount_i32_account.is_ok(),
-                                                                            partner_tx_amount_parse_result.is_ok(),
-                                                                            amount_i32_partner.is_ok()
-                                                                        );
-
-
-                                                // if state_account_amount.is_some()
-                                                //    & partner_tx_amount_parse_result.is_ok()
-                                                //    & state_partner_amount.is_some() {
-                                                if amount_i32_account.is_ok()
-                                                   & partner_tx_amount_parse_result.is_ok()
-                                                   & amount_i32_partner.is_ok() {
-
-                                                    // TODO: error handling on amount adjustments
-                                                    // update senders balance to what they had, plus the partner tx value
-                                                    // state_as_json[$tx_sender] = JsonValue::from( state_account_amount.unwrap() + partner_tx_amount_parse_result.clone().unwrap() );
-                                                    // state_as_json[partner_tx_sender] = JsonValue::from( state_partner_amount.unwrap() - partner_tx_amount_parse_result.unwrap() );
-
-                                                    println!("input: partner_tx_amount_parse_result: {} ", partner_tx_amount_parse_result.clone().unwrap());
-                                                    println!("input: amount_i32_account: {} ", amount_i32_account.clone().unwrap());
-                                                    println!("input: amount_i32_partner: {} ", amount_i32_partner.clone().unwrap());
-
-                                                    // balance check balance >= tx amt
-                                                    if( amount_i32_partner.clone().unwrap() < partner_tx_amount_parse_result.clone().unwrap() ){
-                                                        return $state;
-                                                    } else {}
-
-                                                    match &state_as_json.insert( &( format!("{}", $tx_sender ).to_string() ),
-                                                                                    //format!("{}", JsonValue::from( state_account_amount.unwrap() + partner_tx_amount_parse_result.clone().unwrap() ) ) ) {
-                                                                                    format!("{}", JsonValue::from( amount_i32_account.unwrap() + partner_tx_amount_parse_result.clone().unwrap() ) ) ) {
-                                                                                    //TODO: safe addition
-                                                         Ok(_) => {
-
-                                                             match &state_as_json.insert( &( format!("{}", partner_tx_sender ).to_string() ),
-                                                                                             //format!("{}", JsonValue::from( state_partner_amount.unwrap() - partner_tx_amount_parse_result.unwrap() ) ) ) {
-                                                                                             format!("{}", JsonValue::from( amount_i32_partner.unwrap() - partner_tx_amount_parse_result.unwrap() ) ) ) {
-                                                                                             //TODO: safe subtraction
-                                                                  Ok(_) => {
-                                                                      //TODO: after we insert the initial state for the sender
-                                                                      //current_state_buffer
-                                                                      println!("INPUT: TX execute TX INPUT AFTER: {} : ",  state_as_json.clone()  );
-                                                                      state_as_json
-                                                                  },
-                                                                  Err(_) => {
-                                                                      // error on inserting, return current state
-                                                                      println!("INPUT: TX execute ERROR: State::to_json is NOT okay: {} ", $state.clone() );
-                                                                      $state
-                                                                  }
-                                                             }
-
-                                                         },
-                                                         Err(_) => {
-                                                             // error on inserting, return current state
-                                                             println!("TX execute ERROR: State::to_json is NOT okay: {} ", $state.clone() );
-                                                             $state
-                                                         }
-                                                    }
-
-                                                }else{
-                                                    println!("INPUT: transaction_input_logic: first condition else");
-                                                    $state
-                                                }
-
-                                            }else{
-
-                                                // parse
-                                                //let state_account_amount: Option<i32> = state_as_json[$tx_sender.clone()].as_i32();
-
-                                                //parse partner amount
-                                                let partner_tx_amount_parse_result: Result<i32, std::num::ParseIntError> = partner_amount_section.parse::<i32>();
-
-                                                println!("INPUT: state: {} {} {}", state_as_json.dump(),
-                                                                            $tx_sender.clone(),
-                                                                            partner_tx_sender.clone());
-
-                                                // parse partner sender amount
-                                                let state_partner_amount: &JsonValue = &state_as_json[ partner_tx_sender.clone() ];
-
-                                                let parsed_string: String = state_partner_amount.to_string();
-
-                                                println!("INPUT: parsed_string: {}", parsed_string);
-
-                                                let amount_i32: Result<i32, std::num::ParseIntError> = parsed_string.parse::<i32>();
-
-                                                println!("TX SENDER DOESNT HAVE ACCOUNT: {} - {} - {} - {}", partner_tx_amount_parse_result.is_ok(),
-                                                                                                    state_partner_amount.dump(),
-                                                                                                    partner_tx_sender,
-                                                                                                    amount_i32.clone().unwrap());
-
-                                                // TODO: check if sender exists
-                                                if partner_tx_amount_parse_result.is_ok() & amount_i32.is_ok() {
-
-                                                    // update senders balance to the partner tx value
-                                                    // state_as_json[$tx_sender] = JsonValue::from( partner_tx_amount_parse_result.clone().unwrap() );
-                                                    // state_as_json[partner_tx_sender] = JsonValue::from( state_partner_amount.unwrap() - partner_tx_amount_parse_result.unwrap() );
-
-                                                    match &state_as_json.insert( &( format!("{}", $tx_sender ).to_string() ),
-                                                                                    format!("{}", JsonValue::from( partner_tx_amount_parse_result.clone().unwrap() ) ) ) {
-                                                         Ok(_) => {
-
-                                                             match &state_as_json.insert( &( format!("{}", partner_tx_sender ).to_string() ),
-                                                                                             format!("{}", JsonValue::from( amount_i32.unwrap() - partner_tx_amount_parse_result.unwrap() ) ) ) {
-                                                                  Ok(_) => {
-                                                                      //TODO: after we insert the initial state for the sender
-                                                                      //current_state_buffer
-                                                                      println!("INPUT: TX execute TX Input AFTER: {} : ",  state_as_json.clone()  );
-                                                                      state_as_json
-                                                                  },
-                                                                  Err(_) => {
-                                                                      // error on inserting, return current state
-                                                                      println!("INPUT: TX execute ERROR: State::to_json is NOT okay: {} ", $state.clone() );
-                                                                      $state
-                                                                  }
-                                                             }
-
-                                                         },
-                                                         Err(_) => {
-                                                             // error on inserting, return current state
-                                                             println!("INPUT: TX execute ERROR: State::to_json is NOT okay: {} ", $state.clone() );
-                                                             $state
-                                                         }
-                                                    }
-
-                                                }else{
-
-                                                    println!("INPUT: partner_amount_parse_result is not ok");
-                                                    $state
-
-                                                }
-
+                            let partner_lock: Result<(usize, Vec<String>, Amount), String> = output_lock!(b64_decoded_partner);
+                            let partner_timelock: Result<(Option<i64>, Option<i64>), String> = output_timelock!(b64_decoded_partner);
+
+                            if let (Ok(partner_lock), Ok(partner_timelock)) = (partner_lock, partner_timelock) {
+
+                                let partner_amount: Amount = partner_lock.2;
+
+                                let content: String = input_signing_content!(tx_partner_hash.clone(),
+                                                                             $tx_sender.clone(),
+                                                                             partner_amount.value,
+                                                                             $tx_fee,
+                                                                             $tx_nonce,
+                                                                             $chain_id.clone());
+                                let input_fee: Result<Amount, String> = Amount::string_to_amount(format!("{}", $tx_fee));
+                                let authorization_result: Result<usize, String> = match (output_unlocked!(partner_timelock, $block_id, $block_time), input_fee) {
+                                    (Ok(_), Ok(input_fee)) if input_fee.value <= partner_amount.value => input_authorization!(partner_lock,
+                                                                                                                               tx_signatures.clone(),
+                                                                                                                               tx_public_keys.clone(),
+                                                                                                                               tx_key_types,
+                                                                                                                               content),
+                                    (Ok(_), _) => Err(format!("the fee {} is not an amount up to the output's {}", $tx_fee, partner_amount)),
+                                    (Err(timelock_error), _) => Err(timelock_error)
+                                };
+
+                                match authorization_result {
+
+                                    Ok(approvals) => {
+                                        println!("INPUT: {} approvals", approvals);
+
+                                        // the output's sender pays its amount to the receiver, or the input is refused
+                                        match transfer_amount!(state_as_json.clone(), partner_tx_sender.clone(), $tx_sender.clone(), partner_amount) {
+                                            Ok(transferred_state) => {
+                                                println!("INPUT: TX execute TX INPUT AFTER: {} : ", transferred_state.clone());
+                                                spend_output!(transferred_state, tx_partner_hash, $tx_hash)
+                                            },
+                                            Err(transfer_error) => {
+                                                println!("INPUT: TX execute ERROR: {}", transfer_error);
+                                                $state
                                             }
-
-                                        },
-
-                                        Err(_) => {
-                                            println!("INPUT: SIGNATURE VERIFICATION FAILED");
-                                            $state
                                         }
 
+                                    },
+
+                                    Err(authorization_error) => {
+                                        println!("INPUT: SIGNATURE VERIFICATION FAILED: {}", authorization_error);
+                                        $state
                                     }
 
-                                },
-                                Err(error_message) => {
-                                    println!("INPUT: ERROR: {}", error_message);
-                                    $state
                                 }
+
+                            } else {
+                                println!("INPUT: ERROR partner tx is not an output, or its lock is malformed");
+                                $state
                             }
 
                         } else {
@@ -428,7 +689,7 @@ macro_rules! transaction_input_logic {
 
 
                     }else{
-                        println!("INPUT: ERROR tx_partner_hash doesnt exist in state");
+                        println!("INPUT: ERROR tx_partner_hash doesnt exist in state, or is not a recorded output");
                         $state
                     }
                     //////////////////////
@@ -440,6 +701,18 @@ macro_rules! transaction_input_logic {
                 }
             }
         }
+    };
+
+    /*
+        @pattern StateJson, self, fee
+        the input, then the fee paid by the sender to the fee recipient
+    */
+    ($state: expr, $tx_timestamp: expr, $tx_sender: expr, $tx_hash: expr, $tx_data: expr, $tx_nonce: expr, $chain_id: expr, $block_id: expr, $block_time: expr, $tx_fee: expr, $fee_recipient: expr) => {
+        {
+            let state_before_fee: JsonValue = $state;
+            let executed_state: JsonValue = transaction_input_logic!(state_before_fee.clone(), $tx_timestamp, $tx_sender.clone(), $tx_hash, $tx_data, $tx_nonce, $chain_id, $block_id, $block_time, $tx_fee);
+            transaction_fee_logic!(state_before_fee, executed_state, $tx_sender, $tx_fee, $fee_recipient)
+        }
     }
 }
 
@@ -459,15 +732,44 @@ mod tests {
                     SignatureType,
                     SignatureFormat,
                     SignatureError,
+                    SignatureTypeString,
+                    KeyGenerator,
+                    HDKeyDerivation,
+                    PrivateKey,
+                    Signer,
                     Signature};
+    use std::path::Path;
+    use hash::{Hasher, CalculateSHA256Hash};
+    use amount::{Amount, StringToAmount, CheckedAmount};
+
+    // a key derived for the tests, so no key file is needed
+    fn test_key(index: u32) -> PrivateKey {
+        Signature::derive_key(&[9u8; 64], 0, index).unwrap()
+    }
+
+    // the account of a key is its public key hash
+    fn test_account(private_key: &PrivateKey) -> String {
+        Hasher::calculate_sha256(Signature::public_key_to_base64(&Signature::public_key(private_key).unwrap()).unwrap())
+    }
+
+    // the output, signed by the key's account for nonce 0 on the test chain,
+    // returns the sender and the output data
+    fn signed_output(private_key: &PrivateKey, output: &str, fee: i64) -> (String, String) {
+        let public_key: String = Signature::public_key_to_base64(&Signature::public_key(private_key).unwrap()).unwrap();
+        let sender: String = test_account(private_key);
+        let content: String = output_signing_content!(output, sender.clone(), fee, 0, "africaos-test");
+        let signature: String = Signature::sign(private_key, content.as_bytes()).unwrap();
+        let output_data: String = format!("{} sender_signature={} sender_public_key={} sender_key_type={}",
+                                          output, signature, public_key, Signature::type_to_string(private_key.key_type.clone()));
+        (sender, Encoder::encode_base64(output_data).unwrap())
+    }
 
     #[test]
     fn test_transaction_output_public_key_new_account() -> (){
 
         let test_timestamp: Option<Timestamp> = Timestamp::new();
-        let test_sender: String = String::from("test_sender");
         let test_hash_string: String = String::from("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
-        let test_tx_data_string: String = format!("{}", "TESTDATA");
+        let (test_sender, test_tx_data_string): (String, String) = signed_output(&test_key(0), "TESTDATA", 0);
 
 
         // female transaction will hold the public key hash of the recipient
@@ -480,6 +782,7 @@ mod tests {
             test_sender.as_str() => "0",
             //test_hash_string.as_str() => format!("{} {}", test_sender, test_tx_data_string.clone()),
             test_hash_string.as_str() => format!("{}", test_tx_data_string.clone()),
+            "output_senders" => object!{ test_hash_string.as_str() => test_sender.as_str() },
         };
 
 
@@ -487,7 +790,10 @@ mod tests {
                                                            test_timestamp.unwrap(),
                                                            test_sender,
                                                            test_hash_string,
-                                                           test_tx_data_string );
+                                                           test_tx_data_string,
+                                                           0,
+                                                           "africaos-test",
+                                                           0 );
         assert_eq!(test_json_2, result);
 
     }
@@ -496,10 +802,8 @@ mod tests {
     fn test_transaction_output_public_key_existing_account() -> (){
 
         let test_timestamp: Option<Timestamp> = Timestamp::new();
-        let test_tx_string: String = String::from("TXTESTHASH");
-        let test_sender: String = String::from("test_sender");
         let test_hash_string: String = String::from("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
-        let test_tx_data_string: String = format!("{}", test_hash_string);
+        let (test_sender, test_tx_data_string): (String, String) = signed_output(&test_key(0), &format!("{} 10", test_hash_string), 1);
 
 
         // female transaction will hold the public key hash of the recipient
@@ -511,16 +815,58 @@ mod tests {
             test_sender.as_str() => "100",
             //test_hash_string.as_str() => format!("{} {}", test_sender, test_tx_data_string.clone()),
             test_hash_string.as_str() => format!("{}", test_tx_data_string.clone()),
+            "output_senders" => object!{ test_hash_string.as_str() => test_sender.as_str() },
         };
 
 
         let result: JsonValue = transaction_output_logic!( test_state_json.clone(),
-                                                           test_timestamp.unwrap(),
-                                                           test_sender,
-                                                           test_hash_string,
-                                                           test_tx_data_string );
+                                                           test_timestamp.clone().unwrap(),
+                                                           test_sender.clone(),
+                                                           test_hash_string.clone(),
+                                                           test_tx_data_string.clone(),
+                                                           0,
+                                                           "africaos-test",
+                                                           1 );
         assert_eq!(test_json_2, result);
 
+        // the fee is paid by the sender who signed it
+        let fee_paid: JsonValue = transaction_output_logic!( test_state_json.clone(),
+                                                             test_timestamp.clone().unwrap(),
+                                                             test_sender.clone(),
+                                                             test_hash_string.clone(),
+                                                             test_tx_data_string.clone(),
+                                                             0,
+                                                             "africaos-test",
+                                                             1,
+                                                             "proposer" );
+        assert_eq!(fee_paid[ test_sender.as_str() ], "99");
+        assert_eq!(fee_paid["proposer"], "1");
+
+        // an output is refused when it names another sender, has another fee, nonce or chain,
+        // or is not signed at all
+        let refused = |sender: &str, tx_data: String, nonce: i64, chain_id: &str, fee: i64| -> JsonValue {
+            transaction_output_logic!( test_state_json.clone(),
+                                       test_timestamp.clone().unwrap(),
+                                       sender,
+                                       test_hash_string.clone(),
+                                       tx_data,
+                                       nonce,
+                                       chain_id,
+                                       fee,
+                                       "proposer" )
+        };
+        assert_eq!(refused("victim", test_tx_data_string.clone(), 0, "africaos-test", 1), test_state_json);
+        assert_eq!(refused(&test_sender, test_tx_data_string.clone(), 0, "africaos-test", 50), test_state_json);
+        assert_eq!(refused(&test_sender, test_tx_data_string.clone(), 1, "africaos-test", 1), test_state_json);
+        assert_eq!(refused(&test_sender, test_tx_data_string.clone(), 0, "another-chain", 1), test_state_json);
+        assert_eq!(refused(&test_sender, Encoder::encode_base64(format!("{} 10", test_hash_string)).unwrap(), 0, "africaos-test", 1), test_state_json);
+
+        // and a fee that is not above 0 is refused, it is not free
+        let (_, unpaid_data): (String, String) = signed_output(&test_key(0), &format!("{} 10", test_hash_string), 0);
+        assert_eq!(refused(&test_sender, unpaid_data, 0, "africaos-test", 0), test_state_json);
+        let (_, negative_data): (String, String) = signed_output(&test_key(0), &format!("{} 10", test_hash_string), -5);
+        assert_eq!(refused(&test_sender, negative_data, 0, "africaos-test", -5), test_state_json);
+
     }
 
     #[test]
@@ -530,7 +876,7 @@ mod tests {
         let test_partner_sender: String = String::from("alice");
         let test_sender: String = String::from("test_sender");
         let test_tx_hash_string: String = String::from("TESTTXHASH");
-        let test_pubkhash_string: String = String::from("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
+        let test_pubkhash_string: String = String::from("2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
         let test_amount: i32 = 10;
 
         let test_out_tx_data_string: String = format!("{} {}", test_pubkhash_string, test_amount);
@@ -538,14 +884,15 @@ mod tests {
 
         let test_out_tx_data_string: String = Encoder::encode_base64( test_out_tx_data_string.clone() ).unwrap();
 
-        // the signature of the transaction
-        let test_bob_signature: String = String::from("opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==");
+        // bob signs the input_signing_content of the output hash, the receiver, 10, fee 0, nonce 0 and the chain id
+        let test_bob_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let test_bob_content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let test_bob_signature: String = Signature::sign(&test_bob_key, test_bob_content.as_bytes()).unwrap();
 
         //the public key to check the signature, and to hash
         let test_bob_public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
 
-        let test_in_tx_data_string2: String = format!("{} {} {} {}", test_partner_sender,
-                                                                    test_tx_hash_string,
+        let test_in_tx_data_string2: String = format!("{} {} {}", test_tx_hash_string,
                                                                     test_bob_signature,
                                                                     test_bob_public_key_base64);
 
@@ -559,22 +906,31 @@ mod tests {
         let test_state_json: JsonValue = object!{
             //test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
             test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
-            test_partner_sender.as_str() => "100"
+            test_partner_sender.as_str() => "100",
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
         };
 
         let test_json_2: JsonValue = object!{
             // test_tx_hash_string.as_str() => format!("{} {}", test_sender, test_out_tx_data_string.clone()),
             test_tx_hash_string.as_str() => format!("{}", test_out_tx_data_string.clone()),
             test_partner_sender.as_str() => "90",
-            test_sender.as_str() => "10"
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+            test_sender.as_str() => "10",
+            "spent_outputs" => object!{ test_tx_hash_string.as_str() => test_tx_hash_string.as_str() }
         };
 
-        let result: JsonValue = transaction_input_logic!( test_state_json.clone(),
-                                                          test_timestamp.unwrap(),
-                                                          test_sender,
-                                                          test_tx_hash_string,
-                                                          test_in_tx_data_string
-                                                      );
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( test_state_json.clone(),
+                                      test_timestamp.unwrap(),
+                                      test_sender,
+                                      test_tx_hash_string,
+                                      test_in_tx_data_string,
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
         assert_eq!(test_json_2, result);
 
     }
@@ -586,7 +942,7 @@ mod tests {
         let test_partner_sender: String = String::from("alice");
         let test_sender: String = String::from("test_sender");
         let test_tx_hash_string: String = String::from("TESTTXHASH");
-        let test_pubkhash_string: String = String::from("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
+        let test_pubkhash_string: String = String::from("2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
         let test_amount: i32 = 10;
 
         let test_out_tx_data_string: String = format!("{} {}", test_pubkhash_string, test_amount);
@@ -594,14 +950,15 @@ mod tests {
 
         let test_out_tx_data_string: String = Encoder::encode_base64( test_out_tx_data_string.clone() ).unwrap();
 
-        // the signature of the transaction
-        let test_bob_signature: String = String::from("opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==");
+        // bob signs the input_signing_content of the output hash, the receiver, 10, fee 0, nonce 0 and the chain id
+        let test_bob_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let test_bob_content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let test_bob_signature: String = Signature::sign(&test_bob_key, test_bob_content.as_bytes()).unwrap();
 
         //the public key to check the signature, and to hash
         let test_bob_public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
 
-        let test_in_tx_data_string2: String = format!("{} {} {} {}", test_partner_sender,
-                                                                    test_tx_hash_string,
+        let test_in_tx_data_string2: String = format!("{} {} {}", test_tx_hash_string,
                                                                     test_bob_signature,
                                                                     test_bob_public_key_base64);
 
@@ -616,6 +973,7 @@ mod tests {
             //test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
             test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
             test_partner_sender.as_str() => "90",
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
             test_sender.as_str() => "10",
         };
 
@@ -623,16 +981,434 @@ mod tests {
             // test_tx_hash_string.as_str() => format!("{} {}", test_sender, test_out_tx_data_string.clone()),
             test_tx_hash_string.as_str() => format!("{}", test_out_tx_data_string.clone()),
             test_partner_sender.as_str() => "80",
-            test_sender.as_str() => "20"
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+            test_sender.as_str() => "20",
+            "spent_outputs" => object!{ test_tx_hash_string.as_str() => test_tx_hash_string.as_str() }
         };
 
-        let result: JsonValue = transaction_input_logic!( test_state_json.clone(),
-                                                          test_timestamp.unwrap(),
-                                                          test_sender,
-                                                          test_tx_hash_string,
-                                                          test_in_tx_data_string
-                                                      );
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( test_state_json.clone(),
+                                      test_timestamp.unwrap(),
+                                      test_sender,
+                                      test_tx_hash_string,
+                                      test_in_tx_data_string,
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
         assert_eq!(test_json_2, result);
 
+        // the output is spent, and an account is not an output
+        let claim_again = |tx_data: String| -> JsonValue {
+            transaction_input_logic!( result.clone(),
+                                      Timestamp::new().unwrap(),
+                                      test_sender.clone(),
+                                      String::from("TESTTXHASH2"),
+                                      tx_data,
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        };
+        assert_eq!(claim_again(Encoder::encode_base64(test_in_tx_data_string2.clone()).unwrap()), result);
+        assert_eq!(claim_again(Encoder::encode_base64(format!("{} {} {}", test_partner_sender,
+                                                                             test_bob_signature,
+                                                                             test_bob_public_key_base64)).unwrap()), result);
+        // a short input is refused, not a panic
+        assert_eq!(claim_again(Encoder::encode_base64(format!("TESTTXHASH {}", test_bob_signature)).unwrap()), result);
+        assert_eq!(claim_again(String::new()), result);
+
+    }
+
+    #[test]
+    fn test_transaction_input_logic_refuses_unbound_signature() -> (){
+
+        let test_timestamp: Option<Timestamp> = Timestamp::new();
+        let test_partner_sender: String = String::from("alice");
+        let test_sender: String = String::from("test_sender");
+        let test_tx_hash_string: String = String::from("TESTTXHASH");
+        let test_bob_public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
+        let test_bob_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let test_bob_content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let test_bob_signature: &str = &Signature::sign(&test_bob_key, test_bob_content.as_bytes()).unwrap();
+        // the old constant signature over "TEST"
+        let test_test_signature: &str = "opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==";
+
+        let input_data = |signature: &str| -> String {
+            Encoder::encode_base64(format!("{} {} {}", test_tx_hash_string, signature, test_bob_public_key_base64)).unwrap()
+        };
+        let state_with_output = |public_key_hash: &str| -> JsonValue {
+            object!{
+                test_tx_hash_string.as_str() => Encoder::encode_base64(format!("{} {}", public_key_hash, 10)).unwrap(),
+                "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+                test_partner_sender.as_str() => "100"
+            }
+        };
+        let bob_public_key_hash: &str = "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94";
+
+        // a signature over anything but the input_signing_content is refused
+        let state: JsonValue = state_with_output(bob_public_key_hash);
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( state.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data(test_test_signature),
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(state, result);
+
+        // so is a replay of the signature under another nonce or chain
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( state.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data(test_bob_signature),
+                                      1,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(state, result);
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( state.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data(test_bob_signature),
+                                      0,
+                                      String::from("another-chain"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(state, result);
+
+        // and a valid signature from a key the output was not sent to
+        let state: JsonValue = state_with_output("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( state.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data(test_bob_signature),
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(state, result);
+
+        // and an output with no recorded sender, nobody pays it
+        let mut state: JsonValue = state_with_output(bob_public_key_hash);
+        state.remove("output_senders");
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( state.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data(test_bob_signature),
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(state, result);
     }
+
+    #[test]
+    fn test_transaction_input_logic_ed25519() -> (){
+
+        let test_timestamp: Option<Timestamp> = Timestamp::new();
+        let test_partner_sender: String = String::from("alice");
+        let test_sender: String = String::from("test_sender");
+        let test_tx_hash_string: String = String::from("TESTTXHASH");
+
+        let test_private_key: PrivateKey = test_key(1);
+        let test_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&test_private_key).unwrap()).unwrap();
+        let test_out_tx_data_string: String = Encoder::encode_base64(format!("{} {}", Hasher::calculate_sha256(test_public_key.clone()), 10)).unwrap();
+        let content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let test_signature: String = Signature::sign(&test_private_key, content.as_bytes()).unwrap();
+
+        let input_data = |key_type: &str| -> String {
+            Encoder::encode_base64(format!("{} {} {} {}", test_tx_hash_string, test_signature, test_public_key, key_type)).unwrap()
+        };
+        let test_state_json: JsonValue = object!{
+            test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
+            test_partner_sender.as_str() => "100",
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+        };
+
+        // read as an RSA key, the signature does not hold
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( test_state_json.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data("rsa"),
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(test_state_json, result);
+
+        let test_json_2: JsonValue = object!{
+            test_tx_hash_string.as_str() => test_out_tx_data_string.clone(),
+            test_partner_sender.as_str() => "90",
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+            test_sender.as_str() => "10",
+            "spent_outputs" => object!{ test_tx_hash_string.as_str() => test_tx_hash_string.as_str() }
+        };
+        let result: JsonValue = (|| -> JsonValue {
+            transaction_input_logic!( test_state_json.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data("ed25519"),
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        })();
+        assert_eq!(test_json_2, result);
+    }
+
+    #[test]
+    fn test_transaction_multisig_output_and_input() -> (){
+
+        let test_timestamp: Option<Timestamp> = Timestamp::new();
+        let test_partner_sender: String = test_account(&test_key(0));
+        let test_sender: String = String::from("test_sender");
+        let test_tx_hash_string: String = String::from("TESTTXHASH");
+
+        let rsa_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let ed25519_key: PrivateKey = test_key(1);
+        let rsa_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&rsa_key).unwrap()).unwrap();
+        let ed25519_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&ed25519_key).unwrap()).unwrap();
+        let public_key_hashes: String = format!("{},{},{}",
+                                                Hasher::calculate_sha256(rsa_public_key.clone()),
+                                                Hasher::calculate_sha256(ed25519_public_key.clone()),
+                                                Hasher::calculate_sha256(String::from("THIRDKEY")));
+
+        // an output nobody could claim is refused
+        let unclaimable_output: String = signed_output(&test_key(0), &format!("multisig 4 {} 10", public_key_hashes), 0).1;
+        let output_state: JsonValue = object!{ test_partner_sender.as_str() => "100" };
+        let result: JsonValue = transaction_output_logic!( output_state.clone(),
+                                                           test_timestamp.clone().unwrap(),
+                                                           test_partner_sender.clone(),
+                                                           test_tx_hash_string.clone(),
+                                                           unclaimable_output,
+                                                           0,
+                                                           "africaos-test",
+                                                           0 );
+        assert_eq!(output_state, result);
+
+        // 2 of 3
+        let test_out_tx_data_string: String = signed_output(&test_key(0), &format!("multisig 2 {} 10", public_key_hashes), 0).1;
+        let result: JsonValue = transaction_output_logic!( output_state.clone(),
+                                                           test_timestamp.clone().unwrap(),
+                                                           test_partner_sender.clone(),
+                                                           test_tx_hash_string.clone(),
+                                                           test_out_tx_data_string.clone(),
+                                                           0,
+                                                           "africaos-test",
+                                                           0 );
+        assert_eq!(result[ test_tx_hash_string.as_str() ], test_out_tx_data_string.as_str());
+
+        let content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let rsa_signature: String = Signature::sign(&rsa_key, content.as_bytes()).unwrap();
+        let ed25519_signature: String = Signature::sign(&ed25519_key, content.as_bytes()).unwrap();
+        let input_data = |signatures: &str, public_keys: &str, key_types: &str| -> String {
+            Encoder::encode_base64(format!("{} {} {} {}", test_tx_hash_string, signatures, public_keys, key_types)).unwrap()
+        };
+        let execute_input = |tx_data: String| -> JsonValue {
+            transaction_input_logic!( result.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      tx_data,
+                                      0,
+                                      String::from("africaos-test"),
+                                      1,
+                                      0,
+                                      0 )
+        };
+
+        // one approval is not enough, and a key cannot approve twice
+        assert_eq!(execute_input(input_data(&rsa_signature, &rsa_public_key, "rsa")), result);
+        assert_eq!(execute_input(input_data(&format!("{},{}", rsa_signature, rsa_signature),
+                                            &format!("{},{}", rsa_public_key, rsa_public_key),
+                                            "rsa,rsa")), result);
+
+        let claimed: JsonValue = execute_input(input_data(&format!("{},{}", rsa_signature, ed25519_signature),
+                                                          &format!("{},{}", rsa_public_key, ed25519_public_key),
+                                                          "rsa,ed25519"));
+        assert_eq!(claimed[ test_partner_sender.as_str() ], "90");
+        assert_eq!(claimed[ test_sender.as_str() ], "10");
+    }
+
+    #[test]
+    fn test_transaction_timelocked_output() -> (){
+
+        let test_timestamp: Option<Timestamp> = Timestamp::new();
+        let test_partner_sender: String = test_account(&test_key(0));
+        let test_sender: String = String::from("test_sender");
+        let test_tx_hash_string: String = String::from("TESTTXHASH");
+        let bob_public_key_hash: &str = "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94";
+
+        let rsa_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let rsa_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&rsa_key).unwrap()).unwrap();
+        let content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let rsa_signature: String = Signature::sign(&rsa_key, content.as_bytes()).unwrap();
+
+        // a lock that is not a block id or a unix time is refused
+        let output_state: JsonValue = object!{ test_partner_sender.as_str() => "100" };
+        let result: JsonValue = transaction_output_logic!( output_state.clone(),
+                                                           test_timestamp.clone().unwrap(),
+                                                           test_partner_sender.clone(),
+                                                           test_tx_hash_string.clone(),
+                                                           signed_output(&test_key(0), &format!("{} 10 not_before_block=soon", bob_public_key_hash), 0).1,
+                                                           0,
+                                                           "africaos-test",
+                                                           0 );
+        assert_eq!(output_state, result);
+
+        let test_out_tx_data_string: String = signed_output(&test_key(0), &format!("{} 10 not_before_block=5 not_before_time=1000", bob_public_key_hash), 0).1;
+        let result: JsonValue = transaction_output_logic!( output_state.clone(),
+                                                           test_timestamp.clone().unwrap(),
+                                                           test_partner_sender.clone(),
+                                                           test_tx_hash_string.clone(),
+                                                           test_out_tx_data_string.clone(),
+                                                           0,
+                                                           "africaos-test",
+                                                           0 );
+        assert_eq!(result[ test_tx_hash_string.as_str() ], test_out_tx_data_string.as_str());
+
+        let input_data: String = Encoder::encode_base64(format!("{} {} {}", test_tx_hash_string, rsa_signature, rsa_public_key)).unwrap();
+        let execute_input = |block_id: i64, block_time: i64| -> JsonValue {
+            transaction_input_logic!( result.clone(),
+                                      test_timestamp.clone().unwrap(),
+                                      test_sender.clone(),
+                                      test_tx_hash_string.clone(),
+                                      input_data.clone(),
+                                      0,
+                                      String::from("africaos-test"),
+                                      block_id,
+                                      block_time,
+                                      0 )
+        };
+
+        // too early by block, or by time
+        assert_eq!(execute_input(4, 1000), result);
+        assert_eq!(execute_input(5, 999), result);
+
+        let claimed: JsonValue = execute_input(5, 1000);
+        assert_eq!(claimed[ test_partner_sender.as_str() ], "90");
+        assert_eq!(claimed[ test_sender.as_str() ], "10");
+    }
+
+    #[test]
+    fn test_transaction_amounts_are_checked() -> (){
+
+        let test_timestamp: Option<Timestamp> = Timestamp::new();
+        let test_partner_sender: String = test_account(&test_key(0));
+        let test_sender: String = String::from("test_sender");
+        let test_tx_hash_string: String = String::from("TESTTXHASH");
+        let bob_public_key_hash: &str = "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94";
+
+        // an output must send a whole amount above 0
+        let output_state: JsonValue = object!{ test_partner_sender.as_str() => "100" };
+        for amount in ["-5", "0", "1.5", "18446744073709551616"].iter() {
+            let result: JsonValue = transaction_output_logic!( output_state.clone(),
+                                                               test_timestamp.clone().unwrap(),
+                                                               test_partner_sender.clone(),
+                                                               test_tx_hash_string.clone(),
+                                                               signed_output(&test_key(0), &format!("{} {}", bob_public_key_hash, amount), 0).1,
+                                                               0,
+                                                               "africaos-test",
+                                                               0 );
+            assert_eq!(output_state, result);
+        }
+
+        // bob's signature over the input of 10, nonce 0
+        let rsa_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let rsa_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&rsa_key).unwrap()).unwrap();
+        let content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 0, 0, "africaos-test");
+        let rsa_signature: String = Signature::sign(&rsa_key, content.as_bytes()).unwrap();
+        let input_data: String = Encoder::encode_base64(format!("{} {} {}", test_tx_hash_string, rsa_signature, rsa_public_key)).unwrap();
+        let execute_input = |partner_balance: &str, sender_balance: &str| -> JsonValue {
+            let state: JsonValue = object!{
+                test_tx_hash_string.as_str() => Encoder::encode_base64(format!("{} 10", bob_public_key_hash)).unwrap(),
+                "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+                test_partner_sender.as_str() => partner_balance,
+                test_sender.as_str() => sender_balance
+            };
+            let result: JsonValue = transaction_input_logic!( state.clone(),
+                                                              test_timestamp.clone().unwrap(),
+                                                              test_sender.clone(),
+                                                              test_tx_hash_string.clone(),
+                                                              input_data.clone(),
+                                                              0,
+                                                              String::from("africaos-test"),
+                                                              1,
+                                                              0,
+                                                              0 );
+            if result == state { object!{} } else { result }
+        };
+
+        // the partner cannot pay, or the receiver's balance would overflow
+        assert_eq!(execute_input("5", "0"), object!{});
+        assert_eq!(execute_input("100", &format!("{}", u64::max_value())), object!{});
+        // balances past i32 are fine
+        let claimed: JsonValue = execute_input("5000000000", "4294967296");
+        assert_eq!(claimed[ test_partner_sender.as_str() ], "4999999990");
+        assert_eq!(claimed[ test_sender.as_str() ], "4294967306");
+
+        // the receiver pays the fee out of the amount, so it cannot be more than the amount
+        let costly_content: String = input_signing_content!(test_tx_hash_string.clone(), test_sender.clone(), 10, 11, 0, "africaos-test");
+        let costly_signature: String = Signature::sign(&rsa_key, costly_content.as_bytes()).unwrap();
+        let costly_state: JsonValue = object!{
+            test_tx_hash_string.as_str() => Encoder::encode_base64(format!("{} 10", bob_public_key_hash)).unwrap(),
+            "output_senders" => object!{ test_tx_hash_string.as_str() => test_partner_sender.as_str() },
+            test_partner_sender.as_str() => "100"
+        };
+        let result: JsonValue = transaction_input_logic!( costly_state.clone(),
+                                                          test_timestamp.clone().unwrap(),
+                                                          test_sender.clone(),
+                                                          test_tx_hash_string.clone(),
+                                                          Encoder::encode_base64(format!("{} {} {}", test_tx_hash_string, costly_signature, rsa_public_key)).unwrap(),
+                                                          0,
+                                                          String::from("africaos-test"),
+                                                          1,
+                                                          0,
+                                                          11 );
+        assert_eq!(result, costly_state);
+
+        // the fee is checked the same way, and must be above 0
+        let state_before: JsonValue = object!{ "alice" => "100" };
+        let executed_state: JsonValue = object!{ "alice" => "100", "OUTPUT" => "data" };
+        let fee_paid: JsonValue = transaction_fee_logic!(state_before.clone(), executed_state.clone(), "alice", 3, "proposer");
+        assert_eq!(fee_paid["alice"], "97");
+        assert_eq!(fee_paid["proposer"], "3");
+        assert_eq!(transaction_fee_logic!(state_before.clone(), executed_state.clone(), "alice", 101, "proposer"), state_before);
+        let rich_proposer: JsonValue = object!{ "alice" => "100", "proposer" => format!("{}", u64::max_value()), "OUTPUT" => "data" };
+        assert_eq!(transaction_fee_logic!(state_before.clone(), rich_proposer, "alice", 3, "proposer"), state_before);
+        assert_eq!(transaction_fee_logic!(state_before.clone(), executed_state.clone(), "alice", 0, "proposer"), state_before);
+        assert_eq!(transaction_fee_logic!(state_before.clone(), executed_state.clone(), "alice", -3, "proposer"), state_before);
+    }
+
 }
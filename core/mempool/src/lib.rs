@@ -0,0 +1,487 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+extern crate json;
+use json::{JsonValue};
+use db::{DB, DBStateManager, DBGenesis};
+use block::{ReadBlockFromDB};
+use amount::{Amount};
+use transaction::{Transaction,
+                  TransactionType,
+                  HashTransaction,
+                  DEFAULT_MIN_TRANSACTION_FEE,
+                  State,
+                  StateRoot,
+                  ReadTransactionFromDB,
+                  WriteTransactionToDB,
+                  ExecuteTransactions,
+                  AccountNonce,
+                  SpentOutputs,
+                  RemoveTransactions};
+use timestamp::{Timestamp, NewTimestamp};
+use encode::{Encoder, Base64Decode};
+
+/*
+@desc the most transactions the pool holds, in total and per sender
+*/
+pub const MEMPOOL_MAX_TRANSACTIONS: usize = 1000;
+pub const MEMPOOL_MAX_TRANSACTIONS_PER_SENDER: usize = 25;
+
+/*
+@desc seconds a transaction may wait in the pool before it is evicted
+*/
+pub const MEMPOOL_TRANSACTION_TTL: i64 = 3600;
+
+/*
+@name Mempool
+@desc the pool of submitted transactions waiting for a block. Only
+      transactions that would execute on the current state are admitted
+*/
+pub struct Mempool {}
+
+/*
+@name OrderTransactions
+@desc
+*/
+pub trait OrderTransactions {
+    fn order_transactions(transactions: Vec<Transaction>) -> Vec<Transaction>;
+}
+
+impl OrderTransactions for Mempool {
+    /*
+    @name order_transactions
+    @desc highest fee first, then oldest first, ties broken by hash,
+          so every node orders the same transactions the same way. The
+          transactions of one sender then take their slots in nonce order,
+          since each one needs the nonce of the one before
+    */
+    fn order_transactions(mut transactions: Vec<Transaction>) -> Vec<Transaction> {
+        transactions.sort_by(|a, b| {
+            let a_time: i64 = a.transaction_timestamp.timestamp.parse::<i64>().unwrap_or(0);
+            let b_time: i64 = b.transaction_timestamp.timestamp.parse::<i64>().unwrap_or(0);
+            b.transaction_fee.cmp(&a.transaction_fee)
+                             .then(a_time.cmp(&b_time))
+                             .then(a.transaction_hash.cmp(&b.transaction_hash))
+        });
+        let mut by_nonce: Vec<Transaction> = transactions.clone();
+        by_nonce.sort_by(|a, b| a.transaction_nonce.cmp(&b.transaction_nonce));
+        transactions.iter().map(|slot| {
+            let position: usize = by_nonce.iter()
+                                          .position(|tx| tx.transaction_sender == slot.transaction_sender)
+                                          .unwrap();
+            by_nonce.remove(position)
+        }).collect()
+    }
+}
+
+/*
+@name PendingTransactions
+@desc
+*/
+pub trait PendingTransactions {
+    fn evict_expired() -> ();
+    fn pending_transactions() -> Vec<Transaction>;
+}
+
+impl PendingTransactions for Mempool {
+    fn evict_expired() -> () {
+        let now: i64 = match Timestamp::new() {
+            Some(ts) => ts.timestamp.parse::<i64>().unwrap_or(0),
+            None => return
+        };
+        let expired: Vec<Transaction> = DB::get_all_transactions().into_iter().filter(|tx| {
+            tx.transaction_timestamp.timestamp.parse::<i64>().unwrap_or(0) + MEMPOOL_TRANSACTION_TTL < now
+        }).collect();
+        if !expired.is_empty() {
+            println!("Mempool, evicting {} expired transactions", expired.len());
+            Transaction::remove_transactions(expired);
+        }
+    }
+
+    /*
+    @name pending_transactions
+    @desc the pool in block order, for the block builder
+    */
+    fn pending_transactions() -> Vec<Transaction> {
+        Self::evict_expired();
+        Self::order_transactions(DB::get_all_transactions())
+    }
+}
+
+/*
+@name AdmitTransaction
+@desc
+*/
+pub trait AdmitTransaction {
+    fn current_state() -> Result<JsonValue, String>;
+    fn next_nonce(sender: String) -> i64;
+    fn check_nonce(pool: Vec<Transaction>, transaction: Transaction, state: JsonValue) -> Result<(), String>;
+    fn min_transaction_fee() -> Amount;
+    fn check_fee(transaction: Transaction, min_fee: Amount) -> Result<(), String>;
+    fn check_format(transaction: Transaction) -> Result<(), String>;
+    fn check_pool(pool: Vec<Transaction>, transaction: Transaction, max_transactions: usize, max_per_sender: usize) -> Result<(), String>;
+    fn check_unspent(pool: Vec<Transaction>, transaction: Transaction, state: JsonValue) -> Result<(), String>;
+    fn outbid_transaction(pool: Vec<Transaction>) -> Option<Transaction>;
+    fn dry_run(pool: Vec<Transaction>, transaction: Transaction) -> Result<(), String>;
+    fn admit(transaction: Transaction) -> Result<String, String>;
+    fn reinsert(transactions: Vec<Transaction>) -> usize;
+}
+
+impl AdmitTransaction for Mempool {
+    fn current_state() -> Result<JsonValue, String> {
+        match DB::read_state() {
+            Some(state_string) => match json::parse(&state_string) {
+                Ok(state) => Ok(state),
+                Err(_) => Err(String::from("Mempool, current state is not valid JSON"))
+            },
+            None => Err(String::from("Mempool, no current state"))
+        }
+    }
+
+    /*
+    @name next_nonce
+    @desc the nonce after the pending transactions of the sender
+    */
+    fn next_nonce(sender: String) -> i64 {
+        let state_nonce: i64 = match Self::current_state() {
+            Ok(state) => Transaction::account_nonce(state, sender.clone()),
+            Err(_) => 0
+        };
+        DB::get_all_transactions().into_iter()
+                                  .filter(|tx| tx.transaction_sender == sender)
+                                  .map(|tx| tx.transaction_nonce + 1)
+                                  .fold(state_nonce, |next, nonce| if nonce > next { nonce } else { next })
+    }
+
+    /*
+    @name check_nonce
+    @desc the nonce cannot be used already, on chain or in the pool
+    */
+    fn check_nonce(pool: Vec<Transaction>, transaction: Transaction, state: JsonValue) -> Result<(), String> {
+        let account_nonce: i64 = Transaction::account_nonce(state, transaction.transaction_sender.clone());
+        if transaction.transaction_nonce < account_nonce {
+            return Err(format!("Mempool, nonce {} of {} is already used", transaction.transaction_nonce, transaction.transaction_sender))
+        }
+        if pool.iter().any(|pooled| pooled.transaction_sender == transaction.transaction_sender
+                                    && pooled.transaction_nonce == transaction.transaction_nonce) {
+            return Err(format!("Mempool, nonce {} of {} is already pending", transaction.transaction_nonce, transaction.transaction_sender))
+        }
+        Ok(())
+    }
+
+    /*
+    @name min_transaction_fee
+    @desc the minimum set in the genesis consensus parameters
+    */
+    fn min_transaction_fee() -> Amount {
+        match DB::read_genesis() {
+            Some(genesis_record) => match json::parse(&genesis_record) {
+                Ok(genesis_record) => match genesis_record["genesis"]["consensus"]["min_transaction_fee"].as_u64() {
+                    Some(min_fee) => Amount { value: min_fee },
+                    None => DEFAULT_MIN_TRANSACTION_FEE
+                },
+                Err(_) => DEFAULT_MIN_TRANSACTION_FEE
+            },
+            None => DEFAULT_MIN_TRANSACTION_FEE
+        }
+    }
+
+    /*
+    @name check_fee
+    @desc validator changes are approved by the validators and pay no fee,
+          everything else pays a fee above 0, and at least the minimum
+    */
+    fn check_fee(transaction: Transaction, min_fee: Amount) -> Result<(), String> {
+        match transaction.transaction_type {
+            TransactionType::ValidatorAdd | TransactionType::ValidatorRemove => Ok(()),
+            _ if transaction.transaction_fee.value == 0 => {
+                Err(format!("Mempool, fee {} is not above 0", transaction.transaction_fee))
+            },
+            _ if transaction.transaction_fee < min_fee => {
+                Err(format!("Mempool, fee {} is below the minimum of {}", transaction.transaction_fee, min_fee))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /*
+    @name check_format
+    @desc the transaction must be for our chain, its hash must match its
+          contents, and an input must carry every section the executor reads
+    */
+    fn check_format(transaction: Transaction) -> Result<(), String> {
+        if !Transaction::verify_hash(transaction.clone()) {
+            return Err(format!("Mempool, transaction {} does not match its hash", transaction.transaction_hash))
+        }
+        if Some(transaction.transaction_chain_id.clone()) != DB::read_chain_id() {
+            return Err(format!("Mempool, transaction is for chain {}", transaction.transaction_chain_id))
+        }
+        match transaction.transaction_type {
+            TransactionType::Input => {
+                let decoded: String = Encoder::decode_base64(transaction.transaction_data)?;
+                if decoded.split(" ").count() < 3 {
+                    return Err(String::from("Mempool, input needs an output hash, a signature and a public key"))
+                }
+                Ok(())
+            },
+            TransactionType::TxTypeError => Err(String::from("Mempool, unknown transaction type")),
+            _ => Ok(())
+        }
+    }
+
+    /*
+    @name check_pool
+    @desc no duplicates, and room in the pool for the transaction and its sender.
+          A full pool still takes a transaction paying more than its cheapest one
+    */
+    fn check_pool(pool: Vec<Transaction>, transaction: Transaction, max_transactions: usize, max_per_sender: usize) -> Result<(), String> {
+        let mut sender_count: usize = 0;
+        for pooled in pool.iter() {
+            if pooled.transaction_hash == transaction.transaction_hash {
+                return Err(format!("Mempool, transaction {} is already in the pool", transaction.transaction_hash))
+            }
+            if pooled.transaction_sender == transaction.transaction_sender {
+                sender_count = sender_count + 1;
+            }
+        }
+        if pool.len() >= max_transactions {
+            match Self::order_transactions(pool).last() {
+                Some(cheapest) if cheapest.transaction_fee < transaction.transaction_fee => {},
+                _ => return Err(String::from("Mempool, the pool is full"))
+            }
+        }
+        if sender_count >= max_per_sender {
+            return Err(format!("Mempool, {} has too many pending transactions", transaction.transaction_sender))
+        }
+        Ok(())
+    }
+
+    /*
+    @name check_unspent
+    @desc an input must claim an output on chain, with its recorded sender, or
+          in the pool, that is not spent already, and that no pending input claims
+    */
+    fn check_unspent(pool: Vec<Transaction>, transaction: Transaction, state: JsonValue) -> Result<(), String> {
+        let output_hash: String = match transaction.claimed_output() {
+            Some(output_hash) => output_hash,
+            None => return Ok(())
+        };
+        if Transaction::output_spent(state.clone(), output_hash.clone()) {
+            return Err(format!("Mempool, output {} is already spent", output_hash))
+        }
+        let pending_output: bool = pool.iter().any(|pooled| match pooled.transaction_type {
+            TransactionType::Output => pooled.transaction_hash == output_hash,
+            _ => false
+        });
+        if Transaction::output_sender(state.clone(), output_hash.clone()).is_none() && !pending_output {
+            return Err(format!("Mempool, output {} is unknown", output_hash))
+        }
+        if pool.iter().any(|pooled| pooled.claimed_output() == Some(output_hash.clone())) {
+            return Err(format!("Mempool, output {} is already claimed by a pending input", output_hash))
+        }
+        Ok(())
+    }
+
+    /*
+    @name outbid_transaction
+    @desc the transaction a full pool drops for a better paying one. The
+          cheapest sender's later nonces need its earlier ones, so drop
+          that sender's highest nonce rather than leave a gap
+    */
+    fn outbid_transaction(pool: Vec<Transaction>) -> Option<Transaction> {
+        let cheapest_sender: String = Self::order_transactions(pool.clone()).pop()?.transaction_sender;
+        pool.into_iter()
+            .filter(|pooled| pooled.transaction_sender == cheapest_sender)
+            .max_by_key(|pooled| pooled.transaction_nonce)
+    }
+
+    /*
+    @name dry_run
+    @desc execute the pool and then the transaction on the current state,
+          without writing anything. The executor leaves the state as it
+          was when it refuses a transaction
+    */
+    fn dry_run(pool: Vec<Transaction>, transaction: Transaction) -> Result<(), String> {
+        let current_state: JsonValue = Self::current_state()?;
+        let next_block_id: i64 = DB::get_latest_block_id().unwrap_or(-1) + 1;
+        // the next block is made about now
+        let next_block_time: Timestamp = Timestamp::new().ok_or(String::from("Mempool, no time for the next block"))?;
+        let pool_state: JsonValue = Transaction::apply_block_transactions(current_state, next_block_id, next_block_time.clone(), Self::order_transactions(pool));
+        let admitted_state: JsonValue = Transaction::apply_block_transactions(pool_state.clone(), next_block_id, next_block_time, vec![transaction.clone()]);
+        if State::canonical_state(admitted_state) == State::canonical_state(pool_state) {
+            return Err(format!("Mempool, transaction {} does not execute on the current state", transaction.transaction_hash))
+        }
+        Ok(())
+    }
+
+    fn admit(transaction: Transaction) -> Result<String, String> {
+        Self::check_format(transaction.clone())?;
+        Self::check_fee(transaction.clone(), Self::min_transaction_fee())?;
+        Self::evict_expired();
+        let mut pool: Vec<Transaction> = DB::get_all_transactions();
+        let current_state: JsonValue = Self::current_state()?;
+        Self::check_nonce(pool.clone(), transaction.clone(), current_state.clone())?;
+        Self::check_unspent(pool.clone(), transaction.clone(), current_state)?;
+        Self::check_pool(pool.clone(), transaction.clone(), MEMPOOL_MAX_TRANSACTIONS, MEMPOOL_MAX_TRANSACTIONS_PER_SENDER)?;
+        // make room by dropping the last nonce of the cheapest sender
+        let mut outbid: Vec<Transaction> = Vec::new();
+        if pool.len() >= MEMPOOL_MAX_TRANSACTIONS {
+            outbid = Self::outbid_transaction(pool.clone()).into_iter().collect();
+            let outbid_hashes: Vec<String> = outbid.iter().map(|tx| tx.transaction_hash.clone()).collect();
+            pool.retain(|pooled| !outbid_hashes.contains(&pooled.transaction_hash));
+        }
+        Self::dry_run(pool, transaction.clone())?;
+        if !outbid.is_empty() {
+            println!("Mempool, {} is outbid by {}", outbid[0].transaction_hash, transaction.transaction_hash);
+            Transaction::remove_transactions(outbid);
+        }
+        match DB::write_transaction(transaction.clone()) {
+            Ok(_) => Ok(format!("Mempool, admitted transaction {}", transaction.transaction_hash)),
+            Err(_) => Err(format!("Mempool, could not write transaction {}", transaction.transaction_hash))
+        }
+    }
+
+    /*
+    @name reinsert
+    @desc put the transactions of a rejected proposal back in the pool,
+          those no longer valid are dropped. Returns how many went back
+    */
+    fn reinsert(transactions: Vec<Transaction>) -> usize {
+        let mut reinserted: usize = 0;
+        let pool_hashes: Vec<String> = DB::get_all_transactions().into_iter().map(|tx| tx.transaction_hash).collect();
+        for transaction in Self::order_transactions(transactions) {
+            if pool_hashes.contains(&transaction.transaction_hash) {
+                continue
+            }
+            match Self::admit(transaction) {
+                Ok(_) => reinserted = reinserted + 1,
+                Err(err) => println!("Mempool, reinsert: {}", err)
+            }
+        }
+        reinserted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mempool,
+                OrderTransactions,
+                AdmitTransaction};
+    use transaction::{Transaction, TransactionType};
+    use amount::{Amount};
+    use timestamp::{Timestamp};
+    use encode::{Encoder, Base64Encode};
+    use json::{JsonValue};
+
+    fn test_transaction(sender: &str, time: &str, hash: &str) -> Transaction {
+        Transaction {
+            transaction_timestamp: Timestamp { timestamp: String::from(time) },
+            transaction_type: TransactionType::Output,
+            transaction_sender: String::from(sender),
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: 0 },
+            transaction_nonce: 0,
+            transaction_data: String::from("data"),
+            transaction_hash: String::from(hash)
+        }
+    }
+
+    #[test]
+    fn test_order_is_by_time_then_hash() {
+        let ordered: Vec<Transaction> = Mempool::order_transactions(vec![test_transaction("a", "20", "b"),
+                                                                         test_transaction("a", "10", "c"),
+                                                                         test_transaction("b", "20", "a")]);
+        let hashes: Vec<String> = ordered.into_iter().map(|tx| tx.transaction_hash).collect();
+        assert_eq!(hashes, vec![String::from("c"), String::from("a"), String::from("b")]);
+    }
+
+    #[test]
+    fn test_check_pool_rejects_duplicates_and_full_pools() {
+        let pool: Vec<Transaction> = vec![test_transaction("alice", "10", "h0"),
+                                          test_transaction("alice", "11", "h1"),
+                                          test_transaction("bob", "12", "h2")];
+        assert!(Mempool::check_pool(pool.clone(), test_transaction("carol", "13", "h3"), 4, 2).is_ok());
+        assert!(Mempool::check_pool(pool.clone(), test_transaction("carol", "13", "h1"), 4, 2).is_err());
+        assert!(Mempool::check_pool(pool.clone(), test_transaction("alice", "13", "h3"), 4, 2).is_err());
+        assert!(Mempool::check_pool(pool.clone(), test_transaction("carol", "13", "h3"), 3, 2).is_err());
+    }
+
+    #[test]
+    fn test_higher_fees_go_first_and_outbid_a_full_pool() {
+        let pool: Vec<Transaction> = vec![test_transaction("alice", "10", "h0"),
+                                          test_transaction("bob", "20", "h1")];
+        let mut paying: Transaction = test_transaction("carol", "30", "h2");
+        paying.transaction_fee = Amount { value: 5 };
+        let ordered: Vec<Transaction> = Mempool::order_transactions(vec![pool[0].clone(), pool[1].clone(), paying.clone()]);
+        let hashes: Vec<String> = ordered.into_iter().map(|tx| tx.transaction_hash).collect();
+        assert_eq!(hashes, vec![String::from("h2"), String::from("h0"), String::from("h1")]);
+        assert!(Mempool::check_pool(pool.clone(), test_transaction("carol", "30", "h2"), 2, 2).is_err());
+        assert!(Mempool::check_pool(pool.clone(), paying.clone(), 2, 2).is_ok());
+        assert!(Mempool::check_fee(test_transaction("carol", "30", "h2"), Amount { value: 1 }).is_err());
+        assert!(Mempool::check_fee(paying.clone(), Amount { value: 1 }).is_ok());
+        // without a minimum, a fee still has to be above 0
+        assert!(Mempool::check_fee(test_transaction("carol", "30", "h2"), Amount { value: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_sender_transactions_stay_in_nonce_order() {
+        let mut first: Transaction = test_transaction("alice", "10", "h0");
+        first.transaction_fee = Amount { value: 1 };
+        let mut second: Transaction = test_transaction("alice", "11", "h1");
+        second.transaction_fee = Amount { value: 9 };
+        second.transaction_nonce = 1;
+        let mut other: Transaction = test_transaction("bob", "12", "h2");
+        other.transaction_fee = Amount { value: 5 };
+        let ordered: Vec<Transaction> = Mempool::order_transactions(vec![second, other, first]);
+        let hashes: Vec<String> = ordered.into_iter().map(|tx| tx.transaction_hash).collect();
+        assert_eq!(hashes, vec![String::from("h0"), String::from("h2"), String::from("h1")]);
+    }
+
+    #[test]
+    fn test_outbid_drops_the_last_nonce_of_the_cheapest_sender() {
+        let mut first: Transaction = test_transaction("alice", "10", "h0");
+        first.transaction_fee = Amount { value: 1 };
+        let mut second: Transaction = test_transaction("alice", "11", "h1");
+        second.transaction_fee = Amount { value: 9 };
+        second.transaction_nonce = 1;
+        let mut other: Transaction = test_transaction("bob", "12", "h2");
+        other.transaction_fee = Amount { value: 5 };
+        // alice's nonce 0 is the cheapest, but her nonce 1 needs it
+        let outbid: Option<Transaction> = Mempool::outbid_transaction(vec![first, second, other]);
+        assert_eq!(outbid.map(|tx| tx.transaction_hash), Some(String::from("h1")));
+        assert_eq!(Mempool::outbid_transaction(vec![]), None);
+    }
+
+    #[test]
+    fn test_inputs_claim_unspent_outputs_once() {
+        let test_input = |hash: &str, output_hash: &str| -> Transaction {
+            let mut input: Transaction = test_transaction("bob", "20", hash);
+            input.transaction_type = TransactionType::Input;
+            input.transaction_data = Encoder::encode_base64(format!("{} SIG PK", output_hash)).unwrap();
+            input
+        };
+        let state: JsonValue = json::parse(r#"{"alice": "100", "OUT1": "output", "OUT4": "output",
+                                               "output_senders": {"OUT0": "alice", "OUT1": "alice"},
+                                               "spent_outputs": {"OUT0": "IN0"}}"#).unwrap();
+        let pool: Vec<Transaction> = vec![test_transaction("alice", "10", "OUT2"), test_input("IN1", "OUT1")];
+        assert!(Mempool::check_unspent(pool.clone(), test_input("IN2", "OUT0"), state.clone()).is_err());
+        assert!(Mempool::check_unspent(pool.clone(), test_input("IN2", "OUT1"), state.clone()).is_err());
+        assert!(Mempool::check_unspent(pool.clone(), test_input("IN2", "OUT3"), state.clone()).is_err());
+        // an output with no recorded sender has nobody to pay it
+        assert!(Mempool::check_unspent(pool.clone(), test_input("IN2", "OUT4"), state.clone()).is_err());
+        assert!(Mempool::check_unspent(pool.clone(), test_input("IN2", "OUT2"), state.clone()).is_ok());
+        assert!(Mempool::check_unspent(vec![], test_input("IN2", "OUT1"), state.clone()).is_ok());
+    }
+}
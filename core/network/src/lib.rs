@@ -13,6 +13,9 @@ You should have received a copy of the GNU Lesser General Public License
 along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
 */
 
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
 use std::net::{Shutdown, TcpListener, TcpStream};
 use std::thread;
 use std::io::{Write, Read};
@@ -30,15 +33,52 @@ use proposal::{Proposal,
                GetProposals,
                UpdateProposal,
                CompareWithoutStatus,
-               ValidateProposalBlock,
                ProposalResolutionAccepted};
 use url::Url;
 use reqwest::header::{USER_AGENT, CONTENT_TYPE, ORIGIN};
 use encode::{Encoder, Base64Encode, Base64Decode};
+use genesis::{Genesis, ReadGenesis};
+use mempool::{Mempool, AdmitTransaction};
+use amount::{Amount, StringToAmount};
 use transaction::{Transaction,
+                  JsonConverter as TransactionJsonConverter,
                   CreateNewOuputTransaction,
-                  CreateNewInputTransaction};
+                  CreateNewInputTransaction,
+                  CreateNewValidatorChangeTransaction};
+use validator::{Validator,
+                ValidatorChangeJson,
+                Identity,
+                IdentityManager};
+use keystore::{Keystore, KeystoreSigner};
 use block::{Block, ReadBlock, BlockToJson};
+use evidence::{Evidence,
+               EvidenceStatus,
+               Vote,
+               CreateVote,
+               JsonToVote,
+               VoteToJson,
+               EvidenceToJson,
+               JsonToEvidence,
+               DetectEquivocation,
+               VerifyEvidence,
+               StoreEvidence,
+               ReadEvidence};
+use certificate::{Certificate,
+                  CertificateToJson,
+                  JsonToCertificate,
+                  VerifyCertificate,
+                  StoreCertificate,
+                  ReadCertificate};
+use sync::{Sync,
+           HeightMessage,
+           PeerHeights,
+           SyncRequest,
+           ServeBlocks,
+           ApplyBlocks,
+           HeaderSync,
+           SnapshotSync,
+           ForeignPeers,
+           SyncRange};
 
 
 pub trait PayloadParser {
@@ -177,17 +217,59 @@ pub trait Transmitter{
     */
     fn broadcast_proposal_resolution(proposal: Proposal, peer_location: String, ip: String) -> Result<(), Box<std::error::Error>>;
 
+    /*
+    @name broadcast_height
+    @desc announce our height to a peer, so it knows if it fell behind
+    */
+    fn broadcast_height(peer_location: String, ip: String) -> Result<(), String>;
+
     /*
     @name broadcast_block_query
-    @desc broadcast query to fetch block for sync purposes
+    @desc broadcast query to fetch a range of blocks for sync purposes
     */
-    fn broadcast_block_query(block_id_requested: i64, peer_location: String, ip: String) -> Result<(), Box<std::error::Error>>;
+    fn broadcast_block_query(start: i64, end: i64, peer_location: String, ip: String) -> Result<(), Box<std::error::Error>>;
 
     /*
     @name broadcast_block_query_response
     @desc respond to a block query from another node
     */
-    fn broadcast_block_query_response(proposal: Proposal, peer_location: String) -> Result<(), String> ;
+    fn broadcast_block_query_response(blocks: JsonValue, peer_location: String) -> Result<(), String> ;
+
+    /*
+    @name broadcast_header_query
+    @desc ask a peer for a range of certified block headers, for snapshot sync
+    */
+    fn broadcast_header_query(start: i64, end: i64, peer_location: String, ip: String) -> Result<(), String>;
+
+    /*
+    @name broadcast_header_query_response
+    @desc respond to a header query from another node
+    */
+    fn broadcast_header_query_response(headers: JsonValue, peer_location: String) -> Result<(), String>;
+
+    /*
+    @name broadcast_snapshot_query
+    @desc ask a peer for the state right after the block with this hash
+    */
+    fn broadcast_snapshot_query(block_hash: String, peer_location: String, ip: String) -> Result<(), String>;
+
+    /*
+    @name broadcast_snapshot_query_response
+    @desc respond to a snapshot query from another node
+    */
+    fn broadcast_snapshot_query_response(checkpoint: JsonValue, peer_location: String) -> Result<(), String>;
+
+    /*
+    @name broadcast_evidence
+    @desc gossip an evidence record of conflicting messages to a peer
+    */
+    fn broadcast_evidence(evidence: Evidence, peer_location: String, ip: String) -> Result<(), String>;
+
+    /*
+    @name broadcast_transaction
+    @desc relay a transaction we admitted to a peer's mempool
+    */
+    fn broadcast_transaction(transaction: Transaction, peer_location: String, ip: String) -> Result<(), String>;
 
 }
 
@@ -246,7 +328,17 @@ impl Transmitter for Server {
             let peer_location_url: &str = &format!("http://{}/proposal/response/", peer_location).to_string();
             let url_object = url::Url::parse( peer_location_url );
             let client = reqwest::Client::new();
-            let proposal_to_json: String = Proposal::to_json(proposal.clone()).to_string();
+            //the response carries our signed vote, so the creator can detect a peer voting twice
+            let mut proposal_json: JsonValue = json::parse( &Proposal::to_json(proposal.clone()) ).unwrap();
+            match Vote::create_vote(proposal.clone(), proposal_status) {
+                Some(vote) => {
+                    proposal_json["proposal_vote"] = Vote::to_json(vote);
+                },
+                None => {
+                    println!("broadcast_proposal_response, could not sign vote, sending response without it");
+                }
+            }
+            let proposal_to_json: String = proposal_json.dump();
             let b64_stringed_proposal: Result<String,String> = Encoder::encode_base64(proposal_to_json);
             //TODO: alter a meaningful header, not user agent...
             if b64_stringed_proposal.is_ok() {
@@ -283,7 +375,17 @@ impl Transmitter for Server {
             let peer_location_url: &str = &format!("http://{}/proposal/resolution/", peer_location).to_string();
             let url_object = url::Url::parse( peer_location_url );
             let client = reqwest::Client::new();
-            let proposal_to_json: String = Proposal::to_json(proposal.clone()).to_string();
+            //the resolution carries the commit certificate, so peers can store proof of finality
+            let mut proposal_json: JsonValue = json::parse( &Proposal::to_json(proposal.clone()) ).unwrap();
+            match Certificate::get_certificate_by_block_hash(proposal.proposal_block.block_hash.clone()) {
+                Some(certificate) => {
+                    proposal_json["proposal_certificate"] = Certificate::to_json(certificate);
+                },
+                None => {
+                    println!("broadcast_proposal_resolution, no certificate for block, sending resolution without it");
+                }
+            }
+            let proposal_to_json: String = proposal_json.dump();
             let b64_stringed_proposal: Result<String,String> = Encoder::encode_base64(proposal_to_json);
             //TODO: alter a meaningful header, not user agent...
             if b64_stringed_proposal.is_ok() {
@@ -306,18 +408,49 @@ impl Transmitter for Server {
             }
         }
 
+        /*
+            @name broadcast_height
+        */
+        fn broadcast_height(peer_location: String, ip: String) -> Result<(), String> {
+            println!("Broadcasting height... TO {}", peer_location);
+            let peer_location_url: &str = &format!("http://{}/block/height/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            let b64_stringed_height: Result<String,String> = Encoder::encode_base64(Sync::local_height_json().dump());
+            match b64_stringed_height {
+                Ok(b64_height) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, ip.as_str())
+                                     .header(USER_AGENT, b64_height)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast_height, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast_height, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_height, Broadcast Sent Failed"))
+                        }
+                    }
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_height, could not encode height"))
+                }
+            }
+        }
+
         /*
             @name broadcast_block_query
             @desc
         */
-        fn broadcast_block_query(block_id_requested: i64, peer_location: String, ip: String) -> Result<(), Box<std::error::Error>> {
-            println!("Broadcasting to attempt to sync chain... TO {}, fetching: {}", peer_location, block_id_requested);
+        fn broadcast_block_query(start: i64, end: i64, peer_location: String, ip: String) -> Result<(), Box<std::error::Error>> {
+            println!("Broadcasting to attempt to sync chain... TO {}, fetching: {} to {}", peer_location, start, end);
             let peer_location_url: &str = &format!("http://{}/block/query/", peer_location).to_string();
             let url_object = url::Url::parse( peer_location_url );
             let client = reqwest::Client::new();
             let resp = client.get(peer_location_url)
                              .header(ORIGIN, ip.as_str())
-                             .header(USER_AGENT, format!("{}", block_id_requested))
+                             .header(USER_AGENT, format!("{},{}", start, end))
                              .send();
             match resp {
                 Ok(result) => {
@@ -333,36 +466,205 @@ impl Transmitter for Server {
 
         /*
             @name broadcast_block_query_response
+            @desc send the requested blocks, each with its commit certificate
         */
-        fn broadcast_block_query_response(proposal: Proposal, peer_location: String) -> Result<(), String> {
+        fn broadcast_block_query_response(blocks: JsonValue, peer_location: String) -> Result<(), String> {
             println!("Broadcasting Block Query response to a block query... TO {}", peer_location);
-            let mut map = HashMap::new();
-            let proposal_id_to_insert: &str = &format!("{}", proposal.clone().proposal_id);
-            map.insert("proposal_id",  proposal_id_to_insert);
             let peer_location_url: &str = &format!("http://{}/block/response/", peer_location).to_string();
-            let url_object = url::Url::parse( peer_location_url );
             let client = reqwest::Client::new();
-            let proposal_to_json: String = Proposal::to_json(proposal.clone()).to_string();
-            let b64_stringed_proposal: Result<String,String> = Encoder::encode_base64(proposal_to_json);
-            //TODO: alter a meaningful header, not user agent...
-            if b64_stringed_proposal.is_ok() {
-                println!( "broadcast_block_query_response(), b64_stringed_proposal{}", b64_stringed_proposal.clone().unwrap() );
-                let resp = client.get(peer_location_url)
-                                 .header(ORIGIN, "127.0.0.1")
-                                 .header(USER_AGENT, b64_stringed_proposal.unwrap())
-                                 .send();
-                 match resp {
-                    Ok(result) => {
-                        println!("broadcast block query response, Broadcast Sent Successfully: {:#?}", result);
-                        Ok(())
-                    },
-                    Err(err) => {
-                        println!("broadcast block query response, Broadcast Sent Failed: {:#?}", err);
-                        Ok(())
+            let b64_stringed_blocks: Result<String,String> = Encoder::encode_base64(blocks.dump());
+            match b64_stringed_blocks {
+                Ok(b64_blocks) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, "127.0.0.1")
+                                     .header(USER_AGENT, b64_blocks)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast block query response, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast block query response, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_block_query_response, Broadcast Sent Failed"))
+                        }
                     }
-                 }
-            } else {
-                Ok(())
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_block_query_response, could not encode blocks"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_header_query
+        */
+        fn broadcast_header_query(start: i64, end: i64, peer_location: String, ip: String) -> Result<(), String> {
+            println!("Broadcasting header query... TO {}, fetching: {} to {}", peer_location, start, end);
+            let peer_location_url: &str = &format!("http://{}/header/query/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            let resp = client.get(peer_location_url)
+                             .header(ORIGIN, ip.as_str())
+                             .header(USER_AGENT, format!("{},{}", start, end))
+                             .send();
+            match resp {
+                Ok(result) => {
+                    println!("broadcast_header_query, Broadcast Sent Successfully: {:#?}", result);
+                    Ok(())
+                },
+                Err(err) => {
+                    println!("broadcast_header_query, Broadcast Sent Failed: {:#?}", err);
+                    Err(String::from("broadcast_header_query, Broadcast Sent Failed"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_header_query_response
+            @desc send the requested headers, each with its commit certificate
+        */
+        fn broadcast_header_query_response(headers: JsonValue, peer_location: String) -> Result<(), String> {
+            println!("Broadcasting Header Query response... TO {}", peer_location);
+            let peer_location_url: &str = &format!("http://{}/header/response/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            match Encoder::encode_base64(headers.dump()) {
+                Ok(b64_headers) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, "127.0.0.1")
+                                     .header(USER_AGENT, b64_headers)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast header query response, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast header query response, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_header_query_response, Broadcast Sent Failed"))
+                        }
+                    }
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_header_query_response, could not encode headers"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_snapshot_query
+        */
+        fn broadcast_snapshot_query(block_hash: String, peer_location: String, ip: String) -> Result<(), String> {
+            println!("Broadcasting snapshot query... TO {}, fetching state after: {}", peer_location, block_hash);
+            let peer_location_url: &str = &format!("http://{}/snapshot/query/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            let resp = client.get(peer_location_url)
+                             .header(ORIGIN, ip.as_str())
+                             .header(USER_AGENT, block_hash)
+                             .send();
+            match resp {
+                Ok(result) => {
+                    println!("broadcast_snapshot_query, Broadcast Sent Successfully: {:#?}", result);
+                    Ok(())
+                },
+                Err(err) => {
+                    println!("broadcast_snapshot_query, Broadcast Sent Failed: {:#?}", err);
+                    Err(String::from("broadcast_snapshot_query, Broadcast Sent Failed"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_snapshot_query_response
+            @desc send the checkpoint block, its certificate and the state after it
+        */
+        fn broadcast_snapshot_query_response(checkpoint: JsonValue, peer_location: String) -> Result<(), String> {
+            println!("Broadcasting Snapshot Query response... TO {}", peer_location);
+            let peer_location_url: &str = &format!("http://{}/snapshot/response/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            match Encoder::encode_base64(checkpoint.dump()) {
+                Ok(b64_checkpoint) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, "127.0.0.1")
+                                     .header(USER_AGENT, b64_checkpoint)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast snapshot query response, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast snapshot query response, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_snapshot_query_response, Broadcast Sent Failed"))
+                        }
+                    }
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_snapshot_query_response, could not encode checkpoint"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_evidence
+        */
+        fn broadcast_evidence(evidence: Evidence, peer_location: String, ip: String) -> Result<(), String> {
+            println!("Broadcasting Evidence against {}... TO {}", evidence.evidence_offender, peer_location);
+            let peer_location_url: &str = &format!("http://{}/evidence/submit/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            let evidence_to_json: String = Evidence::to_json(evidence).dump();
+            let b64_stringed_evidence: Result<String,String> = Encoder::encode_base64(evidence_to_json);
+            match b64_stringed_evidence {
+                Ok(b64_evidence) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, ip.as_str())
+                                     .header(USER_AGENT, b64_evidence)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast_evidence, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast_evidence, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_evidence, Broadcast Sent Failed"))
+                        }
+                    }
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_evidence, could not encode evidence"))
+                }
+            }
+        }
+
+        /*
+            @name broadcast_transaction
+        */
+        fn broadcast_transaction(transaction: Transaction, peer_location: String, ip: String) -> Result<(), String> {
+            println!("Relaying Transaction {}... TO {}", transaction.transaction_hash, peer_location);
+            let peer_location_url: &str = &format!("http://{}/transaction/relay/", peer_location).to_string();
+            let client = reqwest::Client::new();
+            let transaction_to_json: String = Transaction::to_json(transaction);
+            let b64_stringed_transaction: Result<String,String> = Encoder::encode_base64(transaction_to_json);
+            match b64_stringed_transaction {
+                Ok(b64_transaction) => {
+                    let resp = client.get(peer_location_url)
+                                     .header(ORIGIN, ip.as_str())
+                                     .header(USER_AGENT, b64_transaction)
+                                     .send();
+                    match resp {
+                        Ok(result) => {
+                            println!("broadcast_transaction, Broadcast Sent Successfully: {:#?}", result);
+                            Ok(())
+                        },
+                        Err(err) => {
+                            println!("broadcast_transaction, Broadcast Sent Failed: {:#?}", err);
+                            Err(String::from("broadcast_transaction, Broadcast Sent Failed"))
+                        }
+                    }
+                },
+                Err(_) => {
+                    Err(String::from("broadcast_transaction, could not encode transaction"))
+                }
             }
         }
 }
@@ -539,6 +841,131 @@ impl Receiver for Server {
 }
 
 
+/*
+@name Finality
+@desc keep the commit certificate sent with a resolution
+*/
+pub trait Finality {
+    fn accept_certificate(certificate: Option<Certificate>, block_hash: String) -> ();
+}
+
+impl Finality for Server {
+    /*
+    @name accept_certificate
+    @desc store the certificate only if it is for the block we just
+          committed and a quorum of registered validators signed it
+    */
+    fn accept_certificate(certificate: Option<Certificate>, block_hash: String) -> () {
+        match certificate {
+            Some(certificate) => {
+                if certificate.certificate_block_hash != block_hash {
+                    println!("accept_certificate, certificate is for another block, ignoring it");
+                    return
+                }
+                match Certificate::verify_certificate(certificate.clone()) {
+                    Ok(_) => {
+                        match Certificate::store_certificate(certificate) {
+                            Ok(result) => println!("accept_certificate, {}", result),
+                            Err(err) => println!("accept_certificate, {}", err)
+                        }
+                    },
+                    Err(err) => println!("accept_certificate, certificate rejected: {}", err)
+                }
+            },
+            None => {
+                println!("accept_certificate, resolution carries no certificate");
+            }
+        }
+    }
+}
+
+/*
+@name ChainCheck
+@desc handshakes, proposals and transactions carry the chain id
+      of the node that made them
+*/
+pub trait ChainCheck {
+    fn check_chain_id(command: &str, data: &str) -> Result<(), String>;
+}
+
+impl ChainCheck for Server {
+    /*
+    @name check_chain_id
+    @desc refuse a message made for another chain. Endpoints without
+          a chain bound message, or with one that does not decode, are
+          left to the endpoint itself
+    */
+    fn check_chain_id(command: &str, data: &str) -> Result<(), String> {
+        let local_chain_id: String = match Genesis::local_chain_id() {
+            Some(chain_id) => chain_id,
+            None => return Err(String::from("check_chain_id, no local chain id"))
+        };
+        let message: JsonValue = match Encoder::decode_base64(String::from(data)) {
+            Ok(decoded) => match json::parse(&decoded) {
+                Ok(message) => message,
+                Err(_) => return Ok(())
+            },
+            Err(_) => return Ok(())
+        };
+        let mut message_chain_ids: Vec<String> = Vec::new();
+        match command {
+            "/block/height/" => {
+                message_chain_ids.push(String::from(message["chain_id"].as_str().unwrap_or("")));
+            },
+            "/proposal/created/" | "/proposal/response/" | "/proposal/resolution/" => {
+                match Proposal::from_json(message) {
+                    Ok(proposal) => {
+                        message_chain_ids.push(proposal.proposal_chain_id);
+                        for transaction in proposal.proposal_block.transactions {
+                            message_chain_ids.push(transaction.transaction_chain_id);
+                        }
+                    },
+                    Err(_) => return Ok(())
+                }
+            },
+            _ => return Ok(())
+        }
+        for chain_id in message_chain_ids {
+            if chain_id != local_chain_id {
+                return Err(format!("check_chain_id, {} is for chain {}, we are on {}", command, chain_id, local_chain_id))
+            }
+        }
+        Ok(())
+    }
+}
+
+/*
+@name SubmissionOptions
+@desc a submitted transaction may end with fee=<amount> and nonce=<nonce>
+      sections. Without them it pays the minimum fee of the chain, and
+      takes the next nonce of the sender. A fee is a whole amount above 0
+*/
+pub trait SubmissionOptions {
+    fn split_submission(data: &str, sender: String) -> Result<(String, Amount, i64), String>;
+}
+
+impl SubmissionOptions for Server {
+    fn split_submission(data: &str, sender: String) -> Result<(String, Amount, i64), String> {
+        let mut sections: Vec<&str> = data.split(" ").collect();
+        let mut fee: Option<Amount> = None;
+        let mut nonce: Option<i64> = None;
+        while let Some(section) = sections.last().cloned() {
+            let value: &str = section.splitn(2, "=").nth(1).unwrap_or("");
+            if section.starts_with("fee=") && fee.is_none() {
+                fee = Some( Amount::positive_amount(String::from(value)).map_err(|err| format!("split_submission, {}: {}", section, err))? );
+            } else if section.starts_with("nonce=") && nonce.is_none() {
+                nonce = Some( value.parse::<i64>().map_err(|_| format!("split_submission, {} is not a number", section))? );
+            } else {
+                break
+            }
+            sections.pop();
+        }
+        Ok((sections.join(" "),
+            fee.unwrap_or_else(|| Mempool::min_transaction_fee()),
+            nonce.unwrap_or_else(|| Mempool::next_nonce(sender))))
+    }
+}
+
 /*
 @name API for Server
 @desc invoked actions by implementing this trait
@@ -562,6 +989,10 @@ impl API for Server {
     @desc interpret which API endpoint action to invoke
     */
     fn invoke_action(command: &str, data: &str, request_origin: String) -> Result<String, String> {
+        if Sync::is_foreign_peer(request_origin.clone()) {
+            return Err(format!("Refusing request from {}, it runs a different genesis", request_origin))
+        }
+        Self::check_chain_id(command, data)?;
         match command {
 
             // TODO:
@@ -595,7 +1026,7 @@ impl API for Server {
             // TODO:
             /*
             @endpoint /API/block/get/
-            @desc get the latest proposal
+            @desc get a block by id, with its commit certificate if we have one
             */
             "/API/block/get/" => {
                 println!("API Block by ID get: {}, {}, {}", command, data, request_origin);
@@ -604,7 +1035,12 @@ impl API for Server {
                 let block_to_return: Option<Block> = Block::get_block_by_block_id(block_id);
                 match block_to_return {
                     Some(block) => {
-                        Ok( String::from(Block::to_json(block).dump()) )
+                        let mut block_json: JsonValue = Block::to_json(block.clone());
+                        block_json["block_certificate"] = match Certificate::get_certificate_by_block_hash(block.block_hash) {
+                            Some(certificate) => Certificate::to_json(certificate),
+                            None => JsonValue::Null
+                        };
+                        Ok( String::from(block_json.dump()) )
                     },
                     None => {
                         Err( String::from("API Block by ID: Block Option was null") )
@@ -637,10 +1073,12 @@ impl API for Server {
             */
             "/transaction/submit/output/" => {
                 println!("Transaction Output Submit: {}, {}, {}", command, data, request_origin);
-                let new_transaction: Option<Transaction> = Transaction::new_output(request_origin.clone(), String::from(data.clone()) );
+                let (transaction_data, transaction_fee, transaction_nonce): (String, Amount, i64) = Self::split_submission(data, request_origin.clone())?;
+                let new_transaction: Option<Transaction> = Transaction::new_output(request_origin.clone(), transaction_data, transaction_fee, transaction_nonce);
                 match new_transaction {
                     Some(tx) => {
-                        println!("Transaction Made: {}", tx.transaction_id);
+                        println!("Transaction Made: {}", tx.transaction_hash);
+                        Mempool::admit(tx.clone())?;
                         let create_tx_result: String = format!("Transaction Received {}", tx.transaction_hash);
                         Ok( String::from(create_tx_result) )
                     },
@@ -657,10 +1095,12 @@ impl API for Server {
             */
             "/transaction/submit/input/" => {
                 println!("Transaction Input Submit: {}, {}, {}", command, data, request_origin);
-                let new_transaction: Option<Transaction> = Transaction::new_input(request_origin.clone(), String::from(data.clone()) );
+                let (transaction_data, transaction_fee, transaction_nonce): (String, Amount, i64) = Self::split_submission(data, request_origin.clone())?;
+                let new_transaction: Option<Transaction> = Transaction::new_input(request_origin.clone(), transaction_data, transaction_fee, transaction_nonce);
                 match new_transaction {
                     Some(tx) => {
-                        println!("Transaction Made: {}", tx.transaction_id);
+                        println!("Transaction Made: {}", tx.transaction_hash);
+                        Mempool::admit(tx.clone())?;
                         let create_tx_result: String = format!("Transaction Received {}", tx.transaction_hash);
                         Ok( String::from(create_tx_result) )
                     },
@@ -671,6 +1111,45 @@ impl API for Server {
                 }
             },
 
+            /*
+            @endpoint /transaction/submit/validator/
+            @desc add or remove a validator from a future block height. The
+                  data is base64 JSON of the change and the approvals the
+                  validators made of it, this node only relays it
+            */
+            "/transaction/submit/validator/" => {
+                println!("Transaction Validator Change Submit: {}, {}, {}", command, data, request_origin);
+                let signed_change: Result<JsonValue, String> = match Encoder::decode_base64(String::from(data)) {
+                    Ok(change_string) => match json::parse(&change_string) {
+                        Ok(signed_change) => match Validator::change_from_json(signed_change["validator_change"].clone()) {
+                            Ok(_) if signed_change["approvals"].is_array() => Ok(signed_change),
+                            Ok(_) => Err(String::from("change has no approvals")),
+                            Err(err) => Err(err)
+                        },
+                        Err(_) => Err(String::from("change is not valid JSON"))
+                    },
+                    Err(err) => Err(err)
+                };
+                match signed_change {
+                    Ok(signed_change) => {
+                        match Transaction::new_validator_change(request_origin.clone(), signed_change, Mempool::next_nonce(request_origin.clone())) {
+                            Some(tx) => {
+                                println!("Transaction Made: {}", tx.transaction_hash);
+                                Mempool::admit(tx.clone())?;
+                                Ok( format!("Transaction Received {}", tx.transaction_hash) )
+                            },
+                            None => {
+                                println!("ERROR Transaction NOT Made");
+                                Err( String::from("Transaction ERROR, NEW TX FAILED TO BE MADE") )
+                            }
+                        }
+                    },
+                    Err(err) => {
+                        Err( format!("Transaction ERROR, validator change: {}", err) )
+                    }
+                }
+            },
+
             /*
             @endpoint /proposal/create/
             @desc create a proposal, NOTE: should we expose this externally?
@@ -704,6 +1183,17 @@ impl API for Server {
                     match decoded_proposal.clone() {
                         Ok(proposal) => {
                             println!("invoke_action, proposal_created: successful proposal decoding, proposal_id: {}", decoded_proposal.unwrap().proposal_id);
+                            //a creator signing a second proposal for the same block and proposal id is equivocating
+                            match Evidence::check_proposal(proposal.clone()) {
+                                Some(evidence) => {
+                                    match Evidence::store_evidence(evidence) {
+                                        Ok(result) => println!("invoke_action, proposal_created: {}", result),
+                                        Err(err) => println!("invoke_action, proposal_created: {}", err)
+                                    }
+                                    return Err(String::from("Proposal Not Valid - creator signed a conflicting proposal"))
+                                },
+                                None => {}
+                            }
                             //TODO: Check current block ID against the proposal block_id to see if network's chain is ahead of the node's chain
                             //proposal verdict
                             match Proposal::validate_proposal(proposal.clone()) {
@@ -726,6 +1216,14 @@ impl API for Server {
                                         ProposalValidationResult::NotValidIncorrectProposalHash => {
                                             Proposal::store_proposal(proposal.clone(), ProposalStatus::NotValidIncorrectProposalHash);
                                             Ok(String::from("Proposal Not Valid - incorrect proposal hash"))
+                                        },
+                                        ProposalValidationResult::NotValidIncorrectSignature => {
+                                            //do not store forged proposals, they could claim any proposal id
+                                            Err(String::from("Proposal Not Valid - incorrect creator signature"))
+                                        },
+                                        ProposalValidationResult::NotValidIncorrectCreator => {
+                                            Proposal::store_proposal(proposal.clone(), ProposalStatus::NotValidIncorrectCreator);
+                                            Ok(String::from("Proposal Not Valid - creator was not elected"))
                                         }
                                     }
                                 },
@@ -758,6 +1256,28 @@ impl API for Server {
                 if decoded_proposal_string.clone().is_ok() {
                     println!("invoke_action(), proposal_response - Success: Received a proposal RESPONDED by another node: {}::{}", data, decoded_proposal_string.clone().unwrap());
                     println!("Decoded Proposal String: {:?}", decoded_proposal_string);
+                    //check the signed vote of the responding peer against the votes it already sent
+                    match json::parse( &decoded_proposal_string.clone().unwrap() ) {
+                        Ok(response_json) => {
+                            match Vote::from_json(response_json["proposal_vote"].clone()) {
+                                Ok(vote) => {
+                                    match Evidence::check_vote(vote) {
+                                        Some(evidence) => {
+                                            match Evidence::store_evidence(evidence) {
+                                                Ok(result) => println!("invoke_action(), proposal_response: {}", result),
+                                                Err(err) => println!("invoke_action(), proposal_response: {}", err)
+                                            }
+                                        },
+                                        None => {}
+                                    }
+                                },
+                                Err(_) => {
+                                    println!("invoke_action(), proposal_response: response carries no vote");
+                                }
+                            }
+                        },
+                        Err(_) => {}
+                    }
                     let decoded_proposal: Result<Proposal, String> = Proposal::from_json_string(decoded_proposal_string.unwrap());
                     //TODO: check if we have a proposal with that id
                     let all_proposals: Option<Vec<Proposal>> = Proposal::get_last_n_proposals();
@@ -882,6 +1402,10 @@ impl API for Server {
                 let decoded_proposal_string: Result<String, String> = Encoder::decode_base64(String::from(data));
                 if decoded_proposal_string.clone().is_ok() {
                     println!("invoke_action(), proposal_resolution - Success: Received a proposal RESOLUTION by another node: {}::{}", data, decoded_proposal_string.clone().unwrap());
+                    let received_certificate: Option<Certificate> = match json::parse( &decoded_proposal_string.clone().unwrap() ) {
+                        Ok(resolution_json) => Certificate::from_json(resolution_json["proposal_certificate"].clone()).ok(),
+                        Err(_) => None
+                    };
                     //TODO: check if we have a proposal with that id
                     let decoded_proposal: Result<Proposal, String> = Proposal::from_json_string(decoded_proposal_string.unwrap());
                     let all_proposals: Option<Vec<Proposal>> = Proposal::get_last_n_proposals();
@@ -924,6 +1448,8 @@ impl API for Server {
                                             //TODO CHECK IF THE AMOUNT OF VOTES IS ENOUGH TO SAY "COMMITTED"
                                             Proposal::update_proposal(found_proposal.clone().unwrap(),
                                             "committed");
+                                            Self::accept_certificate(received_certificate,
+                                                                     decoded_proposal.clone().unwrap().proposal_block.block_hash);
                                             Ok(String::from("Proposal resolution: Successfully parsed"))
                                         },
                                         Err(_) => {
@@ -963,101 +1489,291 @@ impl API for Server {
                 Ok(String::from(""))
             },
 
+            /*
+            @endpoint /block/height/
+            @desc a peer announced its height
+            */
+            "/block/height/" => {
+                println!("height received: {} | {}", data, request_origin);
+                let decoded_height_string: Result<String, String> = Encoder::decode_base64(String::from(data));
+                let height_json: Option<JsonValue> = match decoded_height_string {
+                    Ok(height_string) => json::parse(&height_string).ok(),
+                    Err(_) => None
+                };
+                let peer_height = match height_json {
+                    Some(height_json) => {
+                        Sync::check_peer_genesis(request_origin.clone(), height_json.clone())?;
+                        Sync::peer_height_from_json(request_origin.clone(), height_json)
+                    },
+                    None => None
+                };
+                match peer_height {
+                    Some(peer_height) => {
+                        Sync::record_peer_height(peer_height);
+                        Ok(String::from("Height: Successfully parsed"))
+                    },
+                    None => Err(String::from("Height: Error: could not read peer height"))
+                }
+            },
+
             /*
             @endpoint /block/query/
-            @desc when a node requests a specific block
+            @desc another node asked for a range of blocks, "start,end",
+                  respond with our canonical blocks and their certificates
             */
             "/block/query/" => {
-                // TODO: another node asked for a block by its ID, respond with proposal with block id, and commited
                 println!("block query received: {} | {} | {}", command, data, request_origin);
-                let all_proposals: Option<Vec<Proposal>> = Proposal::get_last_n_proposals();
-                //TODO: Breakout into Proposal::find_proposal
-                let found_proposal: Option<Proposal> = match all_proposals {
-                    Some(proposals) => {
-                        let mut same_proposal: Option<Proposal> = None;
-                        for proposal in proposals {
-                            //TODO: data parse here could be broken out of line to be checked against
-                            if proposal.clone().proposal_block.block_id == data.parse::<i64>().unwrap() {
-                                println!("/block/query/, proposal.block_id matches requested block_id");
-                                if proposal.clone().proposal_status == ProposalStatus::Committed {
-                                    println!("/block/query/, proposal status IS INDEED COMMITED, RESPOND WITH IT!");
-                                    same_proposal = Some(proposal);
-                                    // TODO: can safely break for proposal in proposals iteration
-                                } else {
-                                    println!("/block/query/, ERROR proposal STATUS IS NOT COMMITED, DON'T RESPOND WITH IT");
-                                }
-                            } else {
-                                println!("/block/query/, proposal.block_id does not match requested block_id");
+                match Sync::parse_range(data) {
+                    Some((start, end)) => {
+                        let blocks: JsonValue = Sync::get_certified_blocks(start, end);
+                        if blocks.len() > 0 {
+                            match Self::broadcast_block_query_response(blocks, request_origin) {
+                                Ok(_) => Ok(String::from("RESPONDING TO BLOCK QUERY")),
+                                Err(err) => Err(err)
                             }
+                        } else {
+                            Err(String::from("ERROR RESPONDING TO BLOCK QUERY, NO CERTIFIED BLOCKS IN RANGE"))
                         }
-                        same_proposal
                     },
                     None => {
-                        None
+                        Err(String::from("ERROR RESPONDING TO BLOCK QUERY, RANGE IS NOT VALID"))
                     }
-                };
-                if found_proposal.is_some() {
-                    Self::broadcast_block_query_response(found_proposal.clone().unwrap(), request_origin);
-                    Ok(String::from("RESPONDING TO BLOCK QUERY"))
-                } else {
-                    Err(String::from("ERROR RESPONDING TO BLOCK QUERY, FOUND_PROPOSAL IS NONE!"))
                 }
             },
 
             /*
             @endpoint /block/response/
-            @desc after a node requests a block, the block is sent to this endpoint in response
-
+            @desc after a node requests a range of blocks, they are sent to this endpoint in response.
+                  Each block is only committed if its certificate holds, and only in order
             */
             "/block/response/" => {
-                // TODO: the response is a proposal, containing a block
-                println!("Received Block from a peer AFTER QUERYING FOR IT");
-                let decoded_proposal_string: Result<String, String> = Encoder::decode_base64(String::from(data));
-                if decoded_proposal_string.clone().is_ok() {
-                    println!("invoke_action(), block received AFTER QUERING FOR IT - Success: queryied for block: {}", data);
-                    //TODO: check if we have a proposal with that id
+                println!("Received Blocks from a peer AFTER QUERYING FOR THEM");
+                let decoded_blocks_string: Result<String, String> = Encoder::decode_base64(String::from(data));
+                let blocks: Option<JsonValue> = match decoded_blocks_string {
+                    Ok(blocks_string) => json::parse(&blocks_string).ok(),
+                    Err(_) => None
+                };
+                match blocks {
+                    Some(blocks) => {
+                        match Sync::apply_certified_blocks(blocks) {
+                            Ok(applied) => {
+                                Ok(format!("Block response, synced {} blocks", applied))
+                            },
+                            Err(err) => {
+                                println!("invoke_action(), block response FAILED: {}", err);
+                                Sync::forget_requested_peer();
+                                Err(format!("Block response, {}", err))
+                            }
+                        }
+                    },
+                    None => {
+                        Err(String::from("Block response, could not decode blocks"))
+                    }
+                }
+            },
 
-                    /*
-                     PROBLEM:
-                     broadcast_block_query is invoked during:
-                        - node, AcceptedBroadcasted
-                        - node, RejectedBroadcasted
-                    So, upon a block query response, it immedietly tries to call
-                        - validate_proposal_block, which calls
-                            - commit_if_valid
-                    So, if Alice has the right ascenstor block, b,
-                    held by proposal, p, when it:
-                        - accepts a block,
-                        - and broadcasts it
-
-                    If will execute the same block more than once
-
-                    THEN! EVEN THOUGH the proposal status
-                    is changed, the loop in node has already
-                    loaded the proposals being used in the loop
-                    so it isnt updatingå the proposal about to be
-                    called next, p + 1
-                    This is why we don't see evidence of differing of TXs
-                    */
+            /*
+            @endpoint /header/query/
+            @desc another node asked for a range of headers, "start,end",
+                  respond with our canonical headers and their certificates
+            */
+            "/header/query/" => {
+                println!("header query received: {} | {} | {}", command, data, request_origin);
+                match Sync::parse_range(data) {
+                    Some((start, end)) => {
+                        let headers: JsonValue = Sync::get_certified_headers(start, end);
+                        if headers.len() > 0 {
+                            Self::broadcast_header_query_response(headers, request_origin)
+                                .map(|_| String::from("RESPONDING TO HEADER QUERY"))
+                        } else {
+                            Err(String::from("ERROR RESPONDING TO HEADER QUERY, NO CERTIFIED HEADERS IN RANGE"))
+                        }
+                    },
+                    None => {
+                        Err(String::from("ERROR RESPONDING TO HEADER QUERY, RANGE IS NOT VALID"))
+                    }
+                }
+            },
 
-                    let decoded_proposal: Result<Proposal, String> = Proposal::from_json_string(decoded_proposal_string.unwrap());
-                    match decoded_proposal {
-                        Ok(mut proposal) => {
-                            if proposal.validate_proposal_block().is_ok() {
-                                Ok(String::from(""))
-                            } else {
-                                Err(String::from("Block response, proposal.validate_proposal_block() FAILED"))
+            /*
+            @endpoint /header/response/
+            @desc headers we asked for during snapshot sync, appended to our header chain in order
+            */
+            "/header/response/" => {
+                println!("Received Headers from a peer AFTER QUERYING FOR THEM");
+                let headers: Option<JsonValue> = match Encoder::decode_base64(String::from(data)) {
+                    Ok(headers_string) => json::parse(&headers_string).ok(),
+                    Err(_) => None
+                };
+                match headers {
+                    Some(headers) => {
+                        match Sync::apply_certified_headers(headers) {
+                            Ok(applied) => Ok(format!("Header response, synced {} headers", applied)),
+                            Err(err) => {
+                                println!("invoke_action(), header response FAILED: {}", err);
+                                Sync::forget_requested_peer();
+                                Err(format!("Header response, {}", err))
                             }
-                        },
-                        Err(msg) => {
-                            println!("invoke_action(), ERROR, decoded_proposal FAILED ON BLOCK RESPONSE");
-                             Err(String::from("invoke_action(), ERROR, decoded_proposal FAILED ON BLOCK RESPONSE"))
                         }
+                    },
+                    None => {
+                        Err(String::from("Header response, could not decode headers"))
+                    }
+                }
+            },
+
+            /*
+            @endpoint /snapshot/query/
+            @desc another node asked for the state after one of our blocks, by block hash
+            */
+            "/snapshot/query/" => {
+                println!("snapshot query received: {} | {} | {}", command, data, request_origin);
+                match Sync::get_checkpoint(String::from(data)) {
+                    Some(checkpoint) => {
+                        Self::broadcast_snapshot_query_response(checkpoint, request_origin)
+                            .map(|_| String::from("RESPONDING TO SNAPSHOT QUERY"))
+                    },
+                    None => {
+                        Err(String::from("ERROR RESPONDING TO SNAPSHOT QUERY, NO CERTIFIED STATE FOR BLOCK"))
+                    }
+                }
+            },
+
+            /*
+            @endpoint /snapshot/response/
+            @desc the checkpoint we asked for, installed if it matches our header chain
+            */
+            "/snapshot/response/" => {
+                println!("Received Snapshot from a peer AFTER QUERYING FOR IT");
+                let checkpoint: Option<JsonValue> = match Encoder::decode_base64(String::from(data)) {
+                    Ok(checkpoint_string) => json::parse(&checkpoint_string).ok(),
+                    Err(_) => None
+                };
+                match checkpoint {
+                    Some(checkpoint) => {
+                        match Sync::install_snapshot(checkpoint) {
+                            Ok(block_id) => Ok(format!("Snapshot response, installed checkpoint at block {}", block_id)),
+                            Err(err) => {
+                                println!("invoke_action(), snapshot response FAILED: {}", err);
+                                Sync::forget_requested_peer();
+                                Err(format!("Snapshot response, {}", err))
+                            }
+                        }
+                    },
+                    None => {
+                        Err(String::from("Snapshot response, could not decode snapshot"))
+                    }
+                }
+            },
+                        /*
+            @endpoint /API/evidence/
+            @desc get all evidence of conflicting messages this node holds
+            */
+            "/API/evidence/" => {
+                println!("API Evidence: {}, {}, {}", command, data, request_origin);
+                let mut evidence_list: JsonValue = JsonValue::new_array();
+                for evidence in Evidence::get_all_evidence() {
+                    evidence_list.push( Evidence::to_json(evidence) ).unwrap();
+                }
+                let evidence_response = object!{
+                    "evidence" => evidence_list
+                };
+                Ok( evidence_response.dump() )
+            },
+
+            /*
+            @endpoint /API/account/
+            @desc the balance of an account, the nonce its next transaction
+                  should use, and the minimum fee and chain id transactions are signed for
+            */
+            "/API/account/" => {
+                println!("API Account: {}, {}, {}", command, data, request_origin);
+                let account: String = String::from(data.trim());
+                let state: JsonValue = Mempool::current_state().unwrap_or(JsonValue::new_object());
+                let balance: u64 = Amount::string_to_amount( state[ account.as_str() ].to_string() ).map(|balance| balance.value).unwrap_or(0);
+                let account_response = object!{
+                    "account" => account.clone(),
+                    "balance" => balance,
+                    "next_nonce" => Mempool::next_nonce(account),
+                    "min_transaction_fee" => Mempool::min_transaction_fee().value,
+                    "chain_id" => Genesis::local_chain_id().unwrap_or(String::new())
+                };
+                Ok( account_response.dump() )
+            },
+
+            /*
+            @endpoint /API/node/identity/
+            @desc this node's validator id and public keys, the current one
+                  signs, retired ones are listed so older signatures can be traced
+            */
+            "/API/node/identity/" => {
+                println!("API Node Identity: {}, {}, {}", command, data, request_origin);
+                let identity: Identity = match Identity::read_identity() {
+                    Some(identity) => identity,
+                    None => return Err(String::from("API Node Identity ERROR: no node identity"))
+                };
+                let keystore: JsonValue = match Keystore::identity_json() {
+                    Some(keystore) => keystore,
+                    None => return Err(String::from("API Node Identity ERROR: no keystore"))
+                };
+                let identity_response = object!{
+                    "identity_id" => identity.identity_id,
+                    "identity_location" => identity.identity_location,
+                    "key_type" => keystore["current"]["key_type"].clone(),
+                    "public_key" => keystore["current"]["public_key"].clone(),
+                    "retired_keys" => keystore["retired"].clone()
+                };
+                Ok( identity_response.dump() )
+            },
+
+            /*
+            @endpoint /transaction/relay/
+            @desc receive a transaction a peer admitted, it goes through
+                  the same admission as a submission, so a transaction we
+                  already hold is refused and not relayed again
+            */
+            "/transaction/relay/" => {
+                println!("Transaction relayed from {}", request_origin);
+                match Encoder::decode_base64(String::from(data)) {
+                    Ok(transaction_string) => {
+                        let transaction: Transaction = Transaction::from_json_string(transaction_string)?;
+                        Mempool::admit(transaction.clone())?;
+                        Ok( format!("Transaction Received {}", transaction.transaction_hash) )
+                    },
+                    Err(_) => {
+                        Err(String::from("Transaction relay ERROR: could not decode transaction"))
+                    }
+                }
+            },
+
+            /*
+            @endpoint /evidence/submit/
+            @desc receive evidence gossiped by a peer, stored as pending
+                  so it is gossiped on to our peers once
+            */
+            "/evidence/submit/" => {
+                println!("Evidence received: {}", data);
+                let decoded_evidence_string: Result<String, String> = Encoder::decode_base64(String::from(data));
+                match decoded_evidence_string {
+                    Ok(evidence_string) => {
+                        match Evidence::from_json_string(evidence_string) {
+                            Ok(mut evidence) => {
+                                if Evidence::verify_evidence(evidence.clone()) {
+                                    evidence.evidence_status = EvidenceStatus::Pending;
+                                    Evidence::store_evidence(evidence)
+                                } else {
+                                    Err(String::from("Evidence submit ERROR: evidence could not be verified"))
+                                }
+                            },
+                            Err(err) => Err(err)
+                        }
+                    },
+                    Err(_) => {
+                        Err(String::from("Evidence submit ERROR: could not decode evidence"))
                     }
-                } else {
-                    Err(String::from("Block response, proposal.decode_base64() FAILED"))
                 }
             },
+
             // default case
             _ => Err(String::from("API endpoint not correct"))
         }
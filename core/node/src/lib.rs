@@ -17,7 +17,7 @@ along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
 extern crate json;
 use json::{JsonValue};
 use network::{Server,Receiver,Transmitter};
-use transaction::{Transaction};
+use transaction::{Transaction, SignInput};
 use db::{DB, NodeNameSetter, LogDebug};
 use proposal::{Proposal,
                NewProposal,
@@ -43,6 +43,47 @@ use transaction::{CreateTransactionIndex,
                   CreateStateDB,
                   State};
 
+use validator::{Validator,
+                Identity,
+                CreateValidatorIndex,
+                ValidatorSchedule,
+                ValidatorChangeJson,
+                SignValidatorChange,
+                IdentityManager};
+
+use genesis::{Genesis,
+              ConsensusParams,
+              GenesisJson,
+              InitGenesis,
+              ReadGenesis};
+use mempool::{Mempool, AdmitTransaction, PendingTransactions};
+use signature::{Signature, SignatureType, SignatureTypeString, PrivateKey, PublicKey, KeyGenerator};
+use keystore::{Keystore, ManageKeystore, KEYSTORE_PASSPHRASE_ENV};
+use encode::{Encoder, Base64Encode, Base64Decode};
+use std::collections::HashSet;
+use std::path::Path;
+use std::env;
+
+use evidence::{Evidence,
+               EvidenceStatus,
+               CreateEvidenceIndex,
+               ReadEvidence,
+               StoreEvidence};
+
+use certificate::{Certificate,
+                  CreateCertificate,
+                  VerifyCertificate,
+                  StoreCertificate};
+
+use sync::{Sync,
+           CreateSyncIndex,
+           LocalHeight,
+           PeerHeights,
+           SyncRange,
+           SyncRequest,
+           HeaderSync,
+           ForeignPeers};
+
 use std::thread;
 
 /*
@@ -70,6 +111,8 @@ pub trait PeerManager {
     fn add_peer(&mut self, peer_to_add: Peer) -> ();
     fn get_peers(&mut self) -> &Peers;
     fn peers_to_location_vector(&mut self) -> Vec<String>;
+    fn add_validator_peers(&mut self) -> ();
+    fn drop_foreign_peers(&mut self) -> ();
 }
 
 
@@ -109,6 +152,45 @@ impl PeerManager for Node {
         }
         peer_location_vector
     }
+
+    /*
+    @name add_validator_peers
+    @desc validators added on chain become peers, without restarting with new peers=
+    */
+    fn add_validator_peers(&mut self) -> () {
+        let next_block_id: i64 = match DB::get_latest_block_id() {
+            Some(latest_block_id) => latest_block_id + 1,
+            None => 0
+        };
+        let known_locations: Vec<String> = self.peers_to_location_vector();
+        for validator in Validator::get_validators_at(next_block_id) {
+            if validator.validator_location != self.ip
+                && !known_locations.contains(&validator.validator_location) {
+                println!("Adding validator {} as peer: {}", validator.validator_id, validator.validator_location);
+                self.add_peer(Peer {
+                    location: validator.validator_location
+                });
+            }
+        }
+    }
+
+    /*
+    @name drop_foreign_peers
+    @desc stop talking to peers that announced a different genesis
+    */
+    fn drop_foreign_peers(&mut self) -> () {
+        let foreign_peers: Vec<String> = Sync::get_foreign_peers();
+        if foreign_peers.is_empty() {
+            return
+        }
+        self.peers.peer_set.retain(|peer| {
+            if foreign_peers.contains(&peer.location) {
+                println!("Dropping peer {}, it runs a different genesis", peer.location);
+                return false
+            }
+            true
+        });
+    }
 }
 
 
@@ -119,6 +201,15 @@ pub struct Node {
     peers: Peers,
     server: Server,
     ip: String,
+    key: String,
+    key_type: SignatureType,
+    rotate_key: bool,
+    approve_change: String,
+    print_identity: bool,
+    public_key: String,
+    sync_mode: String,
+    genesis: String,
+    relayed_transactions: HashSet<String>,
 }
 
 pub trait Initiate {
@@ -129,6 +220,19 @@ pub trait Initiate {
     fn set_port(&mut self, port: i32) -> ();
     fn set_initial_peers(&mut self, peer_list_string: String) -> ();
     fn set_node_ip(&mut self, ip: String) -> ();
+    fn set_node_key(&mut self, key_path: String) -> ();
+    fn set_node_key_type(&mut self, key_type: String) -> ();
+    fn set_rotate_key(&mut self, rotate_key: String) -> ();
+    fn set_approve_change(&mut self, change: String) -> ();
+    fn set_print_identity(&mut self, print_identity: String) -> ();
+    fn set_genesis(&mut self, genesis_path: String) -> ();
+    fn set_sync_mode(&mut self, sync_mode: String) -> ();
+    fn init_genesis(&self) -> Result<String, String>;
+    fn init_keystore(&mut self) -> Result<String, String>;
+    fn register_identity(&self) -> ();
+    fn approve_validator_change(&self) -> Option<Result<String, String>>;
+    fn genesis_identity(&self) -> Option<JsonValue>;
+    fn genesis_account(&self) -> String;
 }
 
 impl Initiate for Node {
@@ -151,6 +255,15 @@ impl Initiate for Node {
         //create state database
         State::create_state_db();
 
+        //create validator database
+        Validator::create_validator_index();
+
+        //create evidence and vote databases, kept across restarts
+        Evidence::create_evidence_index();
+
+        //peer heights are learned again after every start
+        Sync::create_sync_index();
+
         //TODO: CREATE DEBUG LOG FILES
         DB::create_debug_log_files();
 
@@ -159,7 +272,16 @@ impl Initiate for Node {
             node_id: node_id,
             peers: Self::new_peers(),
             server: server,
-            ip: ip
+            ip: ip,
+            key: String::new(),
+            key_type: SignatureType::RSA,
+            rotate_key: false,
+            approve_change: String::new(),
+            print_identity: false,
+            public_key: String::new(),
+            sync_mode: String::from("full"),
+            genesis: String::from("./genesis.json"),
+            relayed_transactions: HashSet::new()
         }
     }
 
@@ -208,15 +330,192 @@ impl Initiate for Node {
         println!("Setting node ip: {}", ip);
         self.ip = ip;
     }
+
+    /*
+    @name set_node_key
+    @desc a private key file, imported into the keystore on first start.
+          Without one, the keystore generates an Ed25519 key
+    */
+    fn set_node_key(&mut self, key_path: String) -> (){
+        println!("Setting node key: {}", key_path);
+        self.key = key_path;
+    }
+
+    fn set_node_key_type(&mut self, key_type: String) -> (){
+        match Signature::type_from_string(key_type.as_str()) {
+            Some(signature_type) => {
+                println!("Setting node key type: {}", key_type);
+                self.key_type = signature_type;
+            },
+            None => println!("Unknown key type {}, keeping {}", key_type, Signature::type_to_string(self.key_type.clone()))
+        }
+    }
+
+    fn set_rotate_key(&mut self, rotate_key: String) -> (){
+        println!("Setting rotate key: {}", rotate_key);
+        self.rotate_key = rotate_key == "true";
+    }
+
+    /*
+    @name set_approve_change
+    @desc base64 JSON of a validator change, to approve with our key
+          instead of starting the node
+    */
+    fn set_approve_change(&mut self, change: String) -> (){
+        println!("Setting validator change to approve: {}", change);
+        self.approve_change = change;
+    }
+
+    /*
+    @name set_print_identity
+    @desc print our validator entry for genesis.json instead of starting the node
+    */
+    fn set_print_identity(&mut self, print_identity: String) -> (){
+        println!("Setting print identity: {}", print_identity);
+        self.print_identity = print_identity == "true";
+    }
+
+    /*
+    @name set_sync_mode
+    @desc "full" replays every block from genesis, "snapshot" follows the
+          certified headers and installs the state at their tip instead
+    */
+    fn set_sync_mode(&mut self, sync_mode: String) -> (){
+        println!("Setting sync mode: {}", sync_mode);
+        self.sync_mode = sync_mode;
+    }
+
+    fn set_genesis(&mut self, genesis_path: String) -> (){
+        println!("Setting genesis file: {}", genesis_path);
+        self.genesis = genesis_path;
+    }
+
+    /*
+    @name init_genesis
+    @desc register the genesis validators and build block 0, once all
+          parameters are set. The node must not start if this fails
+    */
+    fn init_genesis(&self) -> Result<String, String> {
+        let genesis: Genesis = Genesis::from_file(self.genesis.clone())?;
+        let genesis_hash: String = Genesis::init_genesis(genesis)?;
+
+        //headers downloaded for snapshot sync, kept across restarts, starting from our block 0
+        Sync::create_header_index();
+        Ok(genesis_hash)
+    }
+
+    /*
+    @name init_keystore
+    @desc create or unlock the keystore with the passphrase from the
+          environment, rotating the key if asked. The node must not start if this fails
+    */
+    fn init_keystore(&mut self) -> Result<String, String> {
+        let passphrase: String = match env::var(KEYSTORE_PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => passphrase,
+            _ => return Err(format!("init_keystore ERROR: set {} to unlock the keystore", KEYSTORE_PASSPHRASE_ENV))
+        };
+        let import_key: Option<PrivateKey> = if self.key.is_empty() {
+            None
+        } else {
+            match Signature::read_private_key(self.key_type.clone(), Path::new(self.key.as_str())) {
+                Ok(private_key) => Some(private_key),
+                Err(err) => return Err(format!("init_keystore ERROR: could not read key {}: {:?}", self.key, err))
+            }
+        };
+        let mut public_key: PublicKey = Keystore::init_keystore(passphrase.as_str(), import_key)?;
+        if self.rotate_key {
+            public_key = Keystore::rotate_key(passphrase.as_str())?;
+        }
+        self.key_type = public_key.key_type.clone();
+        self.public_key = match Signature::public_key_to_base64(&public_key) {
+            Ok(public_key_base64) => public_key_base64,
+            Err(err) => return Err(format!("init_keystore ERROR: {:?}", err))
+        };
+        Ok(self.public_key.clone())
+    }
+
+    fn register_identity(&self) -> (){
+        let identity: Identity = Identity {
+            identity_id: self.node_id,
+            identity_location: self.ip.clone(),
+            identity_public_key: self.public_key.clone(),
+            identity_key_type: self.key_type.clone()
+        };
+        match Identity::store_identity(identity) {
+            Ok(_) => println!("Registered node identity: {}", self.node_id),
+            Err(err) => println!("register_identity ERROR: {}", err)
+        }
+    }
+
+    /*
+    @name approve_validator_change
+    @desc the base64 JSON approval of the change given with approve-change=,
+          signed with our keystore key. Approvals from more than 2/3 of the
+          validators are submitted together with the change
+    */
+    fn approve_validator_change(&self) -> Option<Result<String, String>> {
+        if self.approve_change.is_empty() {
+            return None
+        }
+        let change = match Encoder::decode_base64(self.approve_change.clone()) {
+            Ok(change_string) => match json::parse(&change_string) {
+                Ok(change_json) => Validator::change_from_json(change_json),
+                Err(_) => Err(String::from("approve_validator_change ERROR: change is not valid JSON"))
+            },
+            Err(err) => Err(err)
+        };
+        let approval: Result<JsonValue, String> = match change {
+            Ok(change) => Validator::sign_change(change),
+            Err(err) => Err(err)
+        };
+        Some(approval.and_then(|approval| Encoder::encode_base64(approval.dump())))
+    }
+
+    /*
+    @name genesis_identity
+    @desc our validator entry, with the keystore's public key, for the
+          validators of genesis.json. Every node has its own key
+    */
+    fn genesis_identity(&self) -> Option<JsonValue> {
+        if !self.print_identity {
+            return None
+        }
+        Some(object!{
+            "validator_id" => self.node_id,
+            "validator_location" => self.ip.clone(),
+            "validator_public_key" => self.public_key.clone(),
+            "validator_key_type" => Signature::type_to_string(self.key_type.clone())
+        })
+    }
+
+    /*
+    @name genesis_account
+    @desc the hash of our public key, an account genesis.json can give a balance
+    */
+    fn genesis_account(&self) -> String {
+        Transaction::public_key_hash(self.public_key.clone())
+    }
 }
 
 pub trait StateTransition {
     fn transition(&mut self) -> ();
     fn determine_transition_step(&mut self, proposal: Proposal, proposal_index: JsonValue) -> ();
+    fn gossip_evidence(&mut self) -> ();
+    fn relay_transactions(&mut self) -> ();
+    fn certify_block(proposal: Proposal) -> ();
 }
 
 impl StateTransition for Node {
     fn transition(&mut self) -> (){
+        self.add_validator_peers();
+        self.drop_foreign_peers();
+        //do not vote on proposals while peers are ahead of us, catch up first
+        if self.sync_check() {
+            println!("[transition] behind peers, syncing before resuming consensus");
+            self.gossip_evidence();
+            self.relay_transactions();
+            return
+        }
         //TODO: read DB for new transactions
         let proposals: Result<Vec<Proposal>, ()> = match Proposal::get_last_n_proposals(){
             Some(proposals) => {
@@ -229,8 +528,9 @@ impl StateTransition for Node {
             }
         };
 
-        let delay_proposal_iteration: u32 = 5000; //10000
-        let delay_proposal_creation: u32 = 60000; //10000
+        let consensus_params: ConsensusParams = Genesis::consensus_params();
+        let delay_proposal_iteration: u32 = consensus_params.proposal_iteration_delay;
+        let delay_proposal_creation: u32 = consensus_params.proposal_creation_delay;
         match proposals {
             Ok(p) => {
                 // PROBLEM: AT THE END OF THIS, REFRESH JSON
@@ -255,7 +555,7 @@ impl StateTransition for Node {
                                             let current_block_by_id_option: Option<Block> = DB::get_block_by_block_id(local_block_id);
                                             match current_block_by_id_option {
                                                 Some(current_block_by_id) => {
-                                                    let block_window_length: i64 = 5;
+                                                    let block_window_length: i64 = consensus_params.block_window_length;
                                                     let proposal_window: i64 = current_block_by_id.block_id - block_window_length;
                                                     if proposal.proposal_block.block_id > proposal_window {
                                                         //TODO: Condition on proposal's block_id, here we can limit how many proposals
@@ -311,9 +611,9 @@ impl StateTransition for Node {
                         let latest_block_id_option: Option<i64> = DB::get_latest_block_id();
                         match latest_block_id_option {
                             Some(block_id) => {
-                                let calculated_proposal_creator_id: i32 = Proposal::calculate_next_proposal_creator_id(self.peers.peer_set.len(), block_id) as i32;
-                                println!("calculated_proposal_creator_id: {} latest block_id: {}", calculated_proposal_creator_id, block_id);
-                                if calculated_proposal_creator_id == self.node_id {
+                                let calculated_proposal_creator_id: Option<i64> = Proposal::get_elected_proposal_creator_id(block_id);
+                                println!("calculated_proposal_creator_id: {:?} latest block_id: {}", calculated_proposal_creator_id, block_id);
+                                if calculated_proposal_creator_id == Some(self.node_id as i64) {
                                     thread::sleep_ms(delay_proposal_creation);
                                     Proposal::create( self.clone().ip );
                                 } else {
@@ -334,6 +634,8 @@ impl StateTransition for Node {
                 println!("[transition] ERROR - latest_proposal_option is NONE")
             }
         }
+        self.gossip_evidence();
+        self.relay_transactions();
         println!("[Done with state transition]")
         //TODO: if we find a proposal to be broadcasted,
         //sign it, broadcast it, and then
@@ -343,14 +645,84 @@ impl StateTransition for Node {
         //TODO: prune proposal index
     }
 
+    /*
+    @name gossip_evidence
+    @desc send pending evidence to every peer, then mark it broadcasted
+    */
+    fn gossip_evidence(&mut self) -> (){
+        let node_ip: String = self.ip.to_string();
+        for evidence in Evidence::get_pending_evidence() {
+            for peer in self.peers.clone().peer_set {
+                if Server::broadcast_evidence(evidence.clone(),
+                                              peer.clone().location,
+                                              node_ip.clone()).is_ok() {
+                    println!("[gossip_evidence], broadcast_evidence SUCCESS...");
+                } else {
+                    println!("[gossip_evidence], broadcast_evidence FAILED...");
+                }
+            }
+            match Evidence::update_evidence_status(evidence, EvidenceStatus::Broadcasted) {
+                Ok(_) => {},
+                Err(err) => println!("[gossip_evidence] ERROR: {}", err)
+            }
+        }
+    }
+
+    /*
+    @name relay_transactions
+    @desc send every pooled transaction we have not relayed yet to every
+          peer, so the elected creator holds it whichever node received it.
+          Hashes leave the relayed set once they leave the pool
+    */
+    fn relay_transactions(&mut self) -> (){
+        let node_ip: String = self.ip.to_string();
+        let pending: Vec<Transaction> = Mempool::pending_transactions();
+        let pending_hashes: HashSet<String> = pending.iter()
+                                                     .map(|transaction| transaction.transaction_hash.clone())
+                                                     .collect();
+        self.relayed_transactions.retain(|hash| pending_hashes.contains(hash));
+        for transaction in pending {
+            if self.relayed_transactions.contains(&transaction.transaction_hash) {
+                continue
+            }
+            for peer in self.peers.clone().peer_set {
+                if Server::broadcast_transaction(transaction.clone(),
+                                                 peer.clone().location,
+                                                 node_ip.clone()).is_ok() {
+                    println!("[relay_transactions], broadcast_transaction SUCCESS...");
+                } else {
+                    println!("[relay_transactions], broadcast_transaction FAILED...");
+                }
+            }
+            self.relayed_transactions.insert(transaction.transaction_hash);
+        }
+    }
+
+    /*
+    @name certify_block
+    @desc store the commit certificate of a block we proposed and committed,
+          it is sent to peers along with the resolution
+    */
+    fn certify_block(proposal: Proposal) -> (){
+        match Certificate::create_certificate(proposal) {
+            Some(certificate) => {
+                match Certificate::verify_certificate(certificate.clone()) {
+                    Ok(_) => {
+                        match Certificate::store_certificate(certificate) {
+                            Ok(result) => println!("[certify_block] {}", result),
+                            Err(err) => println!("[certify_block] ERROR: {}", err)
+                        }
+                    },
+                    Err(err) => println!("[certify_block] certificate not stored: {}", err)
+                }
+            },
+            None => println!("[certify_block] ERROR: could not read votes to build certificate")
+        }
+    }
+
     fn determine_transition_step(&mut self, proposal: Proposal, proposal_index: JsonValue) -> (){
         println!("Performing Transition for proposal_id: {}", proposal.clone().proposal_id);
         let node_ip: String = self.ip.to_string();
-        // TODO: for each proposal, read the DB file and replace the proposal "sync checked" upon
-        // Proposal::get_proposl_from_disk_by_id()
-        // TODO: Sync Check, check for sync
-        // TODO: Break out into its own check_is_need_to_sync()
-        Self::sync_check(&mut self.clone(), proposal.clone(), node_ip.clone());
         match proposal.clone().proposal_status {
             ProposalStatus::Pending => {
                 //TODO: broadcast proposal to network,
@@ -483,6 +855,9 @@ impl StateTransition for Node {
                     //todo: at least one peer rejected the proposal
                     println!("[determine_transition_step], Created, a peer rejected it");
                     Proposal::update_proposal(proposal.clone(), "rejected_by_network");
+                    //its transactions are proposed again in a later block
+                    let reinserted: usize = Mempool::reinsert(proposal.proposal_block.transactions.clone());
+                    println!("[determine_transition_step], Created, {} transactions back in the mempool", reinserted);
                 } else {
                     // all good, update
                     println!("[determine_transition_step], Created, all good to update proposal");
@@ -523,36 +898,7 @@ impl StateTransition for Node {
                 //we already Accepted it and told the network
                 //TODO: COULD CHANGE THIS TO BROADCAST RESPONSE?
 
-                //Test because some nodes broadcast acceptance, but don't get the most updated block
-
-                //*** test remove: do we need to broadcast a block query upon reaching a proposal we already broadcasted
-                let local_block_id_option: Option<i64> = DB::get_latest_block_id();
-                match local_block_id_option {
-                    Some(local_block_id) => { // successfuly fetch block id
-
-                        /* test remove: do we need to broadcast a block query upon reaching a proposal we already broadcasted */
-                        /*
-                        for peer in self.peers.clone().peer_set {
-
-                            if Server::broadcast_block_query( ( local_block_id ),
-                                                               peer.clone().location,
-                                                               node_ip.clone()).is_ok() {
-                                println!("[determine_transition_step], broadcast_block_query SUCCESS 1...");
-                                //Ok(String::from("BLOCK BEHIND, QUERING TO SYNC"))
-                            } else {
-                                println!("[determine_transition_step], broadcast_block_query FAILED 1...");
-                            }
-
-                        }
-                        */
-
-
-                    },
-                    None => {
-
-                    }
-                }
-
+                //nodes that missed the latest block catch up through sync_check, at the start of every transition
 
                 for peer in self.peers.clone().peer_set {
                     //TODO: decide who we should broadcast to
@@ -586,8 +932,12 @@ impl StateTransition for Node {
 
                         if (latest_proposal.proposal_status != ProposalStatus::Committed) {
 
-                            let block_commit_result: Result<(),String> = Block::commit_if_valid(proposal.clone().proposal_block);
-                            if block_commit_result.is_ok() {
+                            let block_commit_result: Result<Vec<Transaction>,String> = Block::commit_if_valid(proposal.clone().proposal_block);
+                            if let Ok(abandoned) = block_commit_result {
+                                let reinserted: usize = Mempool::reinsert(abandoned);
+                                println!("[determine_transition_step], {} abandoned transactions back in the mempool", reinserted);
+
+                                Self::certify_block(proposal.clone());
 
                                 for peer in self.peers.clone().peer_set {
                                     //TODO: decide who we should broadcast to
@@ -654,35 +1004,7 @@ impl StateTransition for Node {
                 //we already Rejected it and told the network
                 //TODO: enable this so rejections can continue just as well as acceptances
 
-                //Test because some nodes broadcast acceptance, but don't get the most updated block
-
-                //*** test remove: do we need to broadcast a block query upon reaching a proposal we already broadcasted
-                let local_block_id_option: Option<i64> = DB::get_latest_block_id();
-                match local_block_id_option {
-                    Some(local_block_id) => { // successfuly fetch block id
-
-                        /*
-                        for peer in self.peers.clone().peer_set {
-
-                            if Server::broadcast_block_query( ( local_block_id ),
-                                                               peer.clone().location,
-                                                               node_ip.clone()).is_ok() {
-                                println!("[determine_transition_step], broadcast_block_query SUCCESS 1...");
-                                //Ok(String::from("BLOCK BEHIND, QUERING TO SYNC"))
-                            } else {
-                                println!("[determine_transition_step], broadcast_block_query FAILED 1...");
-                            }
-
-                        }
-                        */
-
-
-                    },
-                    None => {
-
-                    }
-                }
-
+                //nodes that missed the latest block catch up through sync_check, at the start of every transition
 
                 for peer in self.peers.clone().peer_set {
                     //TODO: decide who we should broadcast to
@@ -757,6 +1079,12 @@ impl StateTransition for Node {
                             },
                             ProposalValidationResult::NotValidIncorrectProposalHash => {
                                 DB::update_proposal(proposal.clone(), "not_valid_incorrect_proposal_hash");
+                            },
+                            ProposalValidationResult::NotValidIncorrectSignature => {
+                                DB::update_proposal(proposal.clone(), "not_valid_incorrect_signature");
+                            },
+                            ProposalValidationResult::NotValidIncorrectCreator => {
+                                DB::update_proposal(proposal.clone(), "not_valid_incorrect_creator");
                             }
                         }
                     },
@@ -770,6 +1098,10 @@ impl StateTransition for Node {
             ProposalStatus::NotValidIncorrectProposalHash => {
                 //TODO:
             },
+            ProposalStatus::NotValidIncorrectSignature |
+            ProposalStatus::NotValidIncorrectCreator => {
+                //never respond to proposals we could not attribute to the elected creator
+            },
             ProposalStatus::ProposalStatusError => {
                 //TODO: throw error
             }
@@ -779,27 +1111,74 @@ impl StateTransition for Node {
 
 
 trait SyncCheck {
-    fn sync_check(&mut self, proposal: Proposal, node_ip: String) -> ();
+    fn sync_check(&mut self) -> bool;
 }
 
 impl SyncCheck for Node {
-    fn sync_check(&mut self, proposal: Proposal, node_ip: String) -> () {
-        let local_block_id_option: Option<i64> = DB::get_latest_block_id();
-        match local_block_id_option {
-            Some(local_block_id) => {
-                let current_block_by_id_option: Option<Block> = DB::get_block_by_block_id(local_block_id);
-                match current_block_by_id_option {
-                    Some(current_block_by_id) => {
-                        // TODO: sync check
-                    },
-                    None => {
-
+    /*
+    @name sync_check
+    @desc announce our height to every peer, and if a peer reported a
+          higher one, ask it for the next range of certified blocks.
+          In snapshot mode, a node with only genesis fetches headers and then the
+          state at the header tip instead. Returns true while we are behind
+    */
+    fn sync_check(&mut self) -> bool {
+        let node_ip: String = self.ip.to_string();
+        for peer in self.peers.clone().peer_set {
+            match Server::broadcast_height(peer.clone().location, node_ip.clone()) {
+                Ok(_) => {},
+                Err(err) => println!("[sync_check] {}", err)
+            }
+        }
+        let local_height: i64 = Sync::local_height();
+        if self.sync_mode == "snapshot" && local_height <= 0 {
+            match Sync::best_peer(local_height, Sync::get_peer_heights()) {
+                Some(peer_height) => {
+                    Sync::record_request(peer_height.peer_location.clone());
+                    let header_height: i64 = Sync::header_height();
+                    let query_result: Result<(), String> = match Sync::next_range(header_height, peer_height.peer_height) {
+                        Some((start, end)) => {
+                            println!("[sync_check] snapshot sync, requesting headers {} to {} from {}", start, end, peer_height.peer_location);
+                            Server::broadcast_header_query(start, end, peer_height.peer_location, node_ip)
+                        },
+                        None => match Sync::get_header(header_height) {
+                            Some(header) => {
+                                println!("[sync_check] snapshot sync, requesting state after block {} from {}", header_height, peer_height.peer_location);
+                                Server::broadcast_snapshot_query(header.header_block_hash, peer_height.peer_location, node_ip)
+                            },
+                            None => Err(String::from("no header at the header tip"))
+                        }
+                    };
+                    match query_result {
+                        Ok(_) => {},
+                        Err(err) => println!("[sync_check] snapshot sync query failed: {}", err)
                     }
+                    return true
+                },
+                None => return false
+            }
+        }
+        match Sync::best_peer(local_height, Sync::get_peer_heights()) {
+            Some(peer_height) => {
+                match Sync::next_range(local_height, peer_height.peer_height) {
+                    Some((start, end)) => {
+                        println!("[sync_check] local height {}, {} is at {}, requesting blocks {} to {}",
+                                 local_height,
+                                 peer_height.peer_location,
+                                 peer_height.peer_height,
+                                 start,
+                                 end);
+                        Sync::record_request(peer_height.peer_location.clone());
+                        match Server::broadcast_block_query(start, end, peer_height.peer_location, node_ip) {
+                            Ok(_) => {},
+                            Err(err) => println!("[sync_check] block query failed: {}", err)
+                        }
+                        true
+                    },
+                    None => false
                 }
             },
-            None => {
-
-            }
+            None => false
         }
     }
 }
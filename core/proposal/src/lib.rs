@@ -23,6 +23,7 @@ use db::{DB,
          FileDirectoryReader,
          DBReadProposalPeerStatus,
          DBWriteProposalPeerStatus,
+         DBGenesis,
          LogDebug};
 
 use block::{Block,
@@ -35,8 +36,17 @@ use block::{Block,
             BlockIDGenerator};
 
 use timestamp::{Timestamp, NewTimestamp, StringToTimestamp};
+use mempool::{Mempool, PendingTransactions, AdmitTransaction};
+use transaction::{Transaction};
 use hash::{Hasher, CalculateSHA256Hash};
 use executor::{Executor, ExecuteMacro};
+use validator::{Validator, Identity, IdentityManager, ValidatorSchedule, ElectValidator};
+use keystore::{Keystore, KeystoreSigner};
+use signature::{DigitalSignature,
+                SignatureType,
+                SignatureFormat,
+                Verifier,
+                Signature};
 
 /*
 @name Proposal
@@ -49,6 +59,9 @@ pub struct Proposal {
     pub proposal_hash: String,
     pub proposal_time: Timestamp,
     pub proposal_sender: String,
+    pub proposal_creator: i32,
+    pub proposal_chain_id: String,
+    pub proposal_signature: String,
     pub proposal_block: Block
 }
 
@@ -71,6 +84,8 @@ pub enum ProposalStatus {
     NotValid,    //proposals that do not match any of the above enum values
     NotValidIncorrectNextBlockIndex,    //proposals that do not have the correct next block index
     NotValidIncorrectProposalHash,    //proposals that do not hash to be correct
+    NotValidIncorrectSignature,    //proposals not signed by the registered key of their creator
+    NotValidIncorrectCreator,    //proposals created by a node that was not elected for the block
     ProposalStatusError   //DEFAULT ENUM ERROR
 }
 
@@ -94,6 +109,8 @@ impl StatusToString for Proposal {
             ProposalStatus::NotValid    => "notvalid",
             ProposalStatus::NotValidIncorrectNextBlockIndex => "not_valid_incorrect_next_block_id",
             ProposalStatus::NotValidIncorrectProposalHash => "not_valid_incorrect_proposal_hash",
+            ProposalStatus::NotValidIncorrectSignature => "not_valid_incorrect_signature",
+            ProposalStatus::NotValidIncorrectCreator => "not_valid_incorrect_creator",
             _ => "proposal_status_error"
         }
     }
@@ -127,6 +144,8 @@ impl StringToStatus for Proposal {
             "notvalid" =>            ProposalStatus::NotValid,
             "not_valid_incorrect_next_block_id" => ProposalStatus::NotValidIncorrectNextBlockIndex,
             "not_valid_incorrect_proposal_hash" => ProposalStatus::NotValidIncorrectProposalHash,
+            "not_valid_incorrect_signature" => ProposalStatus::NotValidIncorrectSignature,
+            "not_valid_incorrect_creator" => ProposalStatus::NotValidIncorrectCreator,
             _ =>            ProposalStatus::ProposalStatusError
         }
     }
@@ -168,6 +187,9 @@ impl JsonConverter for Proposal {
             "proposal_hash" => proposal.proposal_hash,
             "proposal_time" => proposal.proposal_time.timestamp,
             "proposal_sender" => proposal.proposal_sender,
+            "proposal_creator" => proposal.proposal_creator,
+            "proposal_chain_id" => proposal.proposal_chain_id,
+            "proposal_signature" => proposal.proposal_signature,
             "proposal_block" => Block::to_json(proposal.proposal_block),
         };
         println!("Proposal, to_json, data.dump(): {}", data.dump());
@@ -186,6 +208,12 @@ impl JsonConverter for Proposal {
                 let proposal_time: Option<Timestamp> = Timestamp::string_to_timestamp(unchecked_proposal_timestamp);
                 if proposal_time.is_some() {
                     let proposal_sender: String = payload["proposal_sender"].to_string();
+                    let proposal_creator: i32 = match payload["proposal_creator"].as_i32() {
+                        Some(creator) => creator,
+                        None => -1
+                    };
+                    let proposal_chain_id: String = String::from(payload["proposal_chain_id"].as_str().unwrap_or(""));
+                    let proposal_signature: String = payload["proposal_signature"].to_string();
                     let block_string_to_check: Result<Block, String> = Block::from_string( payload["proposal_block"].to_string() );
                     match block_string_to_check {
                         Ok(block) => {
@@ -196,6 +224,9 @@ impl JsonConverter for Proposal {
                                 proposal_hash: proposal_hash,
                                 proposal_time: proposal_time.unwrap(),
                                 proposal_sender: proposal_sender,
+                                proposal_creator: proposal_creator,
+                                proposal_chain_id: proposal_chain_id,
+                                proposal_signature: proposal_signature,
                                 proposal_block: proposal_block
                             })
                         },
@@ -334,6 +365,9 @@ impl UpdateProposalInDB for DB {
                             proposal_hash: String::from( all_proposals[proposal.proposal_id.to_string()]["proposal_hash"].as_str().unwrap() ),
                             proposal_time: Timestamp::string_to_timestamp(String::from(all_proposals[proposal.proposal_id.to_string()]["proposal_time"].as_str().unwrap())).unwrap(),
                             proposal_sender: String::from( all_proposals[proposal.proposal_id.to_string()]["proposal_sender"].as_str().unwrap() ),
+                            proposal_creator: proposal.proposal_creator,
+                            proposal_chain_id: proposal.proposal_chain_id.clone(),
+                            proposal_signature: proposal.proposal_signature.clone(),
                             proposal_block: block
                         };
                         let parsed = json::parse( &format!(r#"{}"#, Proposal::to_json(altered_proposal.clone()) ) );
@@ -599,6 +633,8 @@ impl WriteProposalToDB for DB {
                         "proposal_hash" => proposal.proposal_hash,
                         "proposal_time" => proposal.proposal_time.timestamp,
                         "proposal_sender" => proposal.proposal_sender,
+                        "proposal_creator" => proposal.proposal_creator,
+                        "proposal_signature" => proposal.proposal_signature,
                         "proposal_block" => Block::to_json(proposal.proposal_block)
                     };
                     let pindex_insert_result: Result<String, Error> = match parsed["proposals"]
@@ -750,23 +786,74 @@ impl UpdateProposal for Proposal {
 
 /*
     @name HashProposal
-    @desc
+    @desc the hash a creator signs, over the proposal fields, the chain id
+          and the block hash as a JSON object with keys inserted in this order
 */
-trait HashProposal {
-    fn hash_proposal(calculated_proposal_id: i32, new_proposal_sender: String, ts: Timestamp) -> String;
+pub trait HashProposal {
+    fn hash_proposal(calculated_proposal_id: i32, new_proposal_sender: String, proposal_creator: i32, ts: Timestamp, chain_id: String, block_hash: String) -> String;
 }
 
 impl HashProposal for Proposal {
-    fn hash_proposal(calculated_proposal_id: i32, new_proposal_sender: String, ts: Timestamp) -> String {
-        // TODO URGENT: include other variables when hashing proposal
-        let raw_str: String = format!("{}{}{}", calculated_proposal_id, new_proposal_sender, ts.timestamp);
-        let str_to_hash: &str = raw_str.as_str();
-        let string_to_hash: String = String::from( str_to_hash );
-        let new_proposal_hash: String = Hasher::calculate_sha256( string_to_hash );
+    fn hash_proposal(calculated_proposal_id: i32, new_proposal_sender: String, proposal_creator: i32, ts: Timestamp, chain_id: String, block_hash: String) -> String {
+        let content: JsonValue = object!{
+            "proposal_id" => calculated_proposal_id,
+            "proposal_sender" => new_proposal_sender,
+            "proposal_creator" => proposal_creator,
+            "proposal_time" => ts.timestamp,
+            "chain_id" => chain_id,
+            "block_hash" => block_hash
+        };
+        let new_proposal_hash: String = Hasher::calculate_sha256( content.dump() );
         new_proposal_hash
     }
 }
 
+/*
+    @name SignProposal
+    @desc sign a proposal hash with this node's keystore key
+*/
+trait SignProposal {
+    fn sign_proposal_hash(proposal_hash: String) -> Result<String, String>;
+}
+
+impl SignProposal for Proposal {
+    fn sign_proposal_hash(proposal_hash: String) -> Result<String, String> {
+        match Keystore::sign(proposal_hash.as_bytes()) {
+            Ok(signature) => Ok(signature),
+            Err(err) => Err(format!("sign_proposal_hash ERROR: could not sign proposal hash: {}", err))
+        }
+    }
+}
+
+/*
+    @name VerifyProposalSignature
+    @desc verify a proposal was signed by the registered key of its creator
+*/
+pub trait VerifyProposalSignature {
+    fn verify_proposal_signature(proposal: Proposal) -> bool;
+}
+
+impl VerifyProposalSignature for Proposal {
+    fn verify_proposal_signature(proposal: Proposal) -> bool {
+        match Validator::get_validator_at(proposal.proposal_block.block_id, proposal.proposal_creator) {
+            Some(validator) => {
+                let digital_signature: DigitalSignature = DigitalSignature {
+                    signature_type: validator.validator_key_type.clone(),
+                    format_type: SignatureFormat::BYTES
+                };
+                Signature::verify_with_key(digital_signature,
+                                           proposal.proposal_signature,
+                                           proposal.proposal_hash.as_bytes(),
+                                           validator.validator_public_key).is_ok()
+            },
+            None => {
+                println!("verify_proposal_signature, no registered validator for creator: {}", proposal.proposal_creator);
+                false
+            }
+        }
+    }
+}
+
 /*
 @name NewProposal
 @desc trait to create a new proposal
@@ -797,17 +884,36 @@ impl NewProposal for Proposal {
         let calculated_proposal_id: i32 = new_proposal_id + 1;
         let new_proposal_status: ProposalStatus = ProposalStatus::Pending;
         let new_proposal_timestamp: Option<Timestamp> = Timestamp::new();
+        //proposals are signed by this node, so it needs a registered identity to create one
+        let identity: Identity = match Identity::read_identity() {
+            Some(identity) => identity,
+            None => {
+                println!("Proposal create ERROR: no node identity to sign the proposal with");
+                return None
+            }
+        };
         match new_proposal_timestamp {
             Some(ts) => {
                 let new_proposal_sender: String = request_origin;
-
-                // TODO: only hashing proposal id, sender, and timestamp.
-                // TODO: must add other components
-                let new_proposal_hash: String = Self::hash_proposal(calculated_proposal_id.clone(), new_proposal_sender.clone(), ts.clone());
-                //TODO: CREATE NEW BLOCK
-                let new_proposal_block: Result<Block, String> = Block::new(new_proposal_hash.clone());
+                //the block is hashed independently of the proposal, so create it first
+                let new_proposal_block: Result<Block, String> = Block::new(String::new(), Mempool::pending_transactions());
                 match new_proposal_block {
-                    Ok(block) => {
+                    Ok(mut block) => {
+                        let new_proposal_chain_id: String = DB::read_chain_id().unwrap_or(String::from(""));
+                        let new_proposal_hash: String = Self::hash_proposal(calculated_proposal_id.clone(),
+                                                                            new_proposal_sender.clone(),
+                                                                            identity.identity_id,
+                                                                            ts.clone(),
+                                                                            new_proposal_chain_id.clone(),
+                                                                            block.block_hash.clone());
+                        block.proposal_hash = new_proposal_hash.clone();
+                        let new_proposal_signature: String = match Self::sign_proposal_hash(new_proposal_hash.clone()) {
+                            Ok(signature) => signature,
+                            Err(err) => {
+                                println!("Proposal create ERROR: {}", err);
+                                return None
+                            }
+                        };
                         //Increment the local proposal id
                         let new_proposal: Proposal = Proposal {
                             proposal_id: calculated_proposal_id,
@@ -815,6 +921,9 @@ impl NewProposal for Proposal {
                             proposal_hash: new_proposal_hash,
                             proposal_time: ts,
                             proposal_sender: new_proposal_sender,
+                            proposal_creator: identity.identity_id,
+                            proposal_chain_id: new_proposal_chain_id,
+                            proposal_signature: new_proposal_signature,
                             proposal_block: block
                         };
                         //TODO: create proposal attempt in DB
@@ -940,7 +1049,9 @@ pub enum ProposalValidationResult {
     Valid,
     NotValid,
     NotValidIncorrectNextBlockIndex,
-    NotValidIncorrectProposalHash
+    NotValidIncorrectProposalHash,
+    NotValidIncorrectSignature,
+    NotValidIncorrectCreator
 }
 
 pub trait ProposalValidator {
@@ -1058,13 +1169,13 @@ impl ProposalValidator for Proposal {
                     }
 
                     //TODO: breakout into modular, verify_proposal_hash
-                    //TODO: calculate the hash of the proposal (see below)
-                    let string_to_hash: String = String::from( format!("{}{}{}", submitted_proposal.proposal_id,
-                                                                                 submitted_proposal.proposal_sender,
-                                                                                 submitted_proposal.proposal_time.timestamp).as_str() ) ;
-
-                    let expected_hash: String = submitted_proposal.proposal_hash;
-                    let submitted_proposal_hash: String = Hasher::calculate_sha256( string_to_hash );
+                    let expected_hash: String = submitted_proposal.proposal_hash.clone();
+                    let submitted_proposal_hash: String = Self::hash_proposal(submitted_proposal.proposal_id,
+                                                                              submitted_proposal.proposal_sender.clone(),
+                                                                              submitted_proposal.proposal_creator,
+                                                                              submitted_proposal.proposal_time.clone(),
+                                                                              submitted_proposal.proposal_chain_id.clone(),
+                                                                              submitted_proposal.proposal_block.block_hash.clone());
                     //TODO: validate the proposal_hash provided by alice against the proposal_hash bob just calculated
                     match submitted_proposal_hash {
                         _ if submitted_proposal_hash == expected_hash => {
@@ -1076,6 +1187,23 @@ impl ProposalValidator for Proposal {
                             return Ok(ProposalValidationResult::NotValidIncorrectProposalHash)
                         }
                     }
+
+                    //the proposal hash must be signed by the creator's registered key
+                    if !Self::verify_proposal_signature(submitted_proposal.clone()) {
+                        println!("ERROR: proposal signature not valid for creator: {}", submitted_proposal.proposal_creator);
+                        return Ok(ProposalValidationResult::NotValidIncorrectSignature)
+                    }
+
+                    //only the elected creator may propose the next block, no block exists yet to elect from when bootstrapping
+                    if current_block_id_result >= 0 {
+                        match Self::get_elected_proposal_creator_id(current_block_id_result) {
+                            Some(elected_creator_id) if elected_creator_id == submitted_proposal.proposal_creator as i64 => {},
+                            _ => {
+                                println!("ERROR: proposal creator {} was not elected for block {}", submitted_proposal.proposal_creator, submitted_proposal.proposal_block.block_id);
+                                return Ok(ProposalValidationResult::NotValidIncorrectCreator)
+                            }
+                        }
+                    }
                     //TODO: What is the current block_hash of our highest block?
                     //TODO: If the block_hash of bob's highest block is NOT equal to the block_parent_hash of the submitted proposal's proposal_block, bob rejects the block, and sets the proposal to NotValid in the proposal index
                     //TODO: if all of the above does not reject the proposal, bob accepts alice's submitted proposal, responds to alice with "acceptance", and updates the proposal_index to accepted for the proposal_id
@@ -1143,6 +1271,17 @@ impl CompareWithoutStatus for Proposal {
             }
         }
 
+        //test proposal_creator and proposal_signature
+        match proposal_left.proposal_creator == proposal_right.proposal_creator
+              && proposal_left.proposal_signature == proposal_right.proposal_signature {
+            true => {
+
+            },
+            false => {
+                return false
+            }
+        }
+
         //test proposal_block
         match proposal_left.proposal_block == proposal_right.proposal_block {
             true => {
@@ -1176,7 +1315,10 @@ impl ValidateProposalBlock for Proposal {
                 if (proposal.proposal_status != ProposalStatus::Committed) {
 
                     DB::write_proposal_debug( String::from( format!("trying to commit proposal: {} With block ID {}", self.clone().proposal_id, self.clone().proposal_block.block_id) ) );
-                    Block::commit_if_valid(self.clone().proposal_block)
+                    let abandoned: Vec<Transaction> = Block::commit_if_valid(self.clone().proposal_block)?;
+                    let reinserted: usize = Mempool::reinsert(abandoned);
+                    println!("validate_proposal_block: {} abandoned transactions back in the mempool", reinserted);
+                    Ok(())
 
                 } else {
                     Err(String::from("Error: validate_proposal_block, latest proposal is COMMITTED"))
@@ -1255,6 +1397,13 @@ pub trait CalculateProposalCreatorID {
         @example node_is is CONGRUENT to current_block_id % peer_set.len()
     */
     fn calculate_next_proposal_creator_id(peer_length: usize, latest_block_id: i64) -> i64;
+
+    /*
+        @name get_elected_proposal_creator_id
+        @desc elect the creator of the block after latest_block_id, from the
+              validators active at that height
+    */
+    fn get_elected_proposal_creator_id(latest_block_id: i64) -> Option<i64>;
 }
 
 impl CalculateProposalCreatorID for Proposal {
@@ -1265,6 +1414,17 @@ impl CalculateProposalCreatorID for Proposal {
                  latest_block_id);
         Executor::execute_proposal_creator_election(peer_length, latest_block_id)
     }
+
+    fn get_elected_proposal_creator_id(latest_block_id: i64) -> Option<i64> {
+        let validators: Vec<Validator> = Validator::get_validators_at(latest_block_id + 1);
+        if validators.is_empty() {
+            println!("get_elected_proposal_creator_id, ERROR: no registered validators");
+            None
+        } else {
+            Validator::elected_validator(validators, latest_block_id)
+                      .map(|validator| validator.validator_id as i64)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1274,7 +1434,10 @@ mod tests {
                 JsonConverter,
                 ProposalValidator,
                 ProposalValidationResult,
-                CalculateProposalCreatorID};
+                CalculateProposalCreatorID,
+                HashProposal,
+                StatusToString,
+                StringToStatus};
     use block::{Block, CreateNewBlock};
     use timestamp::{Timestamp, NewTimestamp};
 
@@ -1360,4 +1523,35 @@ mod tests {
         assert_eq!(Proposal::calculate_next_proposal_creator_id(number_of_peers, latest_block_id), expected_creator_id);
     }
 
+    #[test]
+    fn test_signature_and_creator_status_round_trip() {
+        let signature_status: &str = Proposal::string_from_status(ProposalStatus::NotValidIncorrectSignature);
+        let creator_status: &str = Proposal::string_from_status(ProposalStatus::NotValidIncorrectCreator);
+        assert_eq!(Proposal::status_from_string(signature_status), ProposalStatus::NotValidIncorrectSignature);
+        assert_eq!(Proposal::status_from_string(creator_status), ProposalStatus::NotValidIncorrectCreator);
+    }
+
+    #[test]
+    fn test_proposal_hash_covers_creator_and_block_hash() {
+        let ts: Timestamp = Timestamp { timestamp: String::from("0") };
+        let chain_id: String = String::from("africaos-test");
+        let original_hash: String = Proposal::hash_proposal(0, String::from("1"), 1, ts.clone(), chain_id.clone(), String::from("block hash"));
+        let other_creator_hash: String = Proposal::hash_proposal(0, String::from("1"), 2, ts.clone(), chain_id.clone(), String::from("block hash"));
+        let other_block_hash: String = Proposal::hash_proposal(0, String::from("1"), 1, ts.clone(), chain_id.clone(), String::from("other block hash"));
+        let other_chain_hash: String = Proposal::hash_proposal(0, String::from("1"), 1, ts.clone(), String::from("africaos-other"), String::from("block hash"));
+        assert_ne!(original_hash, other_creator_hash);
+        assert_ne!(original_hash, other_block_hash);
+        assert_ne!(original_hash, other_chain_hash);
+    }
+
+    #[test]
+    fn test_proposal_hash_fields_do_not_run_together() {
+        //proposal 1 from sender "23" and proposal 12 from sender "3" used to hash the same string
+        let ts: Timestamp = Timestamp { timestamp: String::from("0") };
+        let chain_id: String = String::from("africaos-test");
+        let left: String = Proposal::hash_proposal(1, String::from("23"), 1, ts.clone(), chain_id.clone(), String::from("block hash"));
+        let right: String = Proposal::hash_proposal(12, String::from("3"), 1, ts, chain_id, String::from("block hash"));
+        assert_ne!(left, right);
+    }
+
 }
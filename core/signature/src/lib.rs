@@ -13,11 +13,13 @@ You should have received a copy of the GNU Lesser General Public License
 along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
 */
 use ring::{
-    rand,
+    digest,
+    hmac,
+    rand::{self, SecureRandom},
     signature::{self, KeyPair}
 };
+use bip39::Mnemonic;
 
-use std::path::Path;
 use std::convert::TryInto;
 use encode::{Encoder, RawBytesDecode, RawBytesEncode};
 use std::io::{Error, ErrorKind};
@@ -34,7 +36,6 @@ pub enum SignatureFormat {
     DISK
 }
 
-
 /*
 @name Verifier for Signature
 @description Signing and verifying with RSA (PKCS#1 1.5 padding)
@@ -45,8 +46,12 @@ pub enum SignatureFormat {
 pub enum SignatureError {
    IO(std::io::Error),
    BadPrivateKey,
+   BadPublicKey,
    OOM,
    BadSignature,
+   BadMnemonic,
+   BadDerivationPath,
+   BadAddress,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -55,6 +60,26 @@ pub struct DigitalSignature {
     pub format_type: SignatureFormat
 }
 
+/*
+@name PrivateKey
+@description a private key and its type, PKCS#1 DER for RSA and PKCS#8 for Ed25519
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrivateKey {
+    pub key_type: SignatureType,
+    pub key_bytes: Vec<u8>
+}
+
+/*
+@name PublicKey
+@description a public key and its type, DER RSAPublicKey for RSA and the raw 32 bytes for Ed25519
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublicKey {
+    pub key_type: SignatureType,
+    pub key_bytes: Vec<u8>
+}
+
 trait SignVerify {
     fn sign_and_verify(signature_type: SignatureType, format_type: SignatureFormat) -> bool;
 }
@@ -82,220 +107,469 @@ pub struct Signature {
 @name Signer
 @description
 */
-trait Signer {
+pub trait Signer {
     /*
     @name sign
-    @description
+    @description sign content with the caller's private key, returns the base64 signature
     */
-    fn sign(signature: DigitalSignature, content: &'static str) -> Result<String, SignatureError>;
+    fn sign(private_key: &PrivateKey, content: &[u8]) -> Result<String, SignatureError>;
 
     /*
     @name sign_RSA
-    @description
+    @description sign with a PKCS#1 DER private key, using PKCS#1 1.5 padding and SHA256
     */
-    fn sign_RSA(private_key_path: &std::path::Path, public_key_path: &std::path::Path, content: &'static str) -> Result<String, SignatureError>;
+    fn sign_RSA(private_key: &[u8], content: &[u8]) -> Result<Vec<u8>, SignatureError>;
 
     /*
     @name sign_Ed25519
-    @description
+    @description sign with a PKCS#8 Ed25519 private key
     */
-    fn sign_Ed25519() -> Result<(), ring::error::Unspecified>;
+    fn sign_Ed25519(private_key: &[u8], content: &[u8]) -> Result<Vec<u8>, SignatureError>;
 }
 
 impl Signer for Signature {
-    fn sign(signature: DigitalSignature, content: &'static str) -> Result<String, SignatureError>{
-        match signature.signature_type {
-            RSA => {
-                let private_key_path = Path::new("./keys/private.der");
-                let public_key_path = Path::new("./keys/public.der");
-
-                Self::sign_RSA(private_key_path, public_key_path, content)
-            },
-            ED25519 => {
-                //Self::sign_Ed25519();
-                Ok(String::from("dssdds"))
+    fn sign(private_key: &PrivateKey, content: &[u8]) -> Result<String, SignatureError> {
+        let signature_bytes: Vec<u8> = match private_key.key_type {
+            SignatureType::RSA => Self::sign_RSA(&private_key.key_bytes, content)?,
+            SignatureType::ED25519 => Self::sign_Ed25519(&private_key.key_bytes, content)?
+        };
+        match Encoder::bytes_to_base64( signature_bytes ) {
+            Ok(sig) => Ok(sig),
+            Err(_) => {
+                let encoder_bytes_to_base64_error = Error::new(ErrorKind::Other, "sign: Encoder::bytes_to_base64( signature ) returned error");
+                Err(SignatureError::IO(encoder_bytes_to_base64_error))
             }
         }
     }
 
-    fn sign_RSA(private_key_path: &std::path::Path, public_key_path: &std::path::Path, content: &'static str) -> Result<String, SignatureError> {
-       // Create an `RsaKeyPair` from the DER-encoded bytes. This example uses
-       // a 2048-bit key, but larger keys are also supported.
-       let private_key_der: Vec<u8> = read_file(private_key_path)?;
+    fn sign_RSA(private_key: &[u8], content: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        let key_pair = signature::RsaKeyPair::from_der(private_key).map_err(|_| SignatureError::BadPrivateKey)?;
+        let rng = rand::SystemRandom::new();
+        //the signature is as long as the key's public modulus
+        let mut signature_bytes: Vec<u8> = vec![0; key_pair.public_modulus_len()];
+        key_pair.sign(&signature::RSA_PKCS1_SHA256, &rng, content, &mut signature_bytes).map_err(|_| SignatureError::OOM)?;
+        Ok(signature_bytes)
+    }
 
-       let key_pair = signature::RsaKeyPair::from_der(&private_key_der).map_err(|_| SignatureError::BadPrivateKey)?;
+    fn sign_Ed25519(private_key: &[u8], content: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        let key_pair: signature::Ed25519KeyPair = ed25519_key_pair(private_key)?;
+        Ok(key_pair.sign(content).as_ref().to_vec())
+    }
+}
 
-       // Sign the message, using PKCS#1 v1.5 padding and the
-       // SHA256 digest algorithm.
-       let content_string: &'static str = content; //"HELLO WORKD";
 
-       // get length of content
-       let i: usize = content_string.len();
 
-       //convert str into bytes
-       let bytes_string: &[u8] = content_string.as_bytes();
-       //.try_into().expect("sign_RSA: could not convert content_string into array slice");
+/*
+@name Verifier
+@description
+*/
+pub trait Verifier {
+    /*
+    @name verify
+    @description verify a signature over content against the signer's public key
+    */
+    fn verify(public_key: &PublicKey, signature: &[u8], content: &[u8]) -> Result<String, SignatureError>;
 
-       //create a box
-       let bytes_box: Box<&[u8]> = Box::from(bytes_string);
+    /*
+    @name verify_rsa
+    @description verify against a DER RSAPublicKey
+    */
+    fn verify_rsa(public_key: &[u8], signature: &[u8], content: &[u8]) -> Result<String, SignatureError>;
 
-       //leak box for &'static
-       let MESSAGE: &'static [u8] = Box::leak(bytes_box);
+    /*
+    @name verify_ed25519
+    @description verify against a raw 32 byte Ed25519 public key
+    */
+    fn verify_ed25519(public_key: &[u8], signature: &[u8], content: &[u8]) -> Result<String, SignatureError>;
 
-       let rng = rand::SystemRandom::new();
+    /*
+    @name verify_with_key
+    @description verify a base64 signature over content against a base64 public key,
+                 as they are sent over the wire
+    */
+    fn verify_with_key(ds: DigitalSignature, signature: String, content: &[u8], public_key: String) -> Result<String, SignatureError>;
+}
 
-       //Returns the length in bytes of the key pair's public modulus.
-       let mut signature: Vec<u8> = vec![0; key_pair.public_modulus_len()];
+/*
+@name Verifier for Signature
+@description
+*/
+impl Verifier for Signature {
 
-       //key_pair.sign(&signature.clone()::RSA_PKCS1_SHA256, &rng, MESSAGE, &mut signature.clone()).map_err(|_| SignatureError::OOM)?;
-       key_pair.sign(&signature::RSA_PKCS1_SHA256, &rng, MESSAGE, &mut signature).map_err(|_| SignatureError::OOM)?;
+    fn verify(public_key: &PublicKey, signature: &[u8], content: &[u8]) -> Result<String, SignatureError> {
+        match public_key.key_type {
+            SignatureType::RSA => Self::verify_rsa(&public_key.key_bytes, signature, content),
+            SignatureType::ED25519 => Self::verify_ed25519(&public_key.key_bytes, signature, content)
+        }
+    }
 
-       match Encoder::bytes_to_base64( signature ) {
-           Ok(sig) => {
-               println!("SUCCCESS: {}", sig);
-               Ok( sig )
-           },
-           Err(error_message) => {
-               println!("ERROR: {}", error_message);
+    fn verify_rsa(public_key: &[u8], signature: &[u8], content: &[u8]) -> Result<String, SignatureError> {
+        let public_key = signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, public_key);
+        match public_key.verify(content, signature) {
+            Ok(_) => Ok(String::from("Verification ok")),
+            Err(_) => Err(SignatureError::BadSignature)
+        }
+    }
 
-               // errors can be created from strings
-               let encoder_bytes_to_base64_error = Error::new(ErrorKind::Other, "Encoder::bytes_to_base64( signature ) returned error");
-               Err(SignatureError::IO(encoder_bytes_to_base64_error))
-           }
-       }
+    fn verify_ed25519(public_key: &[u8], signature: &[u8], content: &[u8]) -> Result<String, SignatureError> {
+        let public_key = signature::UnparsedPublicKey::new(&signature::ED25519, public_key);
+        match public_key.verify(content, signature) {
+            Ok(_) => Ok(String::from("Verification ok")),
+            Err(_) => Err(SignatureError::BadSignature)
+        }
+    }
 
-       //key_pair.sign(&signature::RSA_PKCS1_SHA256, &rng, MESSAGE, &mut signature).map_err(|_| SignatureError::OOM)?;
+    fn verify_with_key(ds: DigitalSignature, signature_string: String, content: &[u8], public_key: String) -> Result<String, SignatureError> {
+        let signature_bytes: Vec<u8> = Encoder::base64_to_bytes(signature_string).map_err(|_| SignatureError::BadSignature)?;
+        let public_key: PublicKey = Self::public_key_from_base64(ds.signature_type, public_key)?;
+        Self::verify(&public_key, &signature_bytes, content)
+    }
 
-       // Verify the signature.
-       //let public_key = signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, read_file(public_key_path)?);
+}
 
-       //public_key.verify(MESSAGE, &signature).map_err(|_| SignatureError::BadSignature)
+/*
+@name KeyGenerator
+@description create and read keys
+*/
+pub trait KeyGenerator {
+    /*
+    @name generate_key
+    @description write a new private key to private_key_path, and return it.
+                 RSA keys are made with openssl, see keys/Makefile
+    */
+    fn generate_key(signature_type: SignatureType, private_key_path: &std::path::Path) -> Result<PrivateKey, SignatureError>;
+
+    /*
+    @name read_private_key
+    @description read the private key stored at private_key_path
+    */
+    fn read_private_key(signature_type: SignatureType, private_key_path: &std::path::Path) -> Result<PrivateKey, SignatureError>;
+
+    /*
+    @name public_key
+    @description the public half of a private key
+    */
+    fn public_key(private_key: &PrivateKey) -> Result<PublicKey, SignatureError>;
+
+    /*
+    @name public_key_to_base64
+    @description a public key as it is written in transactions and validators
+    */
+    fn public_key_to_base64(public_key: &PublicKey) -> Result<String, SignatureError>;
 
+    /*
+    @name public_key_from_base64
+    @description read a public key written by public_key_to_base64
+    */
+    fn public_key_from_base64(signature_type: SignatureType, public_key: String) -> Result<PublicKey, SignatureError>;
+}
+
+impl KeyGenerator for Signature {
+    fn generate_key(signature_type: SignatureType, private_key_path: &std::path::Path) -> Result<PrivateKey, SignatureError> {
+        match signature_type {
+            SignatureType::RSA => {
+                let unsupported_key_error = Error::new(ErrorKind::Other, "generate_key: RSA keys are generated with openssl");
+                Err(SignatureError::IO(unsupported_key_error))
+            },
+            SignatureType::ED25519 => {
+                let rng = rand::SystemRandom::new();
+                let pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng).map_err(|_| SignatureError::OOM)?;
+                std::fs::write(private_key_path, pkcs8_bytes.as_ref()).map_err(|e| SignatureError::IO(e))?;
+                Ok(PrivateKey {
+                    key_type: SignatureType::ED25519,
+                    key_bytes: pkcs8_bytes.as_ref().to_vec()
+                })
+            }
+        }
     }
 
-    fn sign_Ed25519() -> Result<(), ring::error::Unspecified> {
-        let rng = rand::SystemRandom::new();
-        let pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng)?;
+    fn read_private_key(signature_type: SignatureType, private_key_path: &std::path::Path) -> Result<PrivateKey, SignatureError> {
+        Ok(PrivateKey {
+            key_type: signature_type,
+            key_bytes: read_file(private_key_path)?
+        })
+    }
 
-        // Normally the application would store the PKCS#8 file persistently. Later
-        // it would read the PKCS#8 file from persistent storage to use it.
+    fn public_key(private_key: &PrivateKey) -> Result<PublicKey, SignatureError> {
+        let public_key_bytes: Vec<u8> = match private_key.key_type {
+            SignatureType::RSA => {
+                let key_pair = signature::RsaKeyPair::from_der(&private_key.key_bytes).map_err(|_| SignatureError::BadPrivateKey)?;
+                key_pair.public_key().as_ref().to_vec()
+            },
+            SignatureType::ED25519 => {
+                ed25519_key_pair(&private_key.key_bytes)?.public_key().as_ref().to_vec()
+            }
+        };
+        Ok(PublicKey {
+            key_type: private_key.key_type.clone(),
+            key_bytes: public_key_bytes
+        })
+    }
 
-        let key_pair = signature::Ed25519KeyPair::from_pkcs8(pkcs8_bytes.as_ref())?;
+    fn public_key_to_base64(public_key: &PublicKey) -> Result<String, SignatureError> {
+        Encoder::bytes_to_base64(public_key.key_bytes.clone()).map_err(|_| SignatureError::BadPublicKey)
+    }
 
-        // Sign the message "hello, world".
-        const MESSAGE: &[u8] = b"hello, world";
-        let sig = key_pair.sign(MESSAGE);
+    fn public_key_from_base64(signature_type: SignatureType, public_key: String) -> Result<PublicKey, SignatureError> {
+        Ok(PublicKey {
+            key_type: signature_type,
+            key_bytes: Encoder::base64_to_bytes(public_key).map_err(|_| SignatureError::BadPublicKey)?
+        })
+    }
+}
 
-        // Normally an application would extract the bytes of the signature and
-        // send them in a protocol message to the peer(s). Here we just get the
-        // public key key directly from the key pair.
-        let peer_public_key_bytes = key_pair.public_key().as_ref();
+/*
+@name SignatureTypeString
+@description the key type as it is written in transactions, identities and validators
+*/
+pub trait SignatureTypeString {
+    fn type_to_string(signature_type: SignatureType) -> &'static str;
+    fn type_from_string(type_string: &str) -> Option<SignatureType>;
+}
 
-        // Verify the signature of the message using the public key. Normally the
-        // verifier of the message would parse the inputs to this code out of the
-        // protocol message(s) sent by the signer.
-        let peer_public_key = signature::UnparsedPublicKey::new(&signature::ED25519, peer_public_key_bytes);
-        peer_public_key.verify(MESSAGE, sig.as_ref())?;
+impl SignatureTypeString for Signature {
+    fn type_to_string(signature_type: SignatureType) -> &'static str {
+        match signature_type {
+            SignatureType::RSA => "rsa",
+            SignatureType::ED25519 => "ed25519"
+        }
+    }
 
-        Ok(())
+    fn type_from_string(type_string: &str) -> Option<SignatureType> {
+        match type_string {
+            "rsa" => Some(SignatureType::RSA),
+            "ed25519" => Some(SignatureType::ED25519),
+            _ => None
+        }
     }
 }
 
 
 
 /*
-@name Verifier
-@description
+@desc HD_PURPOSE and HD_COIN_TYPE start every derivation path, m/44'/1'/account'/0'/index'.
+      SLIP-0044 has no AfricaOS entry, 1 is the coin type shared by test networks
 */
-pub trait Verifier {
+pub const HD_PURPOSE: u32 = 44;
+pub const HD_COIN_TYPE: u32 = 1;
+
+/*
+@desc HD_HARDENED marks a hardened index, Ed25519 derivation only has hardened children
+*/
+const HD_HARDENED: u32 = 0x8000_0000;
+
+/*
+@desc ED25519_PKCS8_V1_PREFIX is the PKCS#8 v1 header of a raw 32 byte Ed25519 seed,
+      the layout openssl writes, so derived keys read like generated ones
+*/
+const ED25519_PKCS8_V1_PREFIX: [u8; 16] = [0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06,
+                                           0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20];
+
+/*
+@desc ADDRESS_CHECKSUM_LEN bytes of the double sha256 of a public key hash end an address
+*/
+const ADDRESS_CHECKSUM_LEN: usize = 4;
+
+/*
+@name MnemonicPhrase
+@description BIP-39 English seed phrases, the phrase is the export of its entropy
+*/
+pub trait MnemonicPhrase {
     /*
-    @name verify
-    @description
+    @name generate_mnemonic
+    @description a new phrase of 12, 15, 18, 21 or 24 words
     */
-    fn verify(ds: DigitalSignature, signature: String, content: &'static str) -> Result<String, SignatureError>;
+    fn generate_mnemonic(word_count: usize) -> Result<String, SignatureError>;
 
     /*
-    @name verify_rsa
-    @description
+    @name mnemonic_from_entropy
+    @description export entropy as its phrase
+    */
+    fn mnemonic_from_entropy(entropy: &[u8]) -> Result<String, SignatureError>;
+
+    /*
+    @name mnemonic_to_entropy
+    @description import a phrase, its checksum word must match
+    */
+    fn mnemonic_to_entropy(phrase: &str) -> Result<Vec<u8>, SignatureError>;
+
+    /*
+    @name mnemonic_to_seed
+    @description the 64 byte seed keys are derived from, the passphrase may be empty
     */
-    fn verify_rsa(signature: Vec<u8>, content: &'static str) -> Result<String, SignatureError>;
+    fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, SignatureError>;
+}
+
+impl MnemonicPhrase for Signature {
+    fn generate_mnemonic(word_count: usize) -> Result<String, SignatureError> {
+        if word_count < 12 || word_count > 24 || word_count % 3 != 0 {
+            return Err(SignatureError::BadMnemonic)
+        }
+        let mut entropy: Vec<u8> = vec![0u8; word_count / 3 * 4];
+        rand::SystemRandom::new().fill(&mut entropy).map_err(|_| SignatureError::OOM)?;
+        Self::mnemonic_from_entropy(&entropy)
+    }
+
+    fn mnemonic_from_entropy(entropy: &[u8]) -> Result<String, SignatureError> {
+        Mnemonic::from_entropy(entropy).map(|mnemonic| mnemonic.to_string())
+                                       .map_err(|_| SignatureError::BadMnemonic)
+    }
+
+    fn mnemonic_to_entropy(phrase: &str) -> Result<Vec<u8>, SignatureError> {
+        Mnemonic::parse(phrase).map(|mnemonic| mnemonic.to_entropy())
+                               .map_err(|_| SignatureError::BadMnemonic)
+    }
+
+    fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, SignatureError> {
+        Mnemonic::parse(phrase).map(|mnemonic| mnemonic.to_seed(passphrase).to_vec())
+                               .map_err(|_| SignatureError::BadMnemonic)
+    }
 }
 
 /*
-@name Verifier for Signature
-@description
+@name HDKeyDerivation
+@description SLIP-0010 Ed25519 keys from a seed, one per account and index
 */
-impl Verifier for Signature {
-
-    fn verify(ds: DigitalSignature, signature_string: String, content: &'static str) -> Result<String, SignatureError> {
+pub trait HDKeyDerivation {
+    /*
+    @name derivation_path
+    @description the hardened path of a receiving key, m/44'/1'/account'/0'/index'
+    */
+    fn derivation_path(account: u32, index: u32) -> Result<Vec<u32>, SignatureError>;
 
-        match ds.signature_type {
-            RSA => {
+    /*
+    @name derive_seed_key
+    @description the raw 32 byte key and chain code at a path
+    */
+    fn derive_seed_key(seed: &[u8], path: &[u32]) -> Result<([u8; 32], [u8; 32]), SignatureError>;
 
-                // TODO: convert base64 to bytes
-                let mut signature_bytes: Result<Vec<u8>, String> = Encoder::base64_to_bytes(signature_string);
+    /*
+    @name derive_key
+    @description the Ed25519 private key of an account and index, as PKCS#8 like any other key
+    */
+    fn derive_key(seed: &[u8], account: u32, index: u32) -> Result<PrivateKey, SignatureError>;
+}
 
-                match signature_bytes {
-                    Ok(sig) => {
-                        return Self::verify_rsa(sig, content)
-                    },
-                    Err(msg) => {
-                        let verify_sig_error = Error::new(ErrorKind::Other, "verify: signature_bytes returned error");
-                        return Err(SignatureError::IO(verify_sig_error))
-                    }
-                }
+impl HDKeyDerivation for Signature {
+    fn derivation_path(account: u32, index: u32) -> Result<Vec<u32>, SignatureError> {
+        if account >= HD_HARDENED || index >= HD_HARDENED {
+            return Err(SignatureError::BadDerivationPath)
+        }
+        Ok(vec![HD_PURPOSE, HD_COIN_TYPE, account, 0, index].into_iter()
+                                                           .map(|step| step | HD_HARDENED)
+                                                           .collect())
+    }
 
-            },
-            ED25519 => {
-                //Self::sign_Ed25519();
-                // Ok(String::from("dssdds"))
-                let unsupported_sig_error = Error::new(ErrorKind::Other, "unsupported signature type");
-                return Err(SignatureError::IO(unsupported_sig_error))
+    fn derive_seed_key(seed: &[u8], path: &[u32]) -> Result<([u8; 32], [u8; 32]), SignatureError> {
+        let master_key: hmac::Key = hmac::Key::new(hmac::HMAC_SHA512, b"ed25519 seed");
+        let (mut key, mut chain_code) = split_hmac(hmac::sign(&master_key, seed))?;
+        for step in path {
+            if step & HD_HARDENED == 0 {
+                return Err(SignatureError::BadDerivationPath)
             }
+            let mut data: Vec<u8> = Vec::with_capacity(37);
+            data.push(0u8);
+            data.extend_from_slice(&key);
+            data.extend_from_slice(&step.to_be_bytes());
+            let child_key: hmac::Key = hmac::Key::new(hmac::HMAC_SHA512, &chain_code);
+            let (child, child_chain_code) = split_hmac(hmac::sign(&child_key, &data))?;
+            key = child;
+            chain_code = child_chain_code;
         }
-
+        Ok((key, chain_code))
     }
 
-    fn verify_rsa(signature: Vec<u8>, content: &'static str) -> Result<String, SignatureError> {
+    fn derive_key(seed: &[u8], account: u32, index: u32) -> Result<PrivateKey, SignatureError> {
+        let (key, _) = Self::derive_seed_key(seed, &Self::derivation_path(account, index)?)?;
+        let mut key_bytes: Vec<u8> = ED25519_PKCS8_V1_PREFIX.to_vec();
+        key_bytes.extend_from_slice(&key);
+        Ok(PrivateKey {
+            key_type: SignatureType::ED25519,
+            key_bytes: key_bytes
+        })
+    }
+}
 
-        let content_string: &'static str = content;
-        // get length of content
-        let i: usize = content_string.len();
-        //convert str into bytes
-        let bytes_string: &[u8] = content_string.as_bytes();
-        //create a box
-        let bytes_box: Box<&[u8]> = Box::from(bytes_string);
-        //leak box for &'static
-        let MESSAGE: &'static [u8] = Box::leak(bytes_box);
+/*
+@name AddressEncoding
+@description base58 addresses of a public key hash with a checksum, so a
+             mistyped address is refused before anything is sent to it
+*/
+pub trait AddressEncoding {
+    /*
+    @name public_key_hash
+    @description the sha256 hex of the base64 public key, what outputs are sent to
+    */
+    fn public_key_hash(public_key: &PublicKey) -> Result<String, SignatureError>;
+    fn encode_address(public_key_hash: &str) -> Result<String, SignatureError>;
 
-        //TODO: get public key by bytes
-        let public_key_path = Path::new("./keys/public.der");
-        //let public_key_bytes: Vec<u8> = read_file(public_key_path)?;
+    /*
+    @name decode_address
+    @description the public key hash of an address, if its checksum matches
+    */
+    fn decode_address(address: &str) -> Result<String, SignatureError>;
+}
 
-        // TODO: remove hardcoded key
-        let public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
+impl AddressEncoding for Signature {
+    fn public_key_hash(public_key: &PublicKey) -> Result<String, SignatureError> {
+        let public_key_base64: String = Self::public_key_to_base64(public_key)?;
+        Ok(to_hex(digest::digest(&digest::SHA256, public_key_base64.as_bytes()).as_ref()))
+    }
 
-        match Encoder::base64_to_bytes( String::from(public_key_base64) ) {
-            Ok(public_key_bytes) => {
-                //assert_eq!("dsd",base64_encoded)
-                //public_key_bytes
-                let public_key = signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, public_key_bytes);
-                match public_key.verify(MESSAGE, &signature).map_err(|_| SignatureError::BadSignature) {
-                    Ok(_) => Ok(String::from("Verification ok")),
-                    Err(_) => Err(SignatureError::BadSignature)
-                }
-            },
-            Err(error_message) => {
-                println!("ERROR: {}", error_message);
-                 Err(SignatureError::BadSignature)
-            }
+    fn encode_address(public_key_hash: &str) -> Result<String, SignatureError> {
+        let mut payload: Vec<u8> = from_hex(public_key_hash).ok_or(SignatureError::BadAddress)?;
+        if payload.len() != digest::SHA256_OUTPUT_LEN {
+            return Err(SignatureError::BadAddress)
         }
+        let checksum: Vec<u8> = address_checksum(&payload);
+        payload.extend_from_slice(&checksum);
+        Ok(bs58::encode(payload).into_string())
+    }
 
+    fn decode_address(address: &str) -> Result<String, SignatureError> {
+        let decoded: Vec<u8> = bs58::decode(address).into_vec().map_err(|_| SignatureError::BadAddress)?;
+        if decoded.len() != digest::SHA256_OUTPUT_LEN + ADDRESS_CHECKSUM_LEN {
+            return Err(SignatureError::BadAddress)
+        }
+        let (payload, checksum) = decoded.split_at(digest::SHA256_OUTPUT_LEN);
+        if address_checksum(payload) != checksum {
+            return Err(SignatureError::BadAddress)
+        }
+        Ok(to_hex(payload))
     }
+}
+
+fn split_hmac(tag: hmac::Tag) -> Result<([u8; 32], [u8; 32]), SignatureError> {
+    let (left, right) = tag.as_ref().split_at(32);
+    Ok((left.try_into().map_err(|_| SignatureError::BadDerivationPath)?,
+        right.try_into().map_err(|_| SignatureError::BadDerivationPath)?))
+}
 
+fn address_checksum(payload: &[u8]) -> Vec<u8> {
+    let first: digest::Digest = digest::digest(&digest::SHA256, payload);
+    digest::digest(&digest::SHA256, first.as_ref()).as_ref()[..ADDRESS_CHECKSUM_LEN].to_vec()
 }
 
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None
+    }
+    (0..hex.len()).step_by(2)
+                  .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+                  .collect()
+}
 
+/*
+@name ed25519_key_pair
+@description openssl writes PKCS#8 v1, without the public key, so it is not required
+*/
+fn ed25519_key_pair(private_key: &[u8]) -> Result<signature::Ed25519KeyPair, SignatureError> {
+    signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(private_key).map_err(|_| SignatureError::BadPrivateKey)
+}
 
 fn read_file(path: &std::path::Path) -> Result<Vec<u8>, SignatureError> {
     use std::io::Read;
@@ -308,38 +582,38 @@ fn read_file(path: &std::path::Path) -> Result<Vec<u8>, SignatureError> {
 
 #[cfg(test)]
 mod tests {
-    use ring::{
-        rand,
-        signature::{self, KeyPair},
-    };
-
     use super::{SignatureType,
                 SignatureFormat,
                 DigitalSignature,
+                PrivateKey,
+                PublicKey,
                 Signer,
                 Verifier,
                 Signature,
                 SignatureError,
-                RawBytesDecode,
-                read_file};
-
-    use encode::{Encoder, RawBytesEncode};
+                KeyGenerator,
+                SignatureTypeString,
+                MnemonicPhrase,
+                HDKeyDerivation,
+                AddressEncoding,
+                HD_HARDENED,
+                to_hex,
+                from_hex};
+
+    use encode::{Encoder, RawBytesDecode};
     use std::path::Path;
 
 
     #[test] // Signing and verifying with RSA
     fn test_sign_rsa_from_disk(){
 
-        let digital_signature: DigitalSignature = DigitalSignature {
-            signature_type: SignatureType::RSA,
-            format_type: SignatureFormat::DISK
-        };
+        let private_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("./test-fixtures/private.der")).unwrap();
 
-        let content: &str = "TEST";
+        let content: &[u8] = b"TEST";
         let signature_result: &str = "opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==";
 
         // sign for testing purposes
-        let test: Result<String, SignatureError> = Signature::sign(digital_signature, content);
+        let test: Result<String, SignatureError> = Signature::sign(&private_key, content);
 
         // assert signatures match
         assert_eq!( test.unwrap(), String::from(signature_result) );
@@ -349,31 +623,75 @@ mod tests {
     #[test]
     fn test_verify_rsa_from_disk(){
 
+        let content: &[u8] = b"TEST";
+        let signature_result: Vec<u8> = Encoder::base64_to_bytes(String::from("opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==")).unwrap();
+        let public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
+        let public_key: PublicKey = Signature::public_key_from_base64(SignatureType::RSA, String::from(public_key_base64)).unwrap();
+
+        let verification_result: Result<String, SignatureError> = Signature::verify(&public_key, &signature_result, content);
+        match verification_result {
+            Ok(result) => assert_eq!( String::from("Verification ok"), result ),
+            Err(_) => assert!(false)
+        }
+
+        // the public key of the stored private key is the one we verify with
+        let private_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("./test-fixtures/private.der")).unwrap();
+        assert_eq!(Signature::public_key(&private_key).unwrap(), public_key);
+
+        // the same signature does not hold for another key
+        let mut other_public_key: PublicKey = public_key.clone();
+        other_public_key.key_bytes[20] ^= 1;
+        assert!(Signature::verify(&other_public_key, &signature_result, content).is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_key(){
         let digital_signature: DigitalSignature = DigitalSignature {
             signature_type: SignatureType::RSA,
             format_type: SignatureFormat::DISK
         };
-
-        let content: &str = "TEST";
-        let signature_result: &str = "opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==";
         let public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
+        let content: String = format!("{}", "proposal hash to sign");
 
+        let private_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("./test-fixtures/private.der")).unwrap();
+        let signed: String = Signature::sign(&private_key, content.as_bytes()).unwrap();
+        let verified: Result<String, SignatureError> = Signature::verify_with_key(digital_signature.clone(), signed.clone(), content.as_bytes(), String::from(public_key_base64));
+        assert!(verified.is_ok());
 
-        match Encoder::base64_to_bytes( String::from(public_key_base64) ) {
-            Ok(public_key_bytes) => {
-                // TODO: pass public_key_bytes to to verify function
-                let verification_result: Result<String, SignatureError> = Signature::verify(digital_signature, String::from(signature_result), content);
+        let tampered: Result<String, SignatureError> = Signature::verify_with_key(digital_signature, signed, b"another proposal hash", String::from(public_key_base64));
+        assert!(tampered.is_err());
+    }
 
-                match verification_result {
-                    Ok(result) => assert_eq!( String::from("Verification ok"), result ),
-                    Err(_) => assert!(false)
-                }
-            },
-            Err(error_message) => {
-                println!("ERROR: {}", error_message);
-                assert!(false)
-            }
-        }
+    #[test]
+    fn test_sign_and_verify_ed25519(){
+        let content: &[u8] = b"TEST";
+
+        // a generated key, written and read back as PKCS#8
+        let key_path = std::env::temp_dir().join("africaos_test_ed25519.pk8");
+        let generated_key: PrivateKey = Signature::generate_key(SignatureType::ED25519, key_path.as_path()).unwrap();
+        assert_eq!(Signature::read_private_key(SignatureType::ED25519, key_path.as_path()).unwrap(), generated_key);
+        let _ = std::fs::remove_file(key_path);
+        let public_key: PublicKey = Signature::public_key(&generated_key).unwrap();
+        let signed: Vec<u8> = Encoder::base64_to_bytes(Signature::sign(&generated_key, content).unwrap()).unwrap();
+        assert!(Signature::verify(&public_key, &signed, content).is_ok());
+        assert!(Signature::verify(&public_key, &signed, b"TESTS").is_err());
+        let generated_public_key: String = Signature::public_key_to_base64(&Signature::public_key(&generated_key).unwrap()).unwrap();
+        let digital_signature: DigitalSignature = DigitalSignature {
+            signature_type: SignatureType::ED25519,
+            format_type: SignatureFormat::BYTES
+        };
+        let signed: String = Signature::sign(&generated_key, content).unwrap();
+        assert!(Signature::verify_with_key(digital_signature, signed.clone(), content, generated_public_key.clone()).is_ok());
+
+        // an Ed25519 signature is not an RSA one
+        let rsa_signature: DigitalSignature = DigitalSignature {
+            signature_type: SignatureType::RSA,
+            format_type: SignatureFormat::BYTES
+        };
+        assert!(Signature::verify_with_key(rsa_signature, signed, content, generated_public_key).is_err());
+
+        assert_eq!(Signature::type_from_string(Signature::type_to_string(SignatureType::ED25519)), Some(SignatureType::ED25519));
+        assert_eq!(Signature::type_from_string("dsa"), None);
     }
 
     #[test]
@@ -382,7 +700,7 @@ mod tests {
             MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB
         */
         // let expected_der_base64: String = String::from("MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB");
-        // let public_key_path = Path::new("./keys/public.der");
+        // let public_key_path = Path::new("./test-fixtures/public.der");
         // let public_key_bytes: Vec<u8> = read_file(public_key_path).unwrap();
         // match Encoder::base64_to_bytes( public_key_bytes ) {
         //     Ok(base64_encoded) => {
@@ -394,4 +712,58 @@ mod tests {
         // }
     }
 
+    #[test] // BIP-39 vector, entropy 00..00 and passphrase TREZOR
+    fn test_mnemonic_import_export(){
+        let phrase: String = Signature::mnemonic_from_entropy(&[0u8; 16]).unwrap();
+        assert_eq!(phrase, "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+        assert_eq!(Signature::mnemonic_to_entropy(phrase.as_str()).unwrap(), vec![0u8; 16]);
+        assert_eq!(to_hex(&Signature::mnemonic_to_seed(phrase.as_str(), "TREZOR").unwrap()),
+                   concat!("c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e5349553",
+                           "1f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"));
+
+        // the last word is a checksum
+        assert!(Signature::mnemonic_to_entropy("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon").is_err());
+
+        let generated: String = Signature::generate_mnemonic(24).unwrap();
+        assert_eq!(generated.split(" ").count(), 24);
+        assert!(Signature::generate_mnemonic(13).is_err());
+    }
+
+    #[test] // SLIP-0010 ed25519 test vector 1
+    fn test_hd_key_derivation(){
+        let seed: Vec<u8> = from_hex("000102030405060708090a0b0c0d0e0f").unwrap();
+        let (master_key, master_chain_code) = Signature::derive_seed_key(&seed, &[]).unwrap();
+        assert_eq!(to_hex(&master_key), "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7");
+        assert_eq!(to_hex(&master_chain_code), "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb");
+        let (child_key, _) = Signature::derive_seed_key(&seed, &[0 | HD_HARDENED]).unwrap();
+        assert_eq!(to_hex(&child_key), "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3");
+        assert!(Signature::derive_seed_key(&seed, &[0]).is_err());
+
+        // derived keys sign like generated ones, and each index is its own key
+        let first: PrivateKey = Signature::derive_key(&seed, 0, 0).unwrap();
+        let second: PrivateKey = Signature::derive_key(&seed, 0, 1).unwrap();
+        assert_eq!(Signature::derive_key(&seed, 0, 0).unwrap(), first);
+        assert_ne!(Signature::public_key(&first).unwrap(), Signature::public_key(&second).unwrap());
+        let content: &[u8] = b"TEST";
+        let signed: Vec<u8> = Encoder::base64_to_bytes(Signature::sign(&first, content).unwrap()).unwrap();
+        assert!(Signature::verify(&Signature::public_key(&first).unwrap(), &signed, content).is_ok());
+        assert!(Signature::derivation_path(HD_HARDENED, 0).is_err());
+    }
+
+    #[test]
+    fn test_address_checksum(){
+        let private_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("./test-fixtures/private.der")).unwrap();
+        let public_key_hash: String = Signature::public_key_hash(&Signature::public_key(&private_key).unwrap()).unwrap();
+        assert_eq!(public_key_hash, "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
+
+        let address: String = Signature::encode_address(public_key_hash.as_str()).unwrap();
+        assert_eq!(Signature::decode_address(address.as_str()).unwrap(), public_key_hash);
+
+        // one changed character fails the checksum
+        let mut altered: Vec<char> = address.chars().collect();
+        altered[5] = if altered[5] == '2' { '3' } else { '2' };
+        assert!(Signature::decode_address(altered.into_iter().collect::<String>().as_str()).is_err());
+        assert!(Signature::encode_address("not a hash").is_err());
+    }
+
 }
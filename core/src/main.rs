@@ -39,7 +39,7 @@ fn main() {
     //TODO: Abstract Parameters
     while let Some(param) = arg_iter.next(){
         println!("arg: {}", param);
-        let split_param_by_assignment: Vec<&str> = param.split("=").collect();
+        let split_param_by_assignment: Vec<&str> = param.splitn(2, "=").collect();
         if split_param_by_assignment.len() == 2 { //equal is a binary operator
             let param_key: &str = split_param_by_assignment[0];
             let param_value: &str = split_param_by_assignment[1];
@@ -50,6 +50,13 @@ fn main() {
                 "port" => node.set_port(param_value.parse::<i32>().unwrap()),
                 "peers" => node.set_initial_peers(param_value.to_string()),
                 "ip" => node.set_node_ip(param_value.to_string()),
+                "key" => node.set_node_key(param_value.to_string()),
+                "key-type" => node.set_node_key_type(param_value.to_string()),
+                "rotate-key" => node.set_rotate_key(param_value.to_string()),
+                "approve-change" => node.set_approve_change(param_value.to_string()),
+                "identity" => node.set_print_identity(param_value.to_string()),
+                "genesis" => node.set_genesis(param_value.to_string()),
+                "sync" => node.set_sync_mode(param_value.to_string()),
                 _ => ()
             }
         } else {
@@ -57,6 +64,43 @@ fn main() {
         }
     }
 
+    match node.init_keystore() {
+        Ok(public_key) => println!("Unlocked node key: {}", public_key),
+        Err(err) => {
+            println!("Refusing to start: {}", err);
+            return
+        }
+    }
+
+    // the genesis validators need every node's key, so it is printed before a genesis is read
+    if let Some(identity) = node.genesis_identity() {
+        println!("Genesis validator: {}", identity.dump());
+        println!("Genesis account: {}", node.genesis_account());
+        return
+    }
+
+    match node.init_genesis() {
+        Ok(genesis_hash) => println!("Started from genesis: {}", genesis_hash),
+        Err(err) => {
+            println!("Refusing to start: {}", err);
+            return
+        }
+    }
+
+    node.register_identity();
+
+    match node.approve_validator_change() {
+        Some(Ok(approval)) => {
+            println!("Validator change approval: {}", approval);
+            return
+        },
+        Some(Err(err)) => {
+            println!("Could not approve validator change: {}", err);
+            return
+        },
+        None => ()
+    }
+
     let n = node.clone();
     thread::spawn(move || {
         n.init();
@@ -74,55 +118,13 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use proposal::{Proposal, ProposalStatus, JsonConverter, ProposalValidator, ProposalValidationResult};
-    use block::{Block, CreateNewBlock};
-    use timestamp::{Timestamp, NewTimestamp};
     use network::{Server, PayloadParser, API};
 
     #[test]
-    fn test_validate_proposal_isok() {
-        let successful_msg: &str = "Successful Proposal Validation";
-        let successful_result_stub: Result<String, std::io::Error> = Ok(String::from(successful_msg));
-        let test_timestamp: Option<Timestamp> = Timestamp::new();
-        let test_block: Result<Block, String> = Block::new();
-
-        println!("test_timestamp: {}", test_timestamp.clone().unwrap().timestamp);
-
-        let test_proposal: Proposal = Proposal {
-            proposal_id: 0,
-            proposal_status: ProposalStatus::Created,
-            proposal_hash: String::from("test proposal hash"),
-            proposal_time: test_timestamp.unwrap(),
-            proposal_sender: String::from("test proposal sender"),
-            proposal_block: test_block.unwrap()
-        };
-        let proposal_validated: Result<ProposalValidationResult, std::io::Error> = Proposal::validate_proposal(test_proposal);
-        assert!(proposal_validated.is_ok());
-    }
-
-    #[test]
-    fn test_validate_proposal() {
-        let successful_msg: &str = "Successful Proposal Validation";
-        let successful_result_stub: Result<String, std::io::Error> = Ok(String::from(successful_msg));
-        let test_timestamp: Option<Timestamp> = Timestamp::new();
-        let test_block: Result<Block, String> = Block::new();
-        println!("test_timestamp: {}", test_timestamp.clone().unwrap().timestamp);
-        let test_proposal: Proposal = Proposal {
-            proposal_id: 0,
-            proposal_status: ProposalStatus::Created,
-            proposal_hash: String::from("############TestHashValue############"),
-            proposal_time: test_timestamp.unwrap(),
-            proposal_sender: String::from("test proposal sender"),
-            proposal_block: test_block.unwrap()
-        };
-        let proposal_validated: Result<ProposalValidationResult, std::io::Error> = Proposal::validate_proposal(test_proposal);
-        assert_eq!(ProposalValidationResult::Valid, proposal_validated.unwrap());
-    }
-
-    #[test]
-    fn test_invoke_action_proposal_created(){
+    fn test_invoke_action_refuses_unsigned_proposal(){
+        //the proposal carries no creator signature or chain id
         let invoked_action_result: Result<String, String> = Server::invoke_action("/proposal/created/", "eyJwcm9wb3NhbF9pZCI6MCwicHJvcG9zYWxfc3RhdHVzIjoiYWNjZXB0ZWQiLCJwcm9wb3NhbF9oYXNoIjoiIyMjIyMjIyMjIyMjVGVzdEhhc2hWYWx1ZSMjIyMjIyMjIyMjIyIsInByb3Bvc2FsX3RpbWUiOiIxNTc0OTA1ODAxIiwicHJvcG9zYWxfc2VuZGVyIjoiMTI3LjAuMC4xIiwicHJvcG9zYWxfYmxvY2siOnsiYmxvY2tfaWQiOjAsImJsb2NrX2hhc2giOiJURVNUIEJMT0NLIEhBU0giLCJibG9ja19wYXJlbnRfaGFzaCI6IlRFU1QgUEFSRU5UIEhBU0giLCJibG9ja190aW1lIjoiMTU3NDkwNTgwMSIsInByb3Bvc2FsX2hhc2giOiJURVNUIFBST1BPU0FMIEhBU0giLCJibG9ja19kYXRhIjoiVEVTVCBEQVRBIn19", String::from("127.0.0.1:8080"));
-        assert!(invoked_action_result.is_ok());
+        assert!(invoked_action_result.is_err());
     }
 
 }
@@ -0,0 +1,827 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
+use db::{DB, DBSync, DBHeader};
+use block::{Block,
+            BlockHeader,
+            BlockHeaders,
+            GENESIS_PARENT_HASH,
+            ReadBlock,
+            BlockToJson,
+            BlockFromString,
+            BlockTree,
+            StateSnapshot,
+            InstallCheckpoint,
+            CommitBlock};
+use certificate::{Certificate,
+                  CertificateToJson,
+                  JsonToCertificate,
+                  VerifyCertificate,
+                  StoreCertificate,
+                  ReadCertificate};
+use genesis::{Genesis, ReadGenesis};
+use validator::{Validator, Identity, IdentityManager, ValidatorSchedule};
+use keystore::{Keystore, KeystoreSigner};
+use transaction::{Transaction};
+use mempool::{Mempool, AdmitTransaction};
+use signature::{DigitalSignature,
+                SignatureFormat,
+                Verifier,
+                Signature};
+
+/*
+@desc how many blocks a peer sends per block query, the whole
+      response has to fit in a single request header
+*/
+pub const SYNC_BATCH_SIZE: i64 = 5;
+
+/*
+@name PeerHeight
+@desc the latest block a peer told us it has
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct PeerHeight {
+    pub peer_location: String,
+    pub peer_height: i64,
+    pub peer_tip_hash: String
+}
+
+/*
+@name Sync
+@desc catch up with peers that are ahead of us, one range of
+      certified blocks at a time
+*/
+pub struct Sync {}
+
+/*
+@name CreateSyncIndex
+@desc peer heights are only meaningful while we run, so
+      the index is reset on every start
+*/
+pub trait CreateSyncIndex {
+    fn create_sync_index() -> ();
+}
+
+impl CreateSyncIndex for Sync {
+    fn create_sync_index() -> () {
+        let new_sync_index = object!{
+            "peers" => object!{},
+            "foreign_peers" => object!{}
+        };
+        match DB::write_sync_index(json::stringify(new_sync_index)) {
+            Ok(_) => println!("Successfully wrote sync index"),
+            Err(_) => println!("Failure writing sync index")
+        }
+    }
+}
+
+/*
+@name LocalHeight
+@desc
+*/
+pub trait LocalHeight {
+    fn local_height() -> i64;
+    fn local_tip_hash() -> String;
+}
+
+impl LocalHeight for Sync {
+    /*
+    @name local_height
+    @desc the id of our latest canonical block, -1 before genesis
+    */
+    fn local_height() -> i64 {
+        match Block::get_latest_block_id() {
+            Some(block_id) => block_id,
+            None => -1
+        }
+    }
+
+    fn local_tip_hash() -> String {
+        match Block::get_block_by_block_id(Self::local_height()) {
+            Some(block) => block.block_hash,
+            None => String::from("")
+        }
+    }
+}
+
+/*
+@name HeightMessage
+@desc the height a node announces to its peers, and reading it back
+*/
+pub trait HeightMessage {
+    fn local_height_json() -> JsonValue;
+    fn height_signing_content(payload: JsonValue) -> String;
+    fn peer_height_from_json(peer_location: String, payload: JsonValue) -> Option<PeerHeight>;
+}
+
+impl HeightMessage for Sync {
+    /*
+    @name local_height_json
+    @desc a validator signs its height with its keystore key, so
+          peers can tell the announcement is really from it
+    */
+    fn local_height_json() -> JsonValue {
+        let mut height_json: JsonValue = object!{
+            "peer_height" => Self::local_height(),
+            "peer_tip_hash" => Self::local_tip_hash(),
+            "genesis_hash" => Genesis::local_genesis_hash().unwrap_or(String::from("")),
+            "chain_id" => Genesis::local_chain_id().unwrap_or(String::from(""))
+        };
+        if let Some(identity) = Identity::read_identity() {
+            height_json["validator_id"] = JsonValue::from(identity.identity_id);
+            match Keystore::sign(Self::height_signing_content(height_json.clone()).as_bytes()) {
+                Ok(signature) => height_json["signature"] = JsonValue::from(signature),
+                Err(err) => println!("local_height_json, height is not signed: {}", err)
+            }
+        }
+        height_json
+    }
+
+    /*
+    @name height_signing_content
+    @desc the fields of a height message that are signed, keys in this order
+    */
+    fn height_signing_content(payload: JsonValue) -> String {
+        let content: JsonValue = object!{
+            "peer_height" => payload["peer_height"].clone(),
+            "peer_tip_hash" => payload["peer_tip_hash"].clone(),
+            "genesis_hash" => payload["genesis_hash"].clone(),
+            "chain_id" => payload["chain_id"].clone(),
+            "validator_id" => payload["validator_id"].clone()
+        };
+        content.dump()
+    }
+
+    fn peer_height_from_json(peer_location: String, payload: JsonValue) -> Option<PeerHeight> {
+        Some(PeerHeight {
+            peer_location: peer_location,
+            peer_height: payload["peer_height"].as_i64()?,
+            peer_tip_hash: String::from(payload["peer_tip_hash"].as_str()?)
+        })
+    }
+}
+
+/*
+@name PeerHeights
+@desc
+*/
+pub trait PeerHeights {
+    fn record_peer_height(peer_height: PeerHeight) -> ();
+    fn forget_peer(peer_location: String) -> ();
+    fn get_peer_heights() -> Vec<PeerHeight>;
+    fn best_peer(local_height: i64, peer_heights: Vec<PeerHeight>) -> Option<PeerHeight>;
+}
+
+impl PeerHeights for Sync {
+    fn record_peer_height(peer_height: PeerHeight) -> () {
+        let mut sync_index: JsonValue = match DB::read_sync_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(index) => index,
+                Err(_) => object!{ "peers" => object!{} }
+            },
+            None => object!{ "peers" => object!{} }
+        };
+        sync_index["peers"][peer_height.peer_location.as_str()] = object!{
+            "peer_height" => peer_height.peer_height,
+            "peer_tip_hash" => peer_height.peer_tip_hash
+        };
+        match DB::write_sync_index(sync_index.dump()) {
+            Ok(_) => {},
+            Err(_) => println!("record_peer_height ERROR: could not write sync index")
+        }
+    }
+
+    /*
+    @name forget_peer
+    @desc drop a peer that could not back up the height it reported
+    */
+    fn forget_peer(peer_location: String) -> () {
+        match DB::read_sync_index() {
+            Some(index_string) => {
+                match json::parse(&index_string) {
+                    Ok(mut sync_index) => {
+                        sync_index["peers"].remove(peer_location.as_str());
+                        match DB::write_sync_index(sync_index.dump()) {
+                            Ok(_) => {},
+                            Err(_) => println!("forget_peer ERROR: could not write sync index")
+                        }
+                    },
+                    Err(_) => {}
+                }
+            },
+            None => {}
+        }
+    }
+
+    fn get_peer_heights() -> Vec<PeerHeight> {
+        let mut peer_heights: Vec<PeerHeight> = Vec::new();
+        let sync_index: JsonValue = match DB::read_sync_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(index) => index,
+                Err(_) => return peer_heights
+            },
+            None => return peer_heights
+        };
+        for (location, peer) in sync_index["peers"].entries() {
+            match (peer["peer_height"].as_i64(), peer["peer_tip_hash"].as_str()) {
+                (Some(height), Some(tip_hash)) => {
+                    peer_heights.push(PeerHeight {
+                        peer_location: String::from(location),
+                        peer_height: height,
+                        peer_tip_hash: String::from(tip_hash)
+                    });
+                },
+                _ => {}
+            }
+        }
+        peer_heights
+    }
+
+    /*
+    @name best_peer
+    @desc the peer furthest ahead of us, if any peer is ahead at all
+    */
+    fn best_peer(local_height: i64, peer_heights: Vec<PeerHeight>) -> Option<PeerHeight> {
+        let mut best: Option<PeerHeight> = None;
+        for peer_height in peer_heights {
+            if peer_height.peer_height <= local_height {
+                continue
+            }
+            best = match best {
+                Some(current) if current.peer_height >= peer_height.peer_height => Some(current),
+                _ => Some(peer_height)
+            };
+        }
+        best
+    }
+}
+
+/*
+@name ForeignPeers
+@desc validators started from a different genesis are on another chain,
+      we stop talking to them for as long as we run
+*/
+pub trait ForeignPeers {
+    fn check_peer_genesis(peer_location: String, payload: JsonValue) -> Result<(), String>;
+    fn height_signed_by(payload: JsonValue, validators: Vec<Validator>) -> Option<Validator>;
+    fn is_foreign_peer(peer_location: String) -> bool;
+    fn get_foreign_peers() -> Vec<String>;
+}
+
+impl ForeignPeers for Sync {
+    /*
+    @name check_peer_genesis
+    @desc compare the genesis hash a peer announced with ours, and refuse
+          the height if it sends none or another one. The origin of a request
+          can be anyone, so only a height signed by one of our validators
+          marks that validator's registered location as foreign
+    */
+    fn check_peer_genesis(peer_location: String, payload: JsonValue) -> Result<(), String> {
+        let local_genesis_hash: String = match Genesis::local_genesis_hash() {
+            Some(genesis_hash) => genesis_hash,
+            None => return Err(String::from("check_peer_genesis, no local genesis"))
+        };
+        let peer_genesis_hash: String = String::from(payload["genesis_hash"].as_str().unwrap_or(""));
+        if peer_genesis_hash == local_genesis_hash {
+            return Ok(())
+        }
+        match Self::height_signed_by(payload, Validator::get_validators_at(Self::local_height() + 1)) {
+            Some(validator) => {
+                let mut sync_index: JsonValue = match DB::read_sync_index() {
+                    Some(index_string) => match json::parse(&index_string) {
+                        Ok(index) => index,
+                        Err(_) => object!{ "peers" => object!{} }
+                    },
+                    None => object!{ "peers" => object!{} }
+                };
+                sync_index["peers"].remove(validator.validator_location.as_str());
+                sync_index["foreign_peers"][validator.validator_location.as_str()] = JsonValue::from(peer_genesis_hash.clone());
+                match DB::write_sync_index(sync_index.dump()) {
+                    Ok(_) => {},
+                    Err(_) => println!("check_peer_genesis ERROR: could not write sync index")
+                }
+            },
+            None => {
+                println!("check_peer_genesis, height from {} is not signed by a validator, not marking it as foreign", peer_location);
+            }
+        }
+        Err(format!("check_peer_genesis, peer {} has genesis {}, ours is {}", peer_location, peer_genesis_hash, local_genesis_hash))
+    }
+
+    /*
+    @name height_signed_by
+    @desc the validator whose registered key signed the height message, if any
+    */
+    fn height_signed_by(payload: JsonValue, validators: Vec<Validator>) -> Option<Validator> {
+        let validator_id: i32 = payload["validator_id"].as_i32()?;
+        let signature: String = String::from(payload["signature"].as_str()?);
+        let validator: Validator = validators.into_iter().find(|validator| validator.validator_id == validator_id)?;
+        let digital_signature: DigitalSignature = DigitalSignature {
+            signature_type: validator.validator_key_type.clone(),
+            format_type: SignatureFormat::BYTES
+        };
+        match Signature::verify_with_key(digital_signature,
+                                         signature,
+                                         Self::height_signing_content(payload).as_bytes(),
+                                         validator.validator_public_key.clone()) {
+            Ok(_) => Some(validator),
+            Err(_) => None
+        }
+    }
+
+    fn is_foreign_peer(peer_location: String) -> bool {
+        match DB::read_sync_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(sync_index) => sync_index["foreign_peers"].has_key(peer_location.as_str()),
+                Err(_) => false
+            },
+            None => false
+        }
+    }
+
+    fn get_foreign_peers() -> Vec<String> {
+        let mut foreign_peers: Vec<String> = Vec::new();
+        match DB::read_sync_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(sync_index) => {
+                    for (location, _) in sync_index["foreign_peers"].entries() {
+                        foreign_peers.push(String::from(location));
+                    }
+                },
+                Err(_) => {}
+            },
+            None => {}
+        }
+        foreign_peers
+    }
+}
+
+/*
+@name SyncRequest
+@desc remember which peer we asked for blocks, block responses
+      are pushed back to us without a usable origin
+*/
+pub trait SyncRequest {
+    fn record_request(peer_location: String) -> ();
+    fn forget_requested_peer() -> ();
+}
+
+impl SyncRequest for Sync {
+    fn record_request(peer_location: String) -> () {
+        match DB::read_sync_index() {
+            Some(index_string) => {
+                match json::parse(&index_string) {
+                    Ok(mut sync_index) => {
+                        sync_index["requested_from"] = JsonValue::from(peer_location);
+                        match DB::write_sync_index(sync_index.dump()) {
+                            Ok(_) => {},
+                            Err(_) => println!("record_request ERROR: could not write sync index")
+                        }
+                    },
+                    Err(_) => {}
+                }
+            },
+            None => {}
+        }
+    }
+
+    /*
+    @name forget_requested_peer
+    @desc the peer could not back up the height it reported,
+          stop syncing from it until it announces again
+    */
+    fn forget_requested_peer() -> () {
+        let requested_from: Option<String> = match DB::read_sync_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(sync_index) => sync_index["requested_from"].as_str().map(String::from),
+                Err(_) => None
+            },
+            None => None
+        };
+        match requested_from {
+            Some(peer_location) => Self::forget_peer(peer_location),
+            None => {}
+        }
+    }
+}
+
+/*
+@name SyncRange
+@desc
+*/
+pub trait SyncRange {
+    fn next_range(local_height: i64, peer_height: i64) -> Option<(i64, i64)>;
+    fn parse_range(range_string: &str) -> Option<(i64, i64)>;
+}
+
+impl SyncRange for Sync {
+    /*
+    @name next_range
+    @desc the inclusive range of block ids to ask a peer for next
+    */
+    fn next_range(local_height: i64, peer_height: i64) -> Option<(i64, i64)> {
+        if peer_height <= local_height {
+            return None
+        }
+        let start: i64 = local_height + 1;
+        let end: i64 = std::cmp::min(peer_height, local_height + SYNC_BATCH_SIZE);
+        Some((start, end))
+    }
+
+    /*
+    @name parse_range
+    @desc a block query is either "start,end" or a single block id
+    */
+    fn parse_range(range_string: &str) -> Option<(i64, i64)> {
+        let bounds: Vec<&str> = range_string.trim().split(",").collect();
+        match bounds.len() {
+            1 => {
+                let block_id: i64 = bounds[0].trim().parse::<i64>().ok()?;
+                Some((block_id, block_id))
+            },
+            2 => {
+                let start: i64 = bounds[0].trim().parse::<i64>().ok()?;
+                let end: i64 = bounds[1].trim().parse::<i64>().ok()?;
+                if start <= end && end - start < SYNC_BATCH_SIZE {
+                    Some((start, end))
+                } else {
+                    None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+/*
+@name ServeBlocks
+@desc
+*/
+pub trait ServeBlocks {
+    fn get_certified_blocks(start: i64, end: i64) -> JsonValue;
+}
+
+impl ServeBlocks for Sync {
+    /*
+    @name get_certified_blocks
+    @desc our canonical blocks in the range, each with its commit certificate.
+          Stops at the first block we hold no certificate for, since
+          the requester could not validate it
+    */
+    fn get_certified_blocks(start: i64, end: i64) -> JsonValue {
+        let mut blocks: JsonValue = JsonValue::new_array();
+        for block_id in start..(end + 1) {
+            let block: Block = match Block::get_block_by_block_id(block_id) {
+                Some(block) => block,
+                None => break
+            };
+            let certificate: Certificate = match Certificate::get_certificate_by_block_hash(block.block_hash.clone()) {
+                Some(certificate) => certificate,
+                None => {
+                    println!("get_certified_blocks, no certificate for block {}, stopping", block_id);
+                    break
+                }
+            };
+            match blocks.push(object!{
+                "block" => Block::to_json(block),
+                "certificate" => Certificate::to_json(certificate)
+            }) {
+                Ok(_) => {},
+                Err(_) => break
+            }
+        }
+        blocks
+    }
+}
+
+/*
+@name ApplyBlocks
+@desc
+*/
+pub trait ApplyBlocks {
+    fn apply_certified_blocks(blocks: JsonValue) -> Result<i64, String>;
+}
+
+impl ApplyBlocks for Sync {
+    /*
+    @name apply_certified_blocks
+    @desc validate and commit blocks received from a peer, in order.
+          A block is only committed if a quorum certified it, and
+          the first failure stops the range
+    */
+    fn apply_certified_blocks(blocks: JsonValue) -> Result<i64, String> {
+        let mut applied: i64 = 0;
+        for entry in blocks.members() {
+            let certificate: Certificate = Certificate::from_json(entry["certificate"].clone())?;
+            if !entry["block"].is_object() {
+                return Err(String::from("apply_certified_blocks, entry carries no block"))
+            }
+            let block: Block = Block::from_string(entry["block"].dump())?;
+            if certificate.certificate_block_hash != block.block_hash
+                || certificate.certificate_block_id != block.block_id {
+                return Err(format!("apply_certified_blocks, certificate does not match block {}", block.block_id))
+            }
+            Certificate::verify_certificate(certificate.clone())?;
+            let abandoned: Vec<Transaction> = Block::commit_if_valid(block.clone())?;
+            let reinserted: usize = Mempool::reinsert(abandoned);
+            if reinserted > 0 {
+                println!("apply_certified_blocks, {} abandoned transactions back in the mempool", reinserted);
+            }
+            Certificate::store_certificate(certificate)?;
+            println!("apply_certified_blocks, synced block {}", block.block_id);
+            applied = applied + 1;
+        }
+        Ok(applied)
+    }
+}
+
+/*
+@name HeaderSync
+@desc snapshot sync first follows the certified header chain,
+      without downloading or executing any block body
+*/
+pub trait HeaderSync {
+    fn create_header_index() -> ();
+    fn header_height() -> i64;
+    fn get_header(block_id: i64) -> Option<BlockHeader>;
+    fn get_certified_headers(start: i64, end: i64) -> JsonValue;
+    fn apply_certified_headers(headers: JsonValue) -> Result<i64, String>;
+}
+
+impl HeaderSync for Sync {
+    /*
+    @name create_header_index
+    @desc downloaded headers are kept across restarts. The chain of
+          headers starts from our own genesis block
+    */
+    fn create_header_index() -> () {
+        match DB::read_header_index() {
+            Some(_) => {},
+            None => {
+                let new_header_index = match Block::get_block_by_block_id(0) {
+                    Some(genesis_block) => object!{
+                        "headers" => object!{
+                            "0" => Block::header_to_json(Block::header(genesis_block))
+                        },
+                        "tip" => 0
+                    },
+                    None => object!{
+                        "headers" => object!{},
+                        "tip" => -1
+                    }
+                };
+                match DB::write_header_index(json::stringify(new_header_index)) {
+                    Ok(_) => println!("Successfully wrote header index"),
+                    Err(_) => println!("Failure writing header index")
+                }
+            }
+        }
+    }
+
+    fn header_height() -> i64 {
+        match DB::read_header_index() {
+            Some(index_string) => match json::parse(&index_string) {
+                Ok(header_index) => header_index["tip"].as_i64().unwrap_or(-1),
+                Err(_) => -1
+            },
+            None => -1
+        }
+    }
+
+    fn get_header(block_id: i64) -> Option<BlockHeader> {
+        let index_string: String = DB::read_header_index()?;
+        let header_index: JsonValue = json::parse(&index_string).ok()?;
+        Block::header_from_json(header_index["headers"][format!("{}", block_id).as_str()].clone()).ok()
+    }
+
+    /*
+    @name get_certified_headers
+    @desc the headers of our canonical blocks in the range, each with its
+          commit certificate. Stops at the first block without a certificate
+    */
+    fn get_certified_headers(start: i64, end: i64) -> JsonValue {
+        let mut headers: JsonValue = JsonValue::new_array();
+        for block_id in start..(end + 1) {
+            let block: Block = match Block::get_block_by_block_id(block_id) {
+                Some(block) => block,
+                None => break
+            };
+            let certificate: Certificate = match Certificate::get_certificate_by_block_hash(block.block_hash.clone()) {
+                Some(certificate) => certificate,
+                None => {
+                    println!("get_certified_headers, no certificate for block {}, stopping", block_id);
+                    break
+                }
+            };
+            match headers.push(object!{
+                "header" => Block::header_to_json(Block::header(block)),
+                "certificate" => Certificate::to_json(certificate)
+            }) {
+                Ok(_) => {},
+                Err(_) => break
+            }
+        }
+        headers
+    }
+
+    /*
+    @name apply_certified_headers
+    @desc extend our header chain, in order. Each header must hash to
+          its block hash, link to the previous header, and carry a
+          certificate a quorum signed for that block hash
+    */
+    fn apply_certified_headers(headers: JsonValue) -> Result<i64, String> {
+        let index_string: String = match DB::read_header_index() {
+            Some(index_string) => index_string,
+            None => return Err(String::from("apply_certified_headers, no header index"))
+        };
+        let mut header_index: JsonValue = match json::parse(&index_string) {
+            Ok(header_index) => header_index,
+            Err(_) => return Err(String::from("apply_certified_headers, header index is not valid JSON"))
+        };
+        let mut applied: i64 = 0;
+        for entry in headers.members() {
+            let header: BlockHeader = Block::header_from_json(entry["header"].clone())?;
+            let certificate: Certificate = Certificate::from_json(entry["certificate"].clone())?;
+            let tip: i64 = header_index["tip"].as_i64().unwrap_or(-1);
+            if header.header_block_id != tip + 1 {
+                return Err(format!("apply_certified_headers, expected header {}, got {}", tip + 1, header.header_block_id))
+            }
+            let expected_parent_hash: String = if tip == -1 {
+                String::from(GENESIS_PARENT_HASH)
+            } else {
+                String::from(header_index["headers"][format!("{}", tip).as_str()]["header_block_hash"].as_str().unwrap_or(""))
+            };
+            if header.header_parent_hash != expected_parent_hash {
+                return Err(format!("apply_certified_headers, header {} does not extend our header chain", header.header_block_id))
+            }
+            if !Block::verify_header_hash(header.clone()) {
+                return Err(format!("apply_certified_headers, header {} does not match its hash", header.header_block_id))
+            }
+            if certificate.certificate_block_hash != header.header_block_hash
+                || certificate.certificate_block_id != header.header_block_id {
+                return Err(format!("apply_certified_headers, certificate does not match header {}", header.header_block_id))
+            }
+            Certificate::verify_certificate(certificate)?;
+            header_index["headers"][format!("{}", header.header_block_id).as_str()] = Block::header_to_json(header.clone());
+            header_index["tip"] = JsonValue::from(header.header_block_id);
+            applied = applied + 1;
+        }
+        match DB::write_header_index(header_index.dump()) {
+            Ok(_) => Ok(applied),
+            Err(_) => Err(String::from("apply_certified_headers, could not write header index"))
+        }
+    }
+}
+
+/*
+@name SnapshotSync
+@desc once the header chain is known, fetch the state at its tip
+      instead of executing every block since genesis
+*/
+pub trait SnapshotSync {
+    fn get_checkpoint(block_hash: String) -> Option<JsonValue>;
+    fn install_snapshot(checkpoint: JsonValue) -> Result<i64, String>;
+}
+
+impl SnapshotSync for Sync {
+    /*
+    @name get_checkpoint
+    @desc a block with its certificate and the state right after it
+    */
+    fn get_checkpoint(block_hash: String) -> Option<JsonValue> {
+        let block: Block = Block::get_block_by_hash(block_hash.clone())?;
+        let certificate: Certificate = Certificate::get_certificate_by_block_hash(block_hash.clone())?;
+        let state: JsonValue = Block::state_after(block_hash)?;
+        Some(object!{
+            "block" => Block::to_json(block),
+            "certificate" => Certificate::to_json(certificate),
+            "state" => state
+        })
+    }
+
+    /*
+    @name install_snapshot
+    @desc the checkpoint block must be the one in our verified header
+          chain, and the state must match the state root it commits to
+    */
+    fn install_snapshot(checkpoint: JsonValue) -> Result<i64, String> {
+        if !checkpoint["block"].is_object() || !checkpoint["state"].is_object() {
+            return Err(String::from("install_snapshot, checkpoint carries no block or state"))
+        }
+        let block: Block = Block::from_string(checkpoint["block"].dump())?;
+        let certificate: Certificate = Certificate::from_json(checkpoint["certificate"].clone())?;
+        match Self::get_header(block.block_id) {
+            Some(header) => {
+                if header.header_block_hash != block.block_hash {
+                    return Err(format!("install_snapshot, block {} is not in our header chain", block.block_id))
+                }
+            },
+            None => return Err(format!("install_snapshot, no header for block {}", block.block_id))
+        }
+        if certificate.certificate_block_hash != block.block_hash
+            || certificate.certificate_block_id != block.block_id {
+            return Err(format!("install_snapshot, certificate does not match block {}", block.block_id))
+        }
+        Certificate::verify_certificate(certificate.clone())?;
+        Block::install_checkpoint(block.clone(), checkpoint["state"].clone())?;
+        Certificate::store_certificate(certificate)?;
+        println!("install_snapshot, installed checkpoint at block {}", block.block_id);
+        Ok(block.block_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sync,
+                PeerHeight,
+                PeerHeights,
+                SyncRange,
+                HeightMessage,
+                ForeignPeers,
+                SYNC_BATCH_SIZE};
+    use validator::{Validator};
+    use json::{JsonValue};
+    use signature::{Signature,
+                    Signer,
+                    KeyGenerator,
+                    HDKeyDerivation,
+                    PrivateKey,
+                    SignatureType};
+
+    fn test_peer(location: &str, height: i64) -> PeerHeight {
+        PeerHeight {
+            peer_location: String::from(location),
+            peer_height: height,
+            peer_tip_hash: String::from("tip")
+        }
+    }
+
+    #[test]
+    fn test_next_range_is_capped_by_batch_size() {
+        assert_eq!(Sync::next_range(3, 4), Some((4, 4)));
+        assert_eq!(Sync::next_range(-1, 20), Some((0, SYNC_BATCH_SIZE - 1)));
+        assert_eq!(Sync::next_range(4, 4), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(Sync::parse_range("7"), Some((7, 7)));
+        assert_eq!(Sync::parse_range("2,4"), Some((2, 4)));
+        assert_eq!(Sync::parse_range("4,2"), None);
+        assert_eq!(Sync::parse_range("0,100"), None);
+        assert_eq!(Sync::parse_range("a,b"), None);
+    }
+
+    #[test]
+    fn test_best_peer_is_furthest_ahead() {
+        let peers: Vec<PeerHeight> = vec![test_peer("a", 3), test_peer("b", 9), test_peer("c", 5)];
+        assert_eq!(Sync::best_peer(4, peers.clone()).unwrap().peer_location, "b");
+        assert_eq!(Sync::best_peer(9, peers), None);
+    }
+
+    #[test]
+    fn test_only_a_validator_signature_identifies_a_peer() {
+        let private_key: PrivateKey = Signature::derive_key(&[7u8; 64], 0, 2).unwrap();
+        let validator: Validator = Validator {
+            validator_id: 2,
+            validator_location: String::from("127.0.0.1:8082"),
+            validator_public_key: Signature::public_key_to_base64(&Signature::public_key(&private_key).unwrap()).unwrap(),
+            validator_key_type: SignatureType::ED25519
+        };
+        let mut height_json: JsonValue = object!{
+            "peer_height" => 4,
+            "peer_tip_hash" => "tip",
+            "genesis_hash" => "another genesis",
+            "chain_id" => "africaos-test",
+            "validator_id" => 2
+        };
+        height_json["signature"] = JsonValue::from(Signature::sign(&private_key, Sync::height_signing_content(height_json.clone()).as_bytes()).unwrap());
+        assert_eq!(Sync::height_signed_by(height_json.clone(), vec![validator.clone()]), Some(validator.clone()));
+
+        // claiming another validator, or changing what was signed, identifies nobody
+        let mut spoofed: JsonValue = height_json.clone();
+        spoofed["validator_id"] = JsonValue::from(1);
+        assert_eq!(Sync::height_signed_by(spoofed, vec![validator.clone()]), None);
+        let mut tampered: JsonValue = height_json.clone();
+        tampered["genesis_hash"] = JsonValue::from("yet another genesis");
+        assert_eq!(Sync::height_signed_by(tampered, vec![validator.clone()]), None);
+        height_json.remove("signature");
+        assert_eq!(Sync::height_signed_by(height_json, vec![validator]), None);
+    }
+}
@@ -22,12 +22,29 @@ use db::{DB,
          FileDirectoryReader,
          DBWriteTransaction,
          DBStateManager,
+         DBGenesis,
          LogDebug};
 use std::io::{Error, ErrorKind};
 use timestamp::{Timestamp, NewTimestamp, StringToTimestamp};
 use hash::{Hasher, CalculateSHA256Hash};
 use encode::{Encoder, Base64Encode, Base64Decode};
-use executor::{Executor, ExecuteMacro};
+use executor::{Executor, ExecuteMacro, SPENT_OUTPUTS_KEY, OUTPUT_SENDERS_KEY};
+use amount::{Amount};
+use validator::{Validator,
+                ValidatorChangeType,
+                ValidatorChangeJson,
+                ValidatorSchedule,
+                ElectValidator};
+
+/*
+@desc the fee a transaction pays when the genesis sets no minimum
+*/
+pub const DEFAULT_MIN_TRANSACTION_FEE: Amount = Amount { value: 0 };
+
+/*
+@desc ACCOUNT_NONCES_KEY is where the next nonce of every account lives in the state
+*/
+pub const ACCOUNT_NONCES_KEY: &str = "account_nonces";
 
 /*
 @name Transaction
@@ -35,11 +52,13 @@ use executor::{Executor, ExecuteMacro};
 */
 #[derive(Clone, Debug, PartialEq)]
 pub struct Transaction {
-    pub transaction_id: i32,
     pub transaction_timestamp: Timestamp,
     pub transaction_type: TransactionType,
     pub transaction_sender: String,
     //pub transaction_recipient: String,
+    pub transaction_chain_id: String,
+    pub transaction_fee: Amount,
+    pub transaction_nonce: i64,
     pub transaction_data: String,
     pub transaction_hash: String
 }
@@ -48,6 +67,8 @@ pub struct Transaction {
 pub enum TransactionType {
     Output,
     Input,
+    ValidatorAdd,
+    ValidatorRemove,
     TxTypeError
 }
 
@@ -56,6 +77,8 @@ trait TransactionTypeToString {
         match transaction_type {
             TransactionType::Output => "output",
             TransactionType::Input => "input",
+            TransactionType::ValidatorAdd => "validator_add",
+            TransactionType::ValidatorRemove => "validator_remove",
             TransactionType::TxTypeError => "tx_type_error"
         }
     }
@@ -66,6 +89,8 @@ trait StringToTransactionType {
         match transaction_type {
             "output" => TransactionType::Output,
             "input" => TransactionType::Input,
+            "validator_add" => TransactionType::ValidatorAdd,
+            "validator_remove" => TransactionType::ValidatorRemove,
             _ => TransactionType::TxTypeError
         }
     }
@@ -77,18 +102,39 @@ impl StringToTransactionType for Transaction {}
 
 /*
     @name HashTransaction
-    @desc hash the contents of a transaction
+    @desc a transaction is identified by the hash of its canonical encoding,
+          so every node gives the same transaction the same id
 */
-trait HashTransaction {
-    fn hash_transaction(transaction_id: i32, ts: Timestamp, data: String) -> String;
+pub trait HashTransaction {
+    fn canonical_encoding(transaction: Transaction) -> String;
+    fn hash_transaction(transaction: Transaction) -> String;
+    fn verify_hash(transaction: Transaction) -> bool;
 }
 
 impl HashTransaction for Transaction {
-    fn hash_transaction(transaction_id: i32, ts: Timestamp, data: String) -> String {
-        let raw_str: String = format!("{}{}{}", transaction_id, ts.timestamp, data);
-        let string_to_hash: String = String::from( raw_str.as_str() ) ;
-        let transaction_hash: String = Hasher::calculate_sha256( string_to_hash );
-        transaction_hash
+    /*
+    @name canonical_encoding
+    @desc every field but the hash, in a fixed order
+    */
+    fn canonical_encoding(transaction: Transaction) -> String {
+        let encoding: JsonValue = object!{
+            "transaction_timestamp" => transaction.transaction_timestamp.timestamp,
+            "transaction_type" => Self::type_to_string(transaction.transaction_type),
+            "transaction_sender" => transaction.transaction_sender,
+            "transaction_chain_id" => transaction.transaction_chain_id,
+            "transaction_fee" => transaction.transaction_fee.value,
+            "transaction_nonce" => transaction.transaction_nonce,
+            "transaction_data" => transaction.transaction_data
+        };
+        encoding.dump()
+    }
+
+    fn hash_transaction(transaction: Transaction) -> String {
+        Hasher::calculate_sha256( Self::canonical_encoding(transaction) )
+    }
+
+    fn verify_hash(transaction: Transaction) -> bool {
+        Self::hash_transaction(transaction.clone()) == transaction.transaction_hash
     }
 }
 
@@ -133,11 +179,13 @@ pub trait JsonConverter {
 impl JsonConverter for Transaction {
     fn to_json(transaction: Transaction) -> String {
         let data = object!{
-            "transaction_id" => transaction.transaction_id,
             "transaction_timestamp" => transaction.transaction_timestamp.timestamp,
             "transaction_type" => Self::type_to_string(transaction.transaction_type),
             "transaction_sender" => transaction.transaction_sender,
             //"transaction_recipient" => transaction.transaction_recipient,
+            "transaction_chain_id" => transaction.transaction_chain_id,
+            "transaction_fee" => transaction.transaction_fee.value,
+            "transaction_nonce" => transaction.transaction_nonce,
             "transaction_data" => transaction.transaction_data,
             "transaction_hash" => transaction.transaction_hash
         };
@@ -146,45 +194,43 @@ impl JsonConverter for Transaction {
     }
 
     fn from_json(payload: JsonValue) -> Result<Transaction, String> {
-        println!("TX From JSON: {} id: {}", payload, payload["transaction_id"]);
-        let transaction_id_from_json: Option<i32> = payload["transaction_id"].as_i32();
-        if transaction_id_from_json.is_some() {
-            let transaction_timestamp_from_json: Option<Timestamp> = Timestamp::string_to_timestamp( payload["transaction_timestamp"].to_string() );
-            match transaction_timestamp_from_json {
-                Some(ts) => {
-                    let parsed_tx: Transaction = Transaction {
-                        transaction_id: transaction_id_from_json.unwrap(),
-                        transaction_timestamp: ts,
-                        transaction_type: Transaction::string_to_type( payload["transaction_type"].as_str().unwrap() ),
-                        transaction_sender: payload["transaction_sender"].to_string(),
-                        //transaction_recipient: payload["transaction_recipient"].to_string(),
-                        transaction_data: payload["transaction_data"].to_string(),
-                        transaction_hash: payload["transaction_hash"].to_string()
-                    };
-                    Ok(parsed_tx)
-                },
-                None => {
-                    Err(String::from("Timestamp from string failed in TX"))
-                }
+        println!("TX From JSON: {} hash: {}", payload, payload["transaction_hash"]);
+        let transaction_timestamp_from_json: Option<Timestamp> = Timestamp::string_to_timestamp( payload["transaction_timestamp"].to_string() );
+        match transaction_timestamp_from_json {
+            Some(ts) => {
+                let parsed_tx: Transaction = Transaction {
+                    transaction_timestamp: ts,
+                    transaction_type: Transaction::string_to_type( payload["transaction_type"].as_str().unwrap_or("") ),
+                    transaction_sender: payload["transaction_sender"].to_string(),
+                    //transaction_recipient: payload["transaction_recipient"].to_string(),
+                    transaction_chain_id: String::from(payload["transaction_chain_id"].as_str().unwrap_or("")),
+                    transaction_fee: Amount { value: payload["transaction_fee"].as_u64().unwrap_or(0) },
+                    transaction_nonce: payload["transaction_nonce"].as_i64().unwrap_or(0),
+                    transaction_data: payload["transaction_data"].to_string(),
+                    transaction_hash: payload["transaction_hash"].to_string()
+                };
+                Ok(parsed_tx)
+            },
+            None => {
+                Err(String::from("Timestamp from string failed in TX"))
             }
-        } else {
-            Err(String::from("transaction_id_from_json IS NONE"))
         }
-
-
-
     }
 
     fn from_json_string(json_string: String) -> Result<Transaction, String> {
-        let json_parsed_tx = json::parse( &format!(r#"{}"#, json_string) ).unwrap();
-        Self::from_json(json_parsed_tx)
+        match json::parse(&json_string) {
+            Ok(json_parsed_tx) => Self::from_json(json_parsed_tx),
+            Err(_) => Err(String::from("Transaction, ERROR: from_json_string, could not parse transaction"))
+        }
     }
 
     fn tx_vec_from_json(payload: JsonValue) -> Result<Vec<Transaction>, String> {
         let mut transactions_vector: Vec<Transaction> = Vec::new();
-        let transactions_iter = payload.entries();
-        for (id, transaction) in transactions_iter {
-            let json_parsed_tx = json::parse( &format!(r#"{}"#, transaction) ).unwrap();
+        for transaction in payload.members() {
+            let json_parsed_tx: JsonValue = match json::parse( &format!(r#"{}"#, transaction) ) {
+                Ok(json_parsed_tx) => json_parsed_tx,
+                Err(_) => return Err(String::from("tx_vec_from_json ERROR could not parse transaction"))
+            };
             match Self::from_json( json_parsed_tx.clone() ) {
                 Ok(tx) => {
                     transactions_vector.push(tx);
@@ -198,26 +244,29 @@ impl JsonConverter for Transaction {
         Ok(transactions_vector)
     }
 
+    /*
+    @desc transactions are kept in block order, in a list
+    */
     fn json_from_tx_vec(transactions: Vec<Transaction>) -> Result<JsonValue, String> {
-        let mut new_transaction_index = object!{};
+        let mut transaction_list: JsonValue = JsonValue::new_array();
         for tx in transactions {
-            match new_transaction_index.insert( &( format!("{}", tx.transaction_id).to_string() ),
-                                                Self::to_json(tx) ) {
-                 Ok(_) => {
-
-                 },
-                 Err(_) => {
-
-                 }
+            match json::parse( &Self::to_json(tx) ) {
+                Ok(tx_json) => {
+                    match transaction_list.push(tx_json) {
+                        Ok(_) => {},
+                        Err(_) => return Err(String::from("json_from_tx_vec ERROR could not add transaction"))
+                    }
+                },
+                Err(_) => return Err(String::from("json_from_tx_vec ERROR transaction is not valid JSON"))
             }
         }
-        Ok(new_transaction_index)
+        Ok(transaction_list)
     }
 }
 
 pub trait ReadTransactionFromDB {
     fn get_all_transactions() -> Vec<Transaction>;
-    fn get_latest_transaction_id() -> Option<i32>;
+    fn get_transaction_by_hash(transaction_hash: String) -> Option<Transaction>;
 }
 
 impl ReadTransactionFromDB for DB {
@@ -242,35 +291,16 @@ impl ReadTransactionFromDB for DB {
     }
 
     /*
-    @name get_latest_transaction_id
-    @desc get the latest proposal
+    @name get_transaction_by_hash
+    @desc the pool index is keyed by transaction hash
     */
-    fn get_latest_transaction_id() -> Option<i32> {
-        let transaction_index_parsed: JsonValue = Self::get_transaction_index_as_json();
-        let all_transactions = &transaction_index_parsed["transactions"];
-        if all_transactions.is_empty() {
-            Some(-1)
-        } else {
-            let mut highest_transaction_id: i32 = -1;
-            let transactions_iter = all_transactions.entries();
-            for (id, transaction_iter) in transactions_iter {
-                println!("get_latest_transaction_id(), tx: iter {}:{}", id, transaction_iter);
-                let transaction_from_json: Result<Transaction, String> = Transaction::from_json( (*transaction_iter).clone() );
-                match transaction_from_json {
-                    Ok(tx) => {
-                        if tx.transaction_id > highest_transaction_id {
-                            highest_transaction_id = tx.transaction_id;
-                        } else {
-                            println!("get_latest_transaction_id(), transaction id not higher than highest_transaction_id: {}", tx.transaction_id);
-                        }
-                    },
-                    Err(_) => {
-                        println!("Couldn't convert JSON tx to tx type");
-                    }
-                }
-            }
-            Some(highest_transaction_id)
+    fn get_transaction_by_hash(transaction_hash: String) -> Option<Transaction> {
+        let transaction_index: JsonValue = Self::get_transaction_index_as_json();
+        let transaction_json: &JsonValue = &transaction_index["transactions"][transaction_hash.as_str()];
+        if transaction_json.is_null() {
+            return None
         }
+        Transaction::from_json(transaction_json.clone()).ok()
     }
 }
 
@@ -341,6 +371,39 @@ impl ClearTransactionIndex for Transaction {
     }
 }
 
+/*
+    @name RemoveTransactions
+    @desc take the transactions of a committed block out of the pool,
+          the rest stay pending for the next block
+*/
+pub trait RemoveTransactions {
+    fn remove_transactions(transactions: Vec<Transaction>) -> ();
+}
+
+impl RemoveTransactions for Transaction {
+    fn remove_transactions(transactions: Vec<Transaction>) -> () {
+        if transactions.is_empty() {
+            return
+        }
+        let committed_hashes: Vec<String> = transactions.into_iter().map(|tx| tx.transaction_hash).collect();
+        let transaction_index: JsonValue = DB::get_transaction_index_as_json();
+        let mut remaining_transactions: JsonValue = object!{};
+        for (id, transaction) in transaction_index["transactions"].entries() {
+            match transaction["transaction_hash"].as_str() {
+                Some(transaction_hash) if committed_hashes.contains(&String::from(transaction_hash)) => {},
+                _ => remaining_transactions[id] = transaction.clone()
+            }
+        }
+        let new_transaction_index: JsonValue = object!{
+            "transactions" => remaining_transactions
+        };
+        match DB::write_transaction_index(new_transaction_index.dump()) {
+            Ok(_) => {},
+            Err(_) => println!("remove_transactions ERROR: could not write transaction index")
+        }
+    }
+}
+
 /*
 @name WriteTransactionToDB
 @desc trait to write a transaction to the DB
@@ -367,22 +430,24 @@ impl WriteTransactionToDB for DB {
         let mut parsed: JsonValue = Self::get_transaction_index_as_json();
         //TODO: alter proposal index json object
         let new_transaction_entry = object!{
-            "transaction_id" => transaction.transaction_id,
             "transaction_timestamp" => transaction.transaction_timestamp.timestamp,
             "transaction_type" => Transaction::type_to_string( transaction.transaction_type ),
             "transaction_sender" => transaction.transaction_sender,
             //"transaction_recipient" => transaction.transaction_recipient,
+            "transaction_chain_id" => transaction.transaction_chain_id,
+            "transaction_fee" => transaction.transaction_fee.value,
+            "transaction_nonce" => transaction.transaction_nonce,
             "transaction_data" => transaction.transaction_data,
-            "transaction_hash" => transaction.transaction_hash
+            "transaction_hash" => transaction.transaction_hash.clone()
         };
 
         let tindex_insert_result: Result<String, Error> = match parsed["transactions"]
-              .insert( &(format!("{}", transaction.transaction_id).to_string() ),
+              .insert( transaction.transaction_hash.as_str(),
                        new_transaction_entry) {
             Ok(_) => {
                 println!("New Transaction JSON: {}", parsed.dump());
                 //TODO: commit proposal to DB
-                let db_write_result: Result<String, std::io::Error> = Self::write_transaction_to_sql(transaction.transaction_id, transaction_string.clone());
+                let db_write_result: Result<String, std::io::Error> = Self::write_transaction_to_sql(transaction.transaction_hash.clone(), transaction_string.clone());
                 if db_write_result.is_ok() {
                     //TODO: commit proposal index to DB
                     let db_index_write_result = Self::write_transaction_index(parsed.dump());
@@ -546,15 +611,12 @@ impl ReadState for State{
 
 ////// New Transaction
 pub trait CreateNewOuputTransaction {
-    fn new_output(sender: String, data: String) -> Option<Transaction>;
+    fn new_output(sender: String, data: String, fee: Amount, nonce: i64) -> Option<Transaction>;
 }
 
 impl CreateNewOuputTransaction for Transaction {
     //TODO: convert to return an Option instead of only Transaction
-    fn new_output(sender: String, data: String) -> Option<Transaction> {
-        let latest_transaction_id: Option<i32> = DB::get_latest_transaction_id();
-        //TODO: condition on successful latest_transaction_id
-        let new_transaction_id: i32 = latest_transaction_id.unwrap() + 1;
+    fn new_output(sender: String, data: String, fee: Amount, nonce: i64) -> Option<Transaction> {
         let new_timestamp: Timestamp = Timestamp::new().unwrap();
         let data_prepended_with_sender: String = format!("{} {}", sender, data);
 
@@ -563,26 +625,21 @@ impl CreateNewOuputTransaction for Transaction {
 
         match b64_encoded_data {
             Ok(data) => {
-                let new_transaction_hash: String = Self::hash_transaction(new_transaction_id.clone(), new_timestamp.clone(), data.clone());
-                let new_tx = Transaction {
-                    transaction_id: new_transaction_id,
+                let chain_id: String = DB::read_chain_id().unwrap_or(String::from(""));
+                let mut new_tx = Transaction {
                     transaction_timestamp: new_timestamp,
                     transaction_type: TransactionType::Output,
                     transaction_sender: sender.clone(),
                     //transaction_recipient: sender,
+                    transaction_chain_id: chain_id,
+                    transaction_fee: fee,
+                    transaction_nonce: nonce,
                     transaction_data: data,
-                    transaction_hash: new_transaction_hash
+                    transaction_hash: String::new()
                 };
-                match DB::write_transaction( new_tx.clone() ){
-                    Ok(msg) => {
-                        println!("CreateNewTransaction SUCCESS: {}", msg);
-                        Some(new_tx)
-                    },
-                    Err(msg) => {
-                        println!("CreateNewTransaction FAILED: {}", msg);
-                        None
-                    }
-                }
+                new_tx.transaction_hash = Self::hash_transaction(new_tx.clone());
+                //the mempool decides whether it is admitted to the pool
+                Some(new_tx)
             },
             Err(_) => {
                 None
@@ -593,38 +650,30 @@ impl CreateNewOuputTransaction for Transaction {
 
 //New Transaction
 pub trait CreateNewInputTransaction {
-    fn new_input(sender: String, data: String) -> Option<Transaction>;
+    fn new_input(sender: String, data: String, fee: Amount, nonce: i64) -> Option<Transaction>;
 }
 
 impl CreateNewInputTransaction for Transaction {
-    fn new_input(sender: String, data: String) -> Option<Transaction> {
-        let latest_transaction_id: Option<i32> = DB::get_latest_transaction_id();
-        //TODO: condition on successful latest_transaction_id
-        let new_transaction_id: i32 = latest_transaction_id.unwrap() + 1;
+    fn new_input(sender: String, data: String, fee: Amount, nonce: i64) -> Option<Transaction> {
         let new_timestamp: Timestamp = Timestamp::new().unwrap();
         let b64_encoded_data: Result<String,String> = Encoder::encode_base64(data);
         match b64_encoded_data {
             Ok(data) => {
-                let new_transaction_hash: String = Self::hash_transaction(new_transaction_id.clone(), new_timestamp.clone(), data.clone());
-                let new_tx = Transaction {
-                    transaction_id: new_transaction_id,
+                let chain_id: String = DB::read_chain_id().unwrap_or(String::from(""));
+                let mut new_tx = Transaction {
                     transaction_timestamp: new_timestamp,
                     transaction_type: TransactionType::Input,
                     transaction_sender: sender.clone(),
                     //transaction_recipient: sender,
+                    transaction_chain_id: chain_id,
+                    transaction_fee: fee,
+                    transaction_nonce: nonce,
                     transaction_data: data,
-                    transaction_hash: new_transaction_hash
+                    transaction_hash: String::new()
                 };
-                match DB::write_transaction( new_tx.clone() ){
-                    Ok(msg) => {
-                        println!("CreateNewTransaction SUCCESS: {}", msg);
-                        Some(new_tx)
-                    },
-                    Err(msg) => {
-                        println!("CreateNewTransaction FAILED: {}", msg);
-                        None
-                    }
-                }
+                new_tx.transaction_hash = Self::hash_transaction(new_tx.clone());
+                //the mempool decides whether it is admitted to the pool
+                Some(new_tx)
             },
             Err(_) => {
                 None
@@ -633,12 +682,129 @@ impl CreateNewInputTransaction for Transaction {
     }
 }
 
+/*
+@name SignInput
+@desc what a receiver needs to claim an output: the hash of its public key,
+      which the sender puts in the output, and the content it signs in the
+      input. The sender signs the output_signing_content of its output
+*/
+pub trait SignInput {
+    fn public_key_hash(public_key: String) -> String;
+    fn input_signing_content(partner_hash: String, recipient: String, amount: Amount, fee: Amount, nonce: i64, chain_id: String) -> String;
+    fn output_signing_content(output: String, sender: String, fee: Amount, nonce: i64, chain_id: String) -> String;
+}
+
+impl SignInput for Transaction {
+    fn public_key_hash(public_key: String) -> String {
+        Hasher::calculate_sha256(public_key)
+    }
+
+    fn input_signing_content(partner_hash: String, recipient: String, amount: Amount, fee: Amount, nonce: i64, chain_id: String) -> String {
+        Executor::execute_input_signing_content(partner_hash, recipient, amount, fee, nonce, chain_id)
+    }
+
+    fn output_signing_content(output: String, sender: String, fee: Amount, nonce: i64, chain_id: String) -> String {
+        Executor::execute_output_signing_content(output, sender, fee, nonce, chain_id)
+    }
+}
+
+//New Transaction
+pub trait CreateNewValidatorChangeTransaction {
+    fn new_validator_change(sender: String, signed_change: JsonValue, nonce: i64) -> Option<Transaction>;
+}
+
+impl CreateNewValidatorChangeTransaction for Transaction {
+    /*
+    @name new_validator_change
+    @desc a validator change and its approvals, see Validator::schedule_change
+    */
+    fn new_validator_change(sender: String, signed_change: JsonValue, nonce: i64) -> Option<Transaction> {
+        let transaction_type: TransactionType = match Validator::change_from_json(signed_change["validator_change"].clone()) {
+            Ok(change) => match change.change_type {
+                ValidatorChangeType::Add => TransactionType::ValidatorAdd,
+                ValidatorChangeType::Remove => TransactionType::ValidatorRemove
+            },
+            Err(err) => {
+                println!("CreateNewTransaction FAILED: {}", err);
+                return None
+            }
+        };
+        let new_timestamp: Timestamp = Timestamp::new().unwrap();
+        match Encoder::encode_base64(signed_change.dump()) {
+            Ok(data) => {
+                let chain_id: String = DB::read_chain_id().unwrap_or(String::from(""));
+                let mut new_tx = Transaction {
+                    transaction_timestamp: new_timestamp,
+                    transaction_type: transaction_type,
+                    transaction_sender: sender,
+                    transaction_chain_id: chain_id,
+                    transaction_fee: Amount { value: 0 },
+                    transaction_nonce: nonce,
+                    transaction_data: data,
+                    transaction_hash: String::new()
+                };
+                new_tx.transaction_hash = Self::hash_transaction(new_tx.clone());
+                //the mempool decides whether it is admitted to the pool
+                Some(new_tx)
+            },
+            Err(_) => {
+                None
+            }
+        }
+    }
+}
+
+/*
+@name StateRoot
+@desc a hash committing to the whole state, so a state snapshot
+      received from a peer can be checked against a block header
+*/
+pub trait StateRoot {
+    fn canonical_state(state: JsonValue) -> String;
+    fn state_root(state: JsonValue) -> String;
+}
+
+impl StateRoot for State {
+    /*
+    @name canonical_state
+    @desc serialize the state with keys sorted at every level, so the
+          same state always gives the same string, whatever the insertion order
+    */
+    fn canonical_state(state: JsonValue) -> String {
+        match state {
+            JsonValue::Object(_) => {
+                let mut entries: Vec<(String, JsonValue)> = state.entries()
+                                                                 .map(|(key, value)| (String::from(key), value.clone()))
+                                                                 .collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                let fields: Vec<String> = entries.into_iter()
+                                                 .map(|(key, value)| format!("{}:{}", json::stringify(key), Self::canonical_state(value)))
+                                                 .collect();
+                format!("{{{}}}", fields.join(","))
+            },
+            JsonValue::Array(_) => {
+                let members: Vec<String> = state.members()
+                                                .map(|member| Self::canonical_state(member.clone()))
+                                                .collect();
+                format!("[{}]", members.join(","))
+            },
+            _ => state.dump()
+        }
+    }
+
+    fn state_root(state: JsonValue) -> String {
+        Hasher::calculate_sha256( Self::canonical_state(state) )
+    }
+}
+
 /*
 @name ExecuteTransactions
 @desc trait for Executable behavior on transactions
 */
 pub trait ExecuteTransactions {
-    fn execute_block_transactions(transactions: Vec<Transaction>) -> ();
+    fn execute_block_transactions(block_id: i64, block_time: Timestamp, transactions: Vec<Transaction>) -> ();
+    fn apply_block_transactions(state: JsonValue, block_id: i64, block_time: Timestamp, transactions: Vec<Transaction>) -> JsonValue;
+    fn fee_recipient(state: JsonValue, block_id: i64) -> Option<String>;
 }
 
 /*
@@ -646,20 +812,13 @@ pub trait ExecuteTransactions {
 @desc implements the executable behavior for a transaction
 */
 impl ExecuteTransactions for Transaction {
-    fn execute_block_transactions(mut transactions: Vec<Transaction>) -> () {
+    fn execute_block_transactions(block_id: i64, block_time: Timestamp, transactions: Vec<Transaction>) -> () {
         //TODO: READ CURRENT STATE
         let current_state: Option<State> = State::read();
         match current_state {
             Some(state) => {
                 println!( "execute_block_transactions(), current_state: {}", State::to_json( state.clone() ) );
-                let mut json_state_buffer: JsonValue = ( State::to_json( state.clone() ) );
-                // iterate over each transaction
-                transactions.iter().for_each( | tx | {
-                    println!( "execute_block_transactions(), BEFORE json_state_buffer OVERWRITE: {}", json_state_buffer.clone() );
-                    DB::write_transaction_debug( String::from( format!("tx individual execution: {}", tx.transaction_hash) ) );
-                    json_state_buffer = tx.execute( &Some( State::to_state( json_state_buffer.clone() ) ) );
-                    println!("execute_block_transactions(),  AFTER json_state_buffer OVERWRITE: {}", json_state_buffer.clone() );
-                });
+                let json_state_buffer: JsonValue = Self::apply_block_transactions( State::to_json( state.clone() ), block_id, block_time, transactions );
                 let state_to_write: String = json::stringify( json_state_buffer.clone() );
                 // TODO: SPECIFY WHICH STATE INDEX TO WRITE
                 match DB::write_state(state_to_write) {
@@ -676,6 +835,173 @@ impl ExecuteTransactions for Transaction {
             }
         }
     }
+
+    /*
+    @name apply_block_transactions
+    @desc execute transactions on a given state and return the resulting
+          state, without touching the state db. used to replay blocks
+          on top of a state snapshot. Validator changes need the id of
+          the block they are in, to only schedule changes for later blocks,
+          and fees go to the creator of the block. A transaction runs only
+          with the next nonce of its sender, and uses it up if it executes.
+          Timelocked outputs are checked against the block id and block time
+    */
+    fn apply_block_transactions(state: JsonValue, block_id: i64, block_time: Timestamp, transactions: Vec<Transaction>) -> JsonValue {
+        let fee_recipient: Option<String> = Self::fee_recipient(state.clone(), block_id);
+        let mut json_state_buffer: JsonValue = state;
+        // iterate over each transaction
+        transactions.iter().for_each( | tx | {
+            println!( "apply_block_transactions(), BEFORE json_state_buffer OVERWRITE: {}", json_state_buffer.clone() );
+            DB::write_transaction_debug( String::from( format!("tx individual execution: {}", tx.transaction_hash) ) );
+            if let Err(err) = tx.check_nonce(json_state_buffer.clone()) {
+                println!("apply_block_transactions(), ERROR: {}", err);
+                return
+            }
+            let executed_state: JsonValue = match tx.transaction_type {
+                TransactionType::ValidatorAdd | TransactionType::ValidatorRemove => {
+                    tx.schedule_validator_change(json_state_buffer.clone(), block_id)
+                },
+                _ => tx.execute_on_state( json_state_buffer.clone(), &fee_recipient, block_id, &block_time )
+            };
+            json_state_buffer = if executed_state != json_state_buffer {
+                Self::use_nonce(executed_state, tx.transaction_sender.clone())
+            } else {
+                executed_state
+            };
+            println!("apply_block_transactions(),  AFTER json_state_buffer OVERWRITE: {}", json_state_buffer.clone() );
+        });
+        json_state_buffer
+    }
+
+    /*
+    @name fee_recipient
+    @desc the account of the validator elected to create the block, the hash
+          of its public key, so it can sign outputs spending the fees. Taken
+          from the state before the block so every node credits the same one
+    */
+    fn fee_recipient(state: JsonValue, block_id: i64) -> Option<String> {
+        Validator::elected_validator(Validator::validators_in_state(state, block_id), block_id - 1)
+                  .map(|validator| Self::public_key_hash(validator.validator_public_key))
+    }
+}
+
+/*
+@name AccountNonce
+@desc every account has a next nonce in the state, starting at 0. A
+      transaction has to carry exactly that nonce, so the same
+      transaction cannot execute twice
+*/
+pub trait AccountNonce {
+    fn account_nonce(state: JsonValue, account: String) -> i64;
+    fn use_nonce(state: JsonValue, account: String) -> JsonValue;
+    fn check_nonce(&self, state: JsonValue) -> Result<(), String>;
+}
+
+impl AccountNonce for Transaction {
+    fn account_nonce(state: JsonValue, account: String) -> i64 {
+        state[ACCOUNT_NONCES_KEY][account.as_str()].as_i64().unwrap_or(0)
+    }
+
+    fn use_nonce(state: JsonValue, account: String) -> JsonValue {
+        let next_nonce: i64 = Self::account_nonce(state.clone(), account.clone()) + 1;
+        let mut new_state: JsonValue = state;
+        if !new_state[ACCOUNT_NONCES_KEY].is_object() {
+            new_state[ACCOUNT_NONCES_KEY] = object!{};
+        }
+        new_state[ACCOUNT_NONCES_KEY][account.as_str()] = JsonValue::from(next_nonce);
+        new_state
+    }
+
+    fn check_nonce(&self, state: JsonValue) -> Result<(), String> {
+        let account_nonce: i64 = Self::account_nonce(state, self.transaction_sender.clone());
+        if self.transaction_nonce < account_nonce {
+            Err(format!("check_nonce, nonce {} of {} is already used", self.transaction_nonce, self.transaction_sender))
+        } else if self.transaction_nonce > account_nonce {
+            Err(format!("check_nonce, nonce {} of {} is ahead of {}", self.transaction_nonce, self.transaction_sender, account_nonce))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/*
+@name SpentOutputs
+@desc an output is claimed by one input only, the executor records the
+      claimed outputs in the state under SPENT_OUTPUTS_KEY, and the sender
+      of every output under OUTPUT_SENDERS_KEY
+*/
+pub trait SpentOutputs {
+    fn output_spent(state: JsonValue, output_hash: String) -> bool;
+    fn output_sender(state: JsonValue, output_hash: String) -> Option<String>;
+    fn claimed_output(&self) -> Option<String>;
+}
+
+impl SpentOutputs for Transaction {
+    fn output_spent(state: JsonValue, output_hash: String) -> bool {
+        state[SPENT_OUTPUTS_KEY].has_key(output_hash.as_str())
+    }
+
+    /*
+    @name output_sender
+    @desc the sender recorded with an output, who pays the input claiming it
+    */
+    fn output_sender(state: JsonValue, output_hash: String) -> Option<String> {
+        state[OUTPUT_SENDERS_KEY][output_hash.as_str()].as_str().map(String::from)
+    }
+
+    /*
+    @name claimed_output
+    @desc the hash of the output an input claims, the first section of its data
+    */
+    fn claimed_output(&self) -> Option<String> {
+        match self.transaction_type {
+            TransactionType::Input => match Encoder::decode_base64(self.transaction_data.clone()) {
+                Ok(decoded) => decoded.split(" ").next().map(String::from),
+                Err(_) => None
+            },
+            _ => None
+        }
+    }
+}
+
+/*
+@name ScheduleValidatorChange
+@desc a validator change that is not signed by a validator, or does not
+      activate after its block, leaves the state as it was
+*/
+trait ScheduleValidatorChange {
+    fn schedule_validator_change(&self, state: JsonValue, block_id: i64) -> JsonValue;
+}
+
+impl ScheduleValidatorChange for Transaction {
+    fn schedule_validator_change(&self, state: JsonValue, block_id: i64) -> JsonValue {
+        let signed_change: Option<JsonValue> = match Encoder::decode_base64(self.transaction_data.clone()) {
+            Ok(decoded) => json::parse(&decoded).ok(),
+            Err(_) => None
+        };
+        let signed_change: JsonValue = match signed_change {
+            Some(signed_change) => signed_change,
+            None => {
+                println!("TX schedule_validator_change() ERROR: could not decode change");
+                return state
+            }
+        };
+        let expected_type: &str = match self.transaction_type {
+            TransactionType::ValidatorAdd => "add",
+            _ => "remove"
+        };
+        if signed_change["validator_change"]["change_type"].as_str() != Some(expected_type) {
+            println!("TX schedule_validator_change() ERROR: change does not match transaction type");
+            return state
+        }
+        match Validator::schedule_change(state.clone(), block_id, self.transaction_chain_id.clone(), signed_change) {
+            Ok(new_state) => new_state,
+            Err(err) => {
+                println!("TX schedule_validator_change() ERROR: {}", err);
+                state
+            }
+        }
+    }
 }
 
 /*
@@ -684,37 +1010,61 @@ impl ExecuteTransactions for Transaction {
 */
 trait Executable {
     fn execute(&self, current_state_buffer: &Option<State>) -> JsonValue;
+    fn execute_on_state(&self, state_as_json: JsonValue, fee_recipient: &Option<String>, block_id: i64, block_time: &Timestamp) -> JsonValue;
 }
 
 impl Executable for Transaction {
     fn execute(&self, current_state_buffer: &Option<State>) -> JsonValue {
-        format!("Executing Transaction {}", self.transaction_id);
+        // outside of a block, timelocked outputs stay locked
+        let no_block_time: Timestamp = Timestamp { timestamp: String::from("0") };
+        self.execute_on_state(State::to_json(current_state_buffer.clone().unwrap()), &None, 0, &no_block_time)
+    }
+
+    /*
+    @name execute_on_state
+    @desc execute on a JSON state directly. Accounts are keyed by node
+          location, which has a ':' in it, so they do not survive a round
+          trip through State. Inputs are executed at the id and time of
+          the block they are in. Without a block creator to pay, the fee is not charged
+    */
+    fn execute_on_state(&self, state_as_json: JsonValue, fee_recipient: &Option<String>, block_id: i64, block_time: &Timestamp) -> JsonValue {
+        println!("Executing Transaction {}", self.transaction_hash);
         match &self.transaction_type {
             TransactionType::Output => {
                 // TODO MACRO USE!!!! CUSTOM_TRANSACTION_OUTPUT_LOGIC!()
-                println!("TX execute() TX Output BEFORE: {} : ",  State::to_json( current_state_buffer.clone().unwrap() ) );
-
-                // TODO: create new address in state
-                let mut state_as_json: JsonValue = State::to_json(current_state_buffer.clone().unwrap());
+                println!("TX execute() TX Output BEFORE: {} : ",  state_as_json.clone() );
                 Executor::execute_transaction_output_logic(state_as_json,
                                                            self.transaction_timestamp.clone(),
                                                            self.transaction_sender.clone(),
                                                            self.transaction_hash.clone(),
-                                                           self.transaction_data.clone())
+                                                           self.transaction_data.clone(),
+                                                           self.transaction_nonce,
+                                                           self.transaction_chain_id.clone(),
+                                                           self.transaction_fee,
+                                                           fee_recipient.clone())
             },
             TransactionType::Input => {
                 // TODO MACRO USE!!!! CUSTOM_TRANSACTION_INPUT_LOGIC!()
                 println!("TX execute() TX Input BEFORE");
-                let mut state_as_json: JsonValue = State::to_json(current_state_buffer.clone().unwrap());
                 Executor::execute_transaction_input_logic(state_as_json,
                                                           self.transaction_timestamp.clone(),
                                                           self.transaction_sender.clone(),
                                                           self.transaction_hash.clone(),
-                                                          self.transaction_data.clone())
+                                                          self.transaction_data.clone(),
+                                                          self.transaction_nonce,
+                                                          self.transaction_chain_id.clone(),
+                                                          block_id,
+                                                          block_time.clone(),
+                                                          self.transaction_fee,
+                                                          fee_recipient.clone())
+            },
+            TransactionType::ValidatorAdd | TransactionType::ValidatorRemove => {
+                println!("TX execute() validator changes are scheduled by apply_block_transactions");
+                state_as_json
             },
             TransactionType::TxTypeError => {
                 println!("TX execute() ERROR: TxTypeError");
-                State::to_json(current_state_buffer.clone().unwrap())
+                state_as_json
             }
         }
     }
@@ -727,9 +1077,35 @@ mod tests {
                 JsonValue,
                 Executable,
                 ExecuteTransactions,
+                AccountNonce,
+                HashTransaction,
+                JsonConverter,
+                StateRoot,
+                SPENT_OUTPUTS_KEY,
+                OUTPUT_SENDERS_KEY,
+                SignInput,
+                Amount,
                 State};
     use timestamp::{Timestamp, NewTimestamp};
     use encode::{Encoder, Base64Encode, Base64Decode};
+    use signature::{Signature, Signer, KeyGenerator, HDKeyDerivation, PrivateKey, SignatureType};
+    use validator::{Validator, ValidatorChange, ValidatorChangeType, ValidatorChangeJson, VALIDATOR_CHANGES_KEY};
+    use std::path::Path;
+
+    // the output signed by a key derived for the tests, for the fee and nonce 0 on the
+    // test chain, returns the sender, the account of the key, and the output data
+    fn signed_output(output: &str, fee: Amount) -> (String, String) {
+        signed_output_by(&Signature::derive_key(&[9u8; 64], 0, 0).unwrap(), output, fee)
+    }
+
+    fn signed_output_by(private_key: &PrivateKey, output: &str, fee: Amount) -> (String, String) {
+        let public_key: String = Signature::public_key_to_base64(&Signature::public_key(private_key).unwrap()).unwrap();
+        let sender: String = Transaction::public_key_hash(public_key.clone());
+        let content: String = Transaction::output_signing_content(String::from(output), sender.clone(), fee, 0, String::from("africaos-test"));
+        let signature: String = Signature::sign(private_key, content.as_bytes()).unwrap();
+        let output_data: String = format!("{} sender_signature={} sender_public_key={} sender_key_type=ed25519", output, signature, public_key);
+        (sender, Encoder::encode_base64(output_data).unwrap())
+    }
 
 
     #[test]
@@ -739,23 +1115,25 @@ mod tests {
             tree: Vec::new()
         };
         let test_tx_hash_string: String = String::from("TESTTXHASH");
-        let test_sender: String = String::from("test_sender");
-        let test_tx_data: String = String::from("test data");
+        let (test_sender, test_tx_data): (String, String) = signed_output("TESTDATA", Amount { value: 0 });
 
         let test_state: &Option<State> = &Some(state);
         let new_tx = Transaction {
-            transaction_id: 1,
             transaction_timestamp: new_timestamp,
             transaction_type: TransactionType::Output,
             transaction_sender: test_sender.clone(),
             //transaction_recipient: String::from("test recipient"),
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: 0 },
+            transaction_nonce: 0,
             transaction_data: test_tx_data.clone(), //data
             transaction_hash: test_tx_hash_string.clone()
         };
         let execution_result: JsonValue = new_tx.execute( test_state );
         let expected_json: JsonValue = object!{
             test_sender.clone().as_str() => "0",
-            test_tx_hash_string.clone().as_str() => format!("{}" , test_tx_data.clone())
+            test_tx_hash_string.clone().as_str() => format!("{}" , test_tx_data.clone()),
+            OUTPUT_SENDERS_KEY => object!{ test_tx_hash_string.clone().as_str() => test_sender.clone() }
         };
         assert_eq!(expected_json, execution_result);
     }
@@ -764,7 +1142,7 @@ mod tests {
     fn test_create_tx_input_execution() {
         let new_timestamp: Timestamp = Timestamp::new().unwrap();
         let test_tx_hash_string: String = String::from("TESTTXHASH");
-        let test_pubkhash_string: String = String::from("e2a8aca48c5b24df14c6e0ab0b30df7ed50fa97bc22fd706c71a7eebe96a8b67");
+        let test_pubkhash_string: String = String::from("2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
         let test_amount: i32 = 10;
         let test_sender: String = String::from("test_sender");
         let test_output_sender: String = String::from("test_output_sender");
@@ -774,51 +1152,223 @@ mod tests {
         let test_out_tx_data_string: String = Encoder::encode_base64(test_out_tx_data_string_preb64.clone()).unwrap();
 
 
-        let test_bob_signature: String = String::from("opEssZ7CaoYvtZJErFPqiB0L+lxwFm1/YT3tLZ+07fCnwWvuRcXtpwmo4esdNs05OItDBK6SZaxVPO+tKG22NC8R64DQj4J6CXpt4XMxtGJSUeY9MyZB6eyW8qYye7zascGv5+Eht4VJ5Zu9TX8Xl2+oyZA+3RYw5QKHvMgHyN0mpPU8PYpBDdVKg5Nglh4WOjqrvJF/EAdyqfeLN0CNJHeFwwjlkDaOz1x9LBOBf8c5HhDulgblSd4tlJ9zRA97SbnxmQtip/XDLweTtCx9vmjFd0tw/JTcfl2V87r+JgxL0r9EgEoFsexs3XkdqKZ2LzypPMvp0XqeoNEJ03g96A==");
+        // bob signs the input_signing_content of the output hash, the receiver, 10, fee 0, nonce 0 and the chain id
+        let test_bob_key: PrivateKey = Signature::read_private_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let test_bob_content: String = Transaction::input_signing_content(test_tx_hash_string.clone(),
+                                                                          test_sender.clone(),
+                                                                          Amount { value: 10 },
+                                                                          Amount { value: 0 },
+                                                                          0,
+                                                                          String::from("africaos-test"));
+        let test_bob_signature: String = Signature::sign(&test_bob_key, test_bob_content.as_bytes()).unwrap();
 
         //the public key to check the signature, and to hash
         let test_bob_public_key_base64: &str = "MIIBCgKCAQEAuJ3hhGpo+nInkqHpgBx3E5eihx0IGNVit5u0UlvLHmnW2PJ3HqFyafr/eYaas7VetW5Ss5kAWZmH3oED7n4xVlXrUeFlSwShSDVMKyT3iK1et1KQIX5cqp2CiFSs+xi5eJOEEWZxoexYbSTg0Rg34gzob+VqHZzRtRN9eTja7ZCE3/m3cMlWfb7yL2jyN521ZL02QG9PZ4EYenDTM2xcWvCZrWtKUFLahCWWQ1H8ZpoWg/y/tenvUy5YnWLrbhbSZqMJQYfnUwr8FRWZsiI2RWRtXlx9X6bxvaOoG/h4IOhV/G52Xas2WxxUbZ5rgRPziA/mvkJxytHkZPeEnpRcawIDAQAB";
 
-        let test_in_tx_data_string_preb64: String = format!("{} {} {} {}", test_output_sender, test_tx_hash_string, test_bob_signature, test_bob_public_key_base64);
+        let test_in_tx_data_string_preb64: String = format!("{} {} {}", test_tx_hash_string, test_bob_signature, test_bob_public_key_base64);
 
         let test_in_tx_data_string: String = Encoder::encode_base64(test_in_tx_data_string_preb64.clone()).unwrap();
 
         println!("be64: {}", test_in_tx_data_string.clone() );
 
-        let alice_state_bootstrap: &str = "test_output_sender:100";
-
-        let test_state_vec: Vec<String> = vec![String::from(format!{"{}:{}", test_tx_hash_string, test_out_tx_data_string}),
-                                               String::from(alice_state_bootstrap)];
-
-        // test state
-        let state: State = State {
-            //tree: Vec::new()
-            tree: test_state_vec
+        // the output, and its recorded sender who pays it
+        let test_state: JsonValue = object!{
+            test_tx_hash_string.clone().as_str() => test_out_tx_data_string.clone(),
+            test_output_sender.clone().as_str() => "100",
+            OUTPUT_SENDERS_KEY => object!{ test_tx_hash_string.clone().as_str() => test_output_sender.clone() }
         };
 
-        let test_state: &Option<State> = &Some(state);
-
         // new tx
         let new_tx = Transaction {
-            transaction_id: 1,
             transaction_timestamp: new_timestamp,
             transaction_type: TransactionType::Input,
             transaction_sender: test_sender.clone(),
             //transaction_recipient: String::from("test recipient"),
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: 0 },
+            transaction_nonce: 0,
             transaction_data: test_in_tx_data_string.clone(), //data
             transaction_hash: test_tx_hash_string.clone()
         };
 
         //execute on tx
-        let execution_result: JsonValue = new_tx.execute( test_state );
+        let execution_result: JsonValue = new_tx.execute_on_state( test_state, &None, 0, &Timestamp { timestamp: String::from("0") } );
 
         let expected_json: JsonValue = object!{
             test_tx_hash_string.clone().as_str() => format!("{}" , test_out_tx_data_string.clone()),
             test_output_sender.clone().as_str() => "90",
-            test_sender.clone().as_str() => "10"
+            OUTPUT_SENDERS_KEY => object!{ test_tx_hash_string.clone().as_str() => test_output_sender.clone() },
+            test_sender.clone().as_str() => "10",
+            SPENT_OUTPUTS_KEY => object!{ test_tx_hash_string.clone().as_str() => test_tx_hash_string.clone() }
         };
 
         assert_eq!(expected_json, execution_result);
 
     }
+
+    #[test]
+    fn test_tx_output_pays_fee_to_recipient() {
+        let test_proposer: &str = "PROPOSERPKHASH";
+        let test_tx = |fee: u64| Transaction {
+            transaction_timestamp: Timestamp::new().unwrap(),
+            transaction_type: TransactionType::Output,
+            transaction_sender: signed_output("TESTDATA", Amount { value: fee }).0,
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: fee },
+            transaction_nonce: 0,
+            transaction_data: signed_output("TESTDATA", Amount { value: fee }).1,
+            transaction_hash: String::from("TESTTXHASH")
+        };
+        let (test_sender, test_tx_data): (String, String) = signed_output("TESTDATA", Amount { value: 5 });
+        let test_state: JsonValue = object!{ test_sender.as_str() => "100" };
+
+        let execution_result: JsonValue = test_tx(5).execute_on_state( test_state.clone(), &Some(String::from(test_proposer)), 1, &Timestamp::new().unwrap() );
+        let expected_json: JsonValue = object!{
+            test_sender.as_str() => "95",
+            "TESTTXHASH" => test_tx_data,
+            OUTPUT_SENDERS_KEY => object!{ "TESTTXHASH" => test_sender.as_str() },
+            test_proposer => "5"
+        };
+        assert_eq!(expected_json, execution_result);
+
+        // a sender who cannot pay the fee leaves the state as it was
+        let refused_result: JsonValue = test_tx(101).execute_on_state( test_state.clone(), &Some(String::from(test_proposer)), 1, &Timestamp::new().unwrap() );
+        assert_eq!(test_state, refused_result);
+        // and so does a fee that is not above 0
+        let refused_result: JsonValue = test_tx(0).execute_on_state( test_state.clone(), &Some(String::from(test_proposer)), 1, &Timestamp::new().unwrap() );
+        assert_eq!(test_state, refused_result);
+    }
+
+    #[test]
+    fn test_proposer_spends_collected_fees() {
+        let test_validator = |validator_id: i32| {
+            let private_key: PrivateKey = Signature::derive_key(&[7u8; 64], 0, validator_id as u32).unwrap();
+            let validator: Validator = Validator {
+                validator_id: validator_id,
+                validator_location: format!("127.0.0.1:808{}", validator_id),
+                validator_public_key: Signature::public_key_to_base64(&Signature::public_key(&private_key).unwrap()).unwrap(),
+                validator_key_type: SignatureType::ED25519
+            };
+            (validator, private_key)
+        };
+        let (first_validator, first_key): (Validator, PrivateKey) = test_validator(1);
+        let (second_validator, _): (Validator, PrivateKey) = test_validator(2);
+        let first_account: String = Transaction::public_key_hash(first_validator.validator_public_key.clone());
+        let second_account: String = Transaction::public_key_hash(second_validator.validator_public_key.clone());
+        let add_change = |validator: Validator| Validator::change_to_json(ValidatorChange {
+            change_type: ValidatorChangeType::Add,
+            change_validator: validator,
+            change_activation_height: 0
+        });
+        let output_tx = |sender: String, data: String, fee: u64| {
+            let mut tx: Transaction = Transaction {
+                transaction_timestamp: Timestamp { timestamp: String::from("10") },
+                transaction_type: TransactionType::Output,
+                transaction_sender: sender,
+                transaction_chain_id: String::from("africaos-test"),
+                transaction_fee: Amount { value: fee },
+                transaction_nonce: 0,
+                transaction_data: data,
+                transaction_hash: String::new()
+            };
+            tx.transaction_hash = Transaction::hash_transaction(tx.clone());
+            tx
+        };
+        let (test_sender, test_tx_data): (String, String) = signed_output("TESTDATA", Amount { value: 5 });
+        let mut test_state: JsonValue = object!{ test_sender.as_str() => "100" };
+        let (first_change, second_change): (JsonValue, JsonValue) = (add_change(first_validator), add_change(second_validator));
+        test_state[VALIDATOR_CHANGES_KEY] = array![first_change, second_change];
+
+        // the first validator creates block 1, and is paid the fee under the hash of its key
+        let first_recipient: Option<String> = Transaction::fee_recipient(test_state.clone(), 1);
+        assert_eq!(first_recipient, Some(first_account.clone()));
+        let first_block_state: JsonValue = output_tx(test_sender.clone(), test_tx_data, 5).execute_on_state(test_state,
+                                                                                                            &first_recipient,
+                                                                                                            1,
+                                                                                                            &Timestamp { timestamp: String::from("10") });
+        assert_eq!(first_block_state[first_account.as_str()], "5");
+        assert_eq!(first_block_state[test_sender.as_str()], "95");
+
+        // in block 2, created by the second validator, the first signs an output paying 2 of it
+        let (fee_sender, fee_tx_data): (String, String) = signed_output_by(&first_key, "TESTDATA", Amount { value: 2 });
+        assert_eq!(fee_sender, first_account);
+        let fee_output: Transaction = output_tx(fee_sender, fee_tx_data, 2);
+        let second_recipient: Option<String> = Transaction::fee_recipient(first_block_state.clone(), 2);
+        assert_eq!(second_recipient, Some(second_account.clone()));
+        let second_block_state: JsonValue = fee_output.execute_on_state(first_block_state,
+                                                                        &second_recipient,
+                                                                        2,
+                                                                        &Timestamp { timestamp: String::from("20") });
+        assert_eq!(second_block_state[first_account.as_str()], "3");
+        assert_eq!(second_block_state[second_account.as_str()], "2");
+        assert_eq!(second_block_state[OUTPUT_SENDERS_KEY][fee_output.transaction_hash.as_str()], first_account.as_str());
+    }
+
+    #[test]
+    fn test_nonce_is_used_once() {
+        let new_tx = Transaction {
+            transaction_timestamp: Timestamp::new().unwrap(),
+            transaction_type: TransactionType::Output,
+            transaction_sender: String::from("test_sender"),
+            transaction_chain_id: String::from("africaos-test"),
+            transaction_fee: Amount { value: 0 },
+            transaction_nonce: 0,
+            transaction_data: String::from("test data"),
+            transaction_hash: String::from("TESTTXHASH")
+        };
+        let state: JsonValue = object!{ "test_sender" => "100" };
+        assert!(new_tx.check_nonce(state.clone()).is_ok());
+        let used_state: JsonValue = Transaction::use_nonce(state, String::from("test_sender"));
+        assert_eq!(Transaction::account_nonce(used_state.clone(), String::from("test_sender")), 1);
+        assert!(new_tx.check_nonce(used_state).is_err());
+    }
+
+    #[test]
+    fn test_hash_identifies_content_and_block_order_is_kept() {
+        let test_tx = |data: &str| {
+            let mut tx: Transaction = Transaction {
+                transaction_timestamp: Timestamp { timestamp: String::from("10") },
+                transaction_type: TransactionType::Output,
+                transaction_sender: String::from("test_sender"),
+                transaction_chain_id: String::from("africaos-test"),
+                transaction_fee: Amount { value: 0 },
+                transaction_nonce: 0,
+                transaction_data: String::from(data),
+                transaction_hash: String::new()
+            };
+            tx.transaction_hash = Transaction::hash_transaction(tx.clone());
+            tx
+        };
+        let first: Transaction = test_tx("b data");
+        let second: Transaction = test_tx("a data");
+        assert_eq!(first.transaction_hash, test_tx("b data").transaction_hash);
+        assert_ne!(first.transaction_hash, second.transaction_hash);
+        assert!(Transaction::verify_hash(first.clone()));
+        let mut tampered: Transaction = first.clone();
+        tampered.transaction_fee = Amount { value: 1 };
+        assert!(!Transaction::verify_hash(tampered));
+
+        let transactions: Vec<Transaction> = vec![first, second];
+        let round_trip: Vec<Transaction> = Transaction::tx_vec_from_json( Transaction::json_from_tx_vec(transactions.clone()).unwrap() ).unwrap();
+        assert_eq!(round_trip, transactions);
+
+        // relayed transactions are untrusted, malformed ones are an error
+        assert_eq!(Transaction::from_json_string( Transaction::to_json(transactions[0].clone()) ).unwrap(), transactions[0]);
+        assert!(Transaction::from_json_string( String::from("{\"transaction_type\": ") ).is_err());
+        assert!(Transaction::tx_vec_from_json( array!["not json"] ).is_err());
+    }
+
+    #[test]
+    fn test_state_root_ignores_key_order() {
+        let left: JsonValue = object!{
+            "alice" => "10",
+            "bob" => object!{ "x" => "1", "y" => "2" }
+        };
+        let right: JsonValue = object!{
+            "bob" => object!{ "y" => "2", "x" => "1" },
+            "alice" => "10"
+        };
+        assert_eq!(State::state_root(left.clone()), State::state_root(right));
+        assert_ne!(State::state_root(left), State::state_root(object!{ "alice" => "11" }));
+    }
 }
@@ -0,0 +1,731 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+#[macro_use]
+extern crate json;
+use json::{JsonValue};
+use db::{DB,
+         DBReadValidator,
+         DBIdentityManager,
+         DBStateManager,
+         DBGenesis};
+use hash::{Hasher, CalculateSHA256Hash};
+use keystore::{Keystore, KeystoreSigner};
+use signature::{DigitalSignature,
+                SignatureType,
+                SignatureFormat,
+                Verifier,
+                SignatureTypeString,
+                Signature};
+use executor::{Executor, ExecuteMacro};
+
+/*
+@desc VALIDATOR_CHANGES_KEY is where scheduled validator set changes live in the state
+*/
+pub const VALIDATOR_CHANGES_KEY: &str = "validator_changes";
+
+/*
+@name Validator
+@desc a node allowed to create and vote on proposals, keyed by its node id
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Validator {
+    pub validator_id: i32,
+    pub validator_location: String,
+    pub validator_public_key: String,
+    pub validator_key_type: SignatureType
+}
+
+/*
+@name ValidatorChange
+@desc add or remove a validator, from a future block height on
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct ValidatorChange {
+    pub change_type: ValidatorChangeType,
+    pub change_validator: Validator,
+    pub change_activation_height: i64
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum ValidatorChangeType {
+    Add,
+    Remove
+}
+
+/*
+@name Identity
+@desc the local node's validator id, and the public half of its keystore key
+*/
+#[derive(Clone,Debug,PartialEq)]
+pub struct Identity {
+    pub identity_id: i32,
+    pub identity_location: String,
+    pub identity_public_key: String,
+    pub identity_key_type: SignatureType
+}
+
+/*
+@name JsonConverter
+@desc
+*/
+pub trait JsonConverter {
+    /*
+    @name to_json
+    @desc convert a validator into a JsonValue
+    */
+    fn to_json(validator: Validator) -> JsonValue;
+
+    /*
+    @name from_json
+    @desc create a validator from a JsonValue
+    */
+    fn from_json(payload: JsonValue) -> Result<Validator, String>;
+
+    /*
+    @name validators_from_json
+    @desc parse a {"validators": [...]} document into a vector of validators
+    */
+    fn validators_from_json(payload: JsonValue) -> Result<Vec<Validator>, String>;
+}
+
+impl JsonConverter for Validator {
+    fn to_json(validator: Validator) -> JsonValue {
+        object!{
+            "validator_id" => validator.validator_id,
+            "validator_location" => validator.validator_location,
+            "validator_public_key" => validator.validator_public_key,
+            "validator_key_type" => Signature::type_to_string(validator.validator_key_type)
+        }
+    }
+
+    fn from_json(payload: JsonValue) -> Result<Validator, String> {
+        let validator_id: Option<i32> = payload["validator_id"].as_i32();
+        let validator_location: Option<&str> = payload["validator_location"].as_str();
+        let validator_public_key: Option<&str> = payload["validator_public_key"].as_str();
+        // validators registered before Ed25519 have RSA keys
+        let validator_key_type: Option<SignatureType> = match payload["validator_key_type"].as_str() {
+            Some(key_type) => Signature::type_from_string(key_type),
+            None => Some(SignatureType::RSA)
+        };
+        match (validator_id, validator_location, validator_public_key, validator_key_type) {
+            (Some(id), Some(location), Some(public_key), Some(key_type)) => {
+                Ok(Validator {
+                    validator_id: id,
+                    validator_location: String::from(location),
+                    validator_public_key: String::from(public_key),
+                    validator_key_type: key_type
+                })
+            },
+            _ => {
+                Err(String::from("Validator, ERROR: from_json, validator is missing id, location or public key, or has an unknown key type"))
+            }
+        }
+    }
+
+    fn validators_from_json(payload: JsonValue) -> Result<Vec<Validator>, String> {
+        if !payload["validators"].is_array() {
+            return Err(String::from("Validator, ERROR: validators_from_json, validators is not an array"))
+        }
+        let mut validators: Vec<Validator> = Vec::new();
+        for validator_json in payload["validators"].members() {
+            validators.push( Self::from_json(validator_json.clone())? );
+        }
+        Ok(validators)
+    }
+}
+
+/*
+@name CreateValidatorIndex
+@desc to initially create the validator index
+*/
+pub trait CreateValidatorIndex {
+    fn create_validator_index() -> ();
+}
+
+impl CreateValidatorIndex for Validator {
+    fn create_validator_index() -> () {
+        let new_validator_index = object!{
+            "validators" => object!{}
+        };
+        let index_to_write: String = json::stringify(new_validator_index);
+        match DB::write_validator_index(index_to_write) {
+            Ok(_) => {
+                println!("Successfully wrote validator index");
+            },
+            Err(_) => {
+                println!("Failure writing validator index");
+            }
+        }
+    }
+}
+
+/*
+@name ReadValidatorFromDB
+@desc trait to read validators from the db
+*/
+pub trait ReadValidatorFromDB {
+    fn get_validator_index_as_json() -> Option<JsonValue>;
+    fn get_all_validators() -> Vec<Validator>;
+    fn get_validator_by_id(validator_id: i32) -> Option<Validator>;
+}
+
+impl ReadValidatorFromDB for DB {
+    /*
+    @name get_validator_index_as_json
+    @desc return the validator index as a json object
+    */
+    fn get_validator_index_as_json() -> Option<JsonValue> {
+        match DB::read_validator_index() {
+            Some(validator_index) => {
+                match json::parse( &format!(r#"{}"#, validator_index) ) {
+                    Ok(parsed) => Some(parsed),
+                    Err(_) => None
+                }
+            },
+            None => None
+        }
+    }
+
+    /*
+    @name get_all_validators
+    @desc get every registered validator, ordered by validator id
+    */
+    fn get_all_validators() -> Vec<Validator> {
+        let mut all_validators: Vec<Validator> = Vec::new();
+        match Self::get_validator_index_as_json() {
+            Some(validator_index) => {
+                for (_id, validator_json) in validator_index["validators"].entries() {
+                    match Validator::from_json(validator_json.clone()) {
+                        Ok(validator) => all_validators.push(validator),
+                        Err(err) => println!("get_all_validators ERROR: {}", err)
+                    }
+                }
+            },
+            None => {
+                println!("get_all_validators ERROR: validator index could not be read");
+            }
+        }
+        all_validators.sort_by_key(|validator| validator.validator_id);
+        all_validators
+    }
+
+    /*
+    @name get_validator_by_id
+    @desc look up a single validator by its node id
+    */
+    fn get_validator_by_id(validator_id: i32) -> Option<Validator> {
+        match Self::get_validator_index_as_json() {
+            Some(validator_index) => {
+                let validator_json: JsonValue = validator_index["validators"][validator_id.to_string().as_str()].clone();
+                match Validator::from_json(validator_json) {
+                    Ok(validator) => Some(validator),
+                    Err(_) => None
+                }
+            },
+            None => None
+        }
+    }
+}
+
+/*
+@name WriteValidatorToDB
+@desc trait to write a validator to the db
+*/
+pub trait WriteValidatorToDB {
+    fn write_validator(validator: Validator) -> Result<String, String>;
+}
+
+impl WriteValidatorToDB for DB {
+    /*
+    @name write_validator
+    @desc insert or replace a validator in the validator index
+    */
+    fn write_validator(validator: Validator) -> Result<String, String> {
+        match Self::get_validator_index_as_json() {
+            Some(mut validator_index) => {
+                validator_index["validators"][validator.validator_id.to_string().as_str()] = Validator::to_json(validator.clone());
+                match DB::write_validator_index(validator_index.dump()) {
+                    Ok(_) => Ok(format!("write_validator, Successfully wrote validator {}", validator.validator_id)),
+                    Err(_) => Err(String::from("write_validator ERROR: could not write validator index"))
+                }
+            },
+            None => {
+                Err(String::from("write_validator ERROR: validator index could not be read"))
+            }
+        }
+    }
+}
+
+/*
+@name RegisterValidators
+@desc register validators from a {"validators": [...]} JSON document
+*/
+pub trait RegisterValidators {
+    fn register_validators(validators_json: String) -> Result<usize, String>;
+}
+
+impl RegisterValidators for Validator {
+    fn register_validators(validators_json: String) -> Result<usize, String> {
+        let parsed: JsonValue = match json::parse( &format!(r#"{}"#, validators_json) ) {
+            Ok(parsed) => parsed,
+            Err(_) => return Err(String::from("register_validators ERROR: could not parse validators JSON"))
+        };
+        let validators: Vec<Validator> = Self::validators_from_json(parsed)?;
+        for validator in validators.clone() {
+            DB::write_validator(validator)?;
+        }
+        Ok(validators.len())
+    }
+}
+
+/*
+@name GetValidators
+@desc get validators without exposing the DB struct
+*/
+pub trait GetValidators {
+    fn get_all_validators() -> Vec<Validator>;
+    fn get_validator_by_id(validator_id: i32) -> Option<Validator>;
+}
+
+impl GetValidators for Validator {
+    fn get_all_validators() -> Vec<Validator> {
+        DB::get_all_validators()
+    }
+
+    fn get_validator_by_id(validator_id: i32) -> Option<Validator> {
+        DB::get_validator_by_id(validator_id)
+    }
+}
+
+/*
+@name ValidatorChangeJson
+@desc
+*/
+pub trait ValidatorChangeJson {
+    fn change_type_to_string(change_type: ValidatorChangeType) -> &'static str;
+    fn change_to_json(change: ValidatorChange) -> JsonValue;
+    fn change_from_json(payload: JsonValue) -> Result<ValidatorChange, String>;
+    fn hash_change(change: ValidatorChange, chain_id: String) -> String;
+}
+
+impl ValidatorChangeJson for Validator {
+    fn change_type_to_string(change_type: ValidatorChangeType) -> &'static str {
+        match change_type {
+            ValidatorChangeType::Add => "add",
+            ValidatorChangeType::Remove => "remove"
+        }
+    }
+
+    fn change_to_json(change: ValidatorChange) -> JsonValue {
+        object!{
+            "change_type" => Self::change_type_to_string(change.change_type),
+            "change_validator" => Self::to_json(change.change_validator),
+            "change_activation_height" => change.change_activation_height
+        }
+    }
+
+    fn change_from_json(payload: JsonValue) -> Result<ValidatorChange, String> {
+        let change_type: ValidatorChangeType = match payload["change_type"].as_str() {
+            Some("add") => ValidatorChangeType::Add,
+            Some("remove") => ValidatorChangeType::Remove,
+            _ => return Err(String::from("Validator, ERROR: change_from_json, change type is not add or remove"))
+        };
+        match payload["change_activation_height"].as_i64() {
+            Some(activation_height) => {
+                Ok(ValidatorChange {
+                    change_type: change_type,
+                    change_validator: Self::from_json(payload["change_validator"].clone())?,
+                    change_activation_height: activation_height
+                })
+            },
+            None => Err(String::from("Validator, ERROR: change_from_json, change has no activation height"))
+        }
+    }
+
+    /*
+    @name hash_change
+    @desc the hash validators approve: the whole change as JSON, key type
+          included, and the chain id, with keys inserted in this order
+    */
+    fn hash_change(change: ValidatorChange, chain_id: String) -> String {
+        let content: JsonValue = object!{
+            "validator_change" => Self::change_to_json(change),
+            "chain_id" => chain_id
+        };
+        Hasher::calculate_sha256( content.dump() )
+    }
+}
+
+/*
+@name ValidatorSchedule
+@desc the validator set at a block height is the registered set, with every
+      scheduled change activated at or below that height applied in order.
+      Changes live in the state, so every node derives the same set
+*/
+pub trait ValidatorSchedule {
+    fn validators_in_state(state: JsonValue, height: i64) -> Vec<Validator>;
+    fn get_validators_at(height: i64) -> Vec<Validator>;
+    fn get_validator_at(height: i64, validator_id: i32) -> Option<Validator>;
+    fn check_approvals(validators: Vec<Validator>, change: ValidatorChange, chain_id: String, approvals: JsonValue) -> Result<usize, String>;
+    fn schedule_change(state: JsonValue, block_id: i64, chain_id: String, signed_change: JsonValue) -> Result<JsonValue, String>;
+}
+
+impl ValidatorSchedule for Validator {
+    fn validators_in_state(state: JsonValue, height: i64) -> Vec<Validator> {
+        let mut validators: Vec<Validator> = Self::get_all_validators();
+        for change_json in state[VALIDATOR_CHANGES_KEY].members() {
+            let change: ValidatorChange = match Self::change_from_json(change_json.clone()) {
+                Ok(change) => change,
+                Err(_) => continue
+            };
+            if change.change_activation_height > height {
+                continue
+            }
+            let validator_id: i32 = change.change_validator.validator_id;
+            validators.retain(|validator| validator.validator_id != validator_id);
+            if change.change_type == ValidatorChangeType::Add {
+                validators.push(change.change_validator);
+            }
+        }
+        validators.sort_by_key(|validator| validator.validator_id);
+        validators
+    }
+
+    /*
+    @name get_validators_at
+    @desc changes are scheduled after the block that includes them, so
+          our latest state knows every change up to the next block
+    */
+    fn get_validators_at(height: i64) -> Vec<Validator> {
+        let state: JsonValue = match DB::read_state() {
+            Some(state_string) => json::parse(&state_string).unwrap_or(object!{}),
+            None => object!{}
+        };
+        Self::validators_in_state(state, height)
+    }
+
+    fn get_validator_at(height: i64, validator_id: i32) -> Option<Validator> {
+        Self::get_validators_at(height).into_iter()
+                                       .find(|validator| validator.validator_id == validator_id)
+    }
+
+    /*
+    @name check_approvals
+    @desc every approval must be a valid signature of the change, from a
+          distinct validator of the set, and more than 2/3 of the set must approve
+    */
+    fn check_approvals(validators: Vec<Validator>, change: ValidatorChange, chain_id: String, approvals: JsonValue) -> Result<usize, String> {
+        let change_hash: String = Self::hash_change(change, chain_id);
+        let mut approvers: Vec<i32> = Vec::new();
+        for approval in approvals.members() {
+            let (signer_id, signature): (i32, &str) = match (approval["signer_id"].as_i32(), approval["signature"].as_str()) {
+                (Some(signer_id), Some(signature)) => (signer_id, signature),
+                _ => return Err(String::from("check_approvals, approval has no signer or signature"))
+            };
+            if approvers.contains(&signer_id) {
+                return Err(format!("check_approvals, {} approved more than once", signer_id))
+            }
+            let signer: &Validator = match validators.iter().find(|validator| validator.validator_id == signer_id) {
+                Some(signer) => signer,
+                None => return Err(format!("check_approvals, signer {} is not a validator", signer_id))
+            };
+            let digital_signature: DigitalSignature = DigitalSignature {
+                signature_type: signer.validator_key_type.clone(),
+                format_type: SignatureFormat::BYTES
+            };
+            if Signature::verify_with_key(digital_signature,
+                                          String::from(signature),
+                                          change_hash.as_bytes(),
+                                          signer.validator_public_key.clone()).is_err() {
+                return Err(format!("check_approvals, signature from {} is not valid", signer_id))
+            }
+            approvers.push(signer_id);
+        }
+        if approvers.len() * 3 <= validators.len() * 2 {
+            return Err(format!("check_approvals, {} of {} validators approved, more than 2/3 must", approvers.len(), validators.len()))
+        }
+        Ok(approvers.len())
+    }
+
+    /*
+    @name schedule_change
+    @desc record a change approved by more than 2/3 of the validators at this
+          block, if it activates after this block and leaves at least one validator
+    */
+    fn schedule_change(state: JsonValue, block_id: i64, chain_id: String, signed_change: JsonValue) -> Result<JsonValue, String> {
+        let change: ValidatorChange = Self::change_from_json(signed_change["validator_change"].clone())?;
+        if change.change_activation_height <= block_id {
+            return Err(format!("schedule_change, activation height {} is not after block {}", change.change_activation_height, block_id))
+        }
+        if !signed_change["approvals"].is_array() {
+            return Err(String::from("schedule_change, change has no approvals"))
+        }
+        Self::check_approvals(Self::validators_in_state(state.clone(), block_id),
+                              change.clone(),
+                              chain_id,
+                              signed_change["approvals"].clone())?;
+        let validators_at_activation: Vec<Validator> = Self::validators_in_state(state.clone(), change.change_activation_height);
+        let already_validator: bool = validators_at_activation.iter()
+                                                              .any(|validator| validator.validator_id == change.change_validator.validator_id);
+        match change.change_type {
+            ValidatorChangeType::Add if already_validator => {
+                return Err(format!("schedule_change, {} is already a validator", change.change_validator.validator_id))
+            },
+            ValidatorChangeType::Remove if !already_validator => {
+                return Err(format!("schedule_change, {} is not a validator", change.change_validator.validator_id))
+            },
+            ValidatorChangeType::Remove if validators_at_activation.len() == 1 => {
+                return Err(String::from("schedule_change, cannot remove the last validator"))
+            },
+            _ => {}
+        }
+        let mut new_state: JsonValue = state;
+        if !new_state[VALIDATOR_CHANGES_KEY].is_array() {
+            new_state[VALIDATOR_CHANGES_KEY] = JsonValue::new_array();
+        }
+        match new_state[VALIDATOR_CHANGES_KEY].push(Self::change_to_json(change)) {
+            Ok(_) => Ok(new_state),
+            Err(_) => Err(String::from("schedule_change, could not record change"))
+        }
+    }
+}
+
+/*
+@name ElectValidator
+@desc the creator of the block after latest_block_id, out of the
+      validators active at that block
+*/
+pub trait ElectValidator {
+    fn elected_validator(validators: Vec<Validator>, latest_block_id: i64) -> Option<Validator>;
+}
+
+impl ElectValidator for Validator {
+    fn elected_validator(validators: Vec<Validator>, latest_block_id: i64) -> Option<Validator> {
+        if validators.is_empty() {
+            return None
+        }
+        // a node counts its neighbors, so exclude ourselves from the validator count
+        let elected_position: i64 = Executor::execute_proposal_creator_election(validators.len() - 1, latest_block_id);
+        // ids are not contiguous once validators leave, so elect by position in the ordered set
+        validators.get((elected_position - 1) as usize).cloned()
+    }
+}
+
+/*
+@name SignValidatorChange
+@desc approve a validator change with this node's identity. The operator
+      collects approvals from the validators, and submits the change with them
+*/
+pub trait SignValidatorChange {
+    fn sign_change(change: ValidatorChange) -> Result<JsonValue, String>;
+}
+
+impl SignValidatorChange for Validator {
+    fn sign_change(change: ValidatorChange) -> Result<JsonValue, String> {
+        let identity: Identity = match Identity::read_identity() {
+            Some(identity) => identity,
+            None => return Err(String::from("sign_change ERROR: no node identity to sign the change with"))
+        };
+        let chain_id: String = DB::read_chain_id().unwrap_or(String::from(""));
+        match Keystore::sign(Self::hash_change(change.clone(), chain_id).as_bytes()) {
+            Ok(signature) => {
+                Ok(object!{
+                    "signer_id" => identity.identity_id,
+                    "signature" => signature
+                })
+            },
+            Err(err) => Err(format!("sign_change ERROR: could not sign change: {}", err))
+        }
+    }
+}
+
+/*
+@name IdentityManager
+@desc store and read the identity this node signs with
+*/
+pub trait IdentityManager {
+    fn store_identity(identity: Identity) -> Result<String, String>;
+    fn read_identity() -> Option<Identity>;
+}
+
+impl IdentityManager for Identity {
+    fn store_identity(identity: Identity) -> Result<String, String> {
+        let identity_json: JsonValue = object!{
+            "identity_id" => identity.identity_id,
+            "identity_location" => identity.identity_location,
+            "identity_public_key" => identity.identity_public_key,
+            "identity_key_type" => Signature::type_to_string(identity.identity_key_type)
+        };
+        match DB::write_identity(identity_json.dump()) {
+            Ok(result) => Ok(result),
+            Err(_) => Err(String::from("store_identity ERROR: could not write identity"))
+        }
+    }
+
+    fn read_identity() -> Option<Identity> {
+        let identity_string: String = DB::read_identity()?;
+        let parsed: JsonValue = json::parse( &format!(r#"{}"#, identity_string) ).ok()?;
+        Some(Identity {
+            identity_id: parsed["identity_id"].as_i32()?,
+            identity_location: String::from( parsed["identity_location"].as_str()? ),
+            identity_public_key: String::from( parsed["identity_public_key"].as_str()? ),
+            identity_key_type: match parsed["identity_key_type"].as_str() {
+                Some(key_type) => Signature::type_from_string(key_type)?,
+                None => SignatureType::RSA
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Validator,
+                ValidatorChange,
+                ValidatorChangeType,
+                ValidatorChangeJson,
+                ValidatorSchedule,
+                JsonConverter,
+                SignatureType};
+    use signature::{Signature,
+                    Signer,
+                    KeyGenerator,
+                    HDKeyDerivation,
+                    PrivateKey};
+    use json::JsonValue;
+
+    fn test_validator(validator_id: i32) -> (Validator, PrivateKey) {
+        let private_key: PrivateKey = Signature::derive_key(&[7u8; 64], 0, validator_id as u32).unwrap();
+        let public_key: String = Signature::public_key_to_base64(&Signature::public_key(&private_key).unwrap()).unwrap();
+        let validator: Validator = Validator {
+            validator_id: validator_id,
+            validator_location: format!("127.0.0.1:808{}", validator_id),
+            validator_public_key: public_key,
+            validator_key_type: SignatureType::ED25519
+        };
+        (validator, private_key)
+    }
+
+    fn test_approval(signer_id: i32, private_key: &PrivateKey, change: ValidatorChange) -> JsonValue {
+        object!{
+            "signer_id" => signer_id,
+            "signature" => Signature::sign(private_key, Validator::hash_change(change, String::from("africaos-test")).as_bytes()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_validator_json_round_trip() {
+        let validator: Validator = Validator {
+            validator_id: 2,
+            validator_location: String::from("127.0.0.1:8082"),
+            validator_public_key: String::from("MIIBCgKCAQEA"),
+            validator_key_type: SignatureType::ED25519
+        };
+        let converted: Result<Validator, String> = Validator::from_json(Validator::to_json(validator.clone()));
+        assert_eq!(converted.unwrap(), validator);
+    }
+
+    #[test]
+    fn test_validators_from_json() {
+        let payload = json::parse(r#"{"validators": [
+            {"validator_id": 1, "validator_location": "127.0.0.1:8081", "validator_public_key": "KEY1"},
+            {"validator_id": 2, "validator_location": "127.0.0.1:8082", "validator_public_key": "KEY2"}
+        ]}"#).unwrap();
+        let validators: Vec<Validator> = Validator::validators_from_json(payload).unwrap();
+        assert_eq!(validators.len(), 2);
+        assert_eq!(validators[1].validator_public_key, String::from("KEY2"));
+        assert_eq!(validators[1].validator_key_type, SignatureType::RSA);
+    }
+
+    #[test]
+    fn test_validator_missing_key_is_rejected() {
+        let payload = object!{
+            "validator_id" => 1,
+            "validator_location" => "127.0.0.1:8081"
+        };
+        assert!(Validator::from_json(payload).is_err());
+        let payload = object!{
+            "validator_id" => 1,
+            "validator_location" => "127.0.0.1:8081",
+            "validator_public_key" => "KEY1",
+            "validator_key_type" => "dsa"
+        };
+        assert!(Validator::from_json(payload).is_err());
+    }
+
+    #[test]
+    fn test_validator_change_json_round_trip() {
+        let change: ValidatorChange = ValidatorChange {
+            change_type: ValidatorChangeType::Remove,
+            change_validator: Validator {
+                validator_id: 4,
+                validator_location: String::from("127.0.0.1:8084"),
+                validator_public_key: String::from("KEY4"),
+                validator_key_type: SignatureType::RSA
+            },
+            change_activation_height: 12
+        };
+        let converted: ValidatorChange = Validator::change_from_json(Validator::change_to_json(change.clone())).unwrap();
+        assert_eq!(converted, change);
+        let chain_id: String = String::from("africaos-test");
+        let mut later: ValidatorChange = change.clone();
+        later.change_activation_height = 13;
+        assert_ne!(Validator::hash_change(change.clone(), chain_id.clone()), Validator::hash_change(later, chain_id.clone()));
+        let mut other_key_type: ValidatorChange = change.clone();
+        other_key_type.change_validator.validator_key_type = SignatureType::ED25519;
+        assert_ne!(Validator::hash_change(change.clone(), chain_id.clone()), Validator::hash_change(other_key_type, chain_id.clone()));
+        assert_ne!(Validator::hash_change(change.clone(), chain_id), Validator::hash_change(change, String::from("africaos-other")));
+    }
+
+    #[test]
+    fn test_validator_change_needs_two_thirds_of_validators() {
+        let keyed: Vec<(Validator, PrivateKey)> = (1..5).map(test_validator).collect();
+        let validators: Vec<Validator> = keyed.iter().map(|(validator, _)| validator.clone()).collect();
+        let change: ValidatorChange = ValidatorChange {
+            change_type: ValidatorChangeType::Remove,
+            change_validator: validators[3].clone(),
+            change_activation_height: 12
+        };
+        let approve = |count: usize| -> JsonValue {
+            let mut approvals: JsonValue = JsonValue::new_array();
+            for (validator, private_key) in keyed.iter().take(count) {
+                approvals.push(test_approval(validator.validator_id, private_key, change.clone())).unwrap();
+            }
+            approvals
+        };
+        let chain_id: String = String::from("africaos-test");
+        // 2 of 4 is not more than 2/3, 3 of 4 is
+        assert!(Validator::check_approvals(validators.clone(), change.clone(), chain_id.clone(), approve(2)).is_err());
+        assert_eq!(Validator::check_approvals(validators.clone(), change.clone(), chain_id.clone(), approve(3)), Ok(3));
+
+        // one validator cannot approve twice
+        let mut repeated: JsonValue = approve(2);
+        repeated.push(test_approval(1, &keyed[0].1, change.clone())).unwrap();
+        assert!(Validator::check_approvals(validators.clone(), change.clone(), chain_id.clone(), repeated).is_err());
+
+        // nor sign for another validator
+        let mut forged: JsonValue = approve(2);
+        forged.push(test_approval(3, &keyed[0].1, change.clone())).unwrap();
+        assert!(Validator::check_approvals(validators.clone(), change.clone(), chain_id.clone(), forged).is_err());
+
+        // approvals of another change do not count
+        let mut later: ValidatorChange = change.clone();
+        later.change_activation_height = 13;
+        assert!(Validator::check_approvals(validators.clone(), later, chain_id.clone(), approve(4)).is_err());
+
+        // nor approvals made on another chain
+        assert!(Validator::check_approvals(validators, change.clone(), String::from("africaos-other"), approve(4)).is_err());
+    }
+}
@@ -0,0 +1,490 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use json::{JsonValue};
+use std::path::Path;
+use std::fs;
+use reqwest::header::{USER_AGENT, ORIGIN};
+use transaction::{Transaction, SignInput};
+use amount::{Amount};
+use signature::{Signature,
+                SignatureType,
+                PrivateKey,
+                PublicKey,
+                Signer,
+                KeyGenerator,
+                SignatureTypeString,
+                MnemonicPhrase,
+                HDKeyDerivation,
+                AddressEncoding};
+
+/*
+@name Wallet
+@desc an account holder's keys, and the transactions they submit to a node
+*/
+pub struct Wallet {}
+
+/*
+@name WalletKeys
+@desc the keys a wallet receives outputs with and signs inputs with
+*/
+pub trait WalletKeys {
+    fn generate_key(key_type: SignatureType, key_path: &Path) -> Result<PrivateKey, String>;
+    fn read_key(key_type: SignatureType, key_path: &Path) -> Result<PrivateKey, String>;
+    fn public_key(private_key: &PrivateKey) -> Result<String, String>;
+
+    /*
+    @name public_key_hash
+    @desc the public key hash outputs are sent to
+    */
+    fn public_key_hash(public_key: String) -> String;
+
+    /*
+    @name address
+    @desc the public key hash with a checksum, what a wallet hands out
+    */
+    fn address(public_key: String) -> Result<String, String>;
+
+    /*
+    @name recipient_hash
+    @desc the public key hash to send an output to, from an address or the hash itself
+    */
+    fn recipient_hash(recipient: &str) -> Result<String, String>;
+}
+
+impl WalletKeys for Wallet {
+    fn generate_key(key_type: SignatureType, key_path: &Path) -> Result<PrivateKey, String> {
+        if key_path.exists() {
+            return Err(format!("generate_key ERROR: {} already exists", key_path.display()))
+        }
+        Signature::generate_key(key_type, key_path).map_err(|err| format!("generate_key ERROR: {:?}", err))
+    }
+
+    fn read_key(key_type: SignatureType, key_path: &Path) -> Result<PrivateKey, String> {
+        Signature::read_private_key(key_type, key_path).map_err(|err| format!("read_key ERROR: could not read {}: {:?}", key_path.display(), err))
+    }
+
+    fn public_key(private_key: &PrivateKey) -> Result<String, String> {
+        let public_key: PublicKey = Signature::public_key(private_key).map_err(|err| format!("public_key ERROR: {:?}", err))?;
+        Signature::public_key_to_base64(&public_key).map_err(|err| format!("public_key ERROR: {:?}", err))
+    }
+
+    fn public_key_hash(public_key: String) -> String {
+        Transaction::public_key_hash(public_key)
+    }
+
+    fn address(public_key: String) -> Result<String, String> {
+        Signature::encode_address(Self::public_key_hash(public_key).as_str()).map_err(|err| format!("address ERROR: {:?}", err))
+    }
+
+    fn recipient_hash(recipient: &str) -> Result<String, String> {
+        if recipient.len() == 64 && recipient.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(recipient.to_lowercase())
+        }
+        Signature::decode_address(recipient).map_err(|_| format!("recipient_hash ERROR: {} is not an address, or its checksum does not match", recipient))
+    }
+}
+
+/*
+@name HDWallet
+@desc many receiving keys from one seed. The seed file holds the entropy
+      of the mnemonic, keys are derived at m/44'/1'/account'/0'/index'
+*/
+pub trait HDWallet {
+    fn new_seed(word_count: usize, seed_path: &Path) -> Result<String, String>;
+    fn import_mnemonic(phrase: &str, seed_path: &Path) -> Result<(), String>;
+    fn export_mnemonic(seed_path: &Path) -> Result<String, String>;
+    fn derive_key(seed_path: &Path, passphrase: &str, account: u32, index: u32) -> Result<PrivateKey, String>;
+}
+
+impl HDWallet for Wallet {
+    /*
+    @name new_seed
+    @desc store a new seed, and return its mnemonic to be written down
+    */
+    fn new_seed(word_count: usize, seed_path: &Path) -> Result<String, String> {
+        let phrase: String = Signature::generate_mnemonic(word_count).map_err(|_| format!("new_seed ERROR: a mnemonic has 12, 15, 18, 21 or 24 words, not {}", word_count))?;
+        Self::import_mnemonic(phrase.as_str(), seed_path)?;
+        Ok(phrase)
+    }
+
+    fn import_mnemonic(phrase: &str, seed_path: &Path) -> Result<(), String> {
+        if seed_path.exists() {
+            return Err(format!("import_mnemonic ERROR: {} already exists", seed_path.display()))
+        }
+        let entropy: Vec<u8> = Signature::mnemonic_to_entropy(phrase).map_err(|_| String::from("import_mnemonic ERROR: not a valid mnemonic"))?;
+        fs::write(seed_path, entropy).map_err(|err| format!("import_mnemonic ERROR: could not write {}: {}", seed_path.display(), err))
+    }
+
+    fn export_mnemonic(seed_path: &Path) -> Result<String, String> {
+        let entropy: Vec<u8> = fs::read(seed_path).map_err(|err| format!("export_mnemonic ERROR: could not read {}: {}", seed_path.display(), err))?;
+        Signature::mnemonic_from_entropy(&entropy).map_err(|_| format!("export_mnemonic ERROR: {} is not a seed", seed_path.display()))
+    }
+
+    fn derive_key(seed_path: &Path, passphrase: &str, account: u32, index: u32) -> Result<PrivateKey, String> {
+        let phrase: String = Self::export_mnemonic(seed_path)?;
+        let seed: Vec<u8> = Signature::mnemonic_to_seed(phrase.as_str(), passphrase).map_err(|_| String::from("derive_key ERROR: not a valid mnemonic"))?;
+        Signature::derive_key(&seed, account, index).map_err(|err| format!("derive_key ERROR: {:?}", err))
+    }
+}
+
+/*
+@name BuildTransaction
+@desc the submission data a node turns into a transaction, with the fee
+      and nonce options appended. Both are signed, outputs by the sender
+      and inputs by the keys claiming the output. An output's timelock is
+      the block id and unix time it cannot be claimed before
+*/
+pub trait BuildTransaction {
+    fn output_submission(recipient_address: String, amount: Amount, timelock: (Option<i64>, Option<i64>)) -> String;
+    fn multisig_output_submission(threshold: usize, recipient_addresses: Vec<String>, amount: Amount, timelock: (Option<i64>, Option<i64>)) -> String;
+    fn sign_output(private_key: &PrivateKey,
+                   output: String,
+                   fee: Amount,
+                   nonce: i64,
+                   chain_id: String) -> Result<String, String>;
+    fn input_submission(private_key: &PrivateKey,
+                        partner_hash: String,
+                        recipient: String,
+                        amount: Amount,
+                        fee: Amount,
+                        nonce: i64,
+                        chain_id: String) -> Result<String, String>;
+    fn approve_input(private_key: &PrivateKey,
+                     partner_hash: String,
+                     recipient: String,
+                     amount: Amount,
+                     fee: Amount,
+                     nonce: i64,
+                     chain_id: String) -> Result<String, String>;
+    fn multisig_input_submission(partner_hash: String,
+                                 approvals: Vec<String>,
+                                 fee: Amount,
+                                 nonce: i64) -> Result<String, String>;
+    fn timelock_options(timelock: (Option<i64>, Option<i64>)) -> String;
+    fn submission_options(fee: Amount, nonce: i64) -> String;
+}
+
+impl BuildTransaction for Wallet {
+    /*
+    @name output_submission
+    @desc <pkhash> <amount>, before the sender signs it
+    */
+    fn output_submission(recipient_address: String, amount: Amount, timelock: (Option<i64>, Option<i64>)) -> String {
+        format!("{} {}{}", recipient_address, amount, Self::timelock_options(timelock))
+    }
+
+    /*
+    @name multisig_output_submission
+    @desc multisig <M> <pkhash,...> <amount>, claimed by an input with M of the keys' approvals
+    */
+    fn multisig_output_submission(threshold: usize, recipient_addresses: Vec<String>, amount: Amount, timelock: (Option<i64>, Option<i64>)) -> String {
+        format!("multisig {} {} {}{}", threshold, recipient_addresses.join(","), amount, Self::timelock_options(timelock))
+    }
+
+    /*
+    @name sign_output
+    @desc the output with the sender's signature, public key and key type.
+          The sender is the account of the key's public key hash
+    */
+    fn sign_output(private_key: &PrivateKey,
+                   output: String,
+                   fee: Amount,
+                   nonce: i64,
+                   chain_id: String) -> Result<String, String> {
+        let public_key: String = Self::public_key(private_key)?;
+        let content: String = Transaction::output_signing_content(output.clone(), Self::public_key_hash(public_key.clone()), fee, nonce, chain_id);
+        let signature: String = Signature::sign(private_key, content.as_bytes()).map_err(|err| format!("sign_output ERROR: could not sign output: {:?}", err))?;
+        Ok(format!("{} sender_signature={} sender_public_key={} sender_key_type={}{}",
+                   output,
+                   signature,
+                   public_key,
+                   Signature::type_to_string(private_key.key_type.clone()),
+                   Self::submission_options(fee, nonce)))
+    }
+
+    /*
+    @name input_submission
+    @desc <partner_tx_hash> <sig> <pk> <key_type>, signed over the input's
+          canonical content, so the fee and nonce must be the ones it is submitted with.
+          The output's sender pays, the node knows it from the output
+    */
+    fn input_submission(private_key: &PrivateKey,
+                        partner_hash: String,
+                        recipient: String,
+                        amount: Amount,
+                        fee: Amount,
+                        nonce: i64,
+                        chain_id: String) -> Result<String, String> {
+        let approval: String = Self::approve_input(private_key, partner_hash.clone(), recipient, amount, fee, nonce, chain_id)?;
+        Self::multisig_input_submission(partner_hash, vec![approval], fee, nonce)
+    }
+
+    /*
+    @name approve_input
+    @desc one key holder's signature over an input, <key_type>:<pk>:<sig>,
+          for whoever collects the approvals and submits the input
+    */
+    fn approve_input(private_key: &PrivateKey,
+                     partner_hash: String,
+                     recipient: String,
+                     amount: Amount,
+                     fee: Amount,
+                     nonce: i64,
+                     chain_id: String) -> Result<String, String> {
+        let content: String = Transaction::input_signing_content(partner_hash, recipient, amount, fee, nonce, chain_id);
+        let signature: String = Signature::sign(private_key, content.as_bytes()).map_err(|err| format!("approve_input ERROR: could not sign input: {:?}", err))?;
+        Ok(format!("{}:{}:{}", Signature::type_to_string(private_key.key_type.clone()), Self::public_key(private_key)?, signature))
+    }
+
+    /*
+    @name multisig_input_submission
+    @desc the approvals as comma separated signatures, public keys and key types
+    */
+    fn multisig_input_submission(partner_hash: String,
+                                 approvals: Vec<String>,
+                                 fee: Amount,
+                                 nonce: i64) -> Result<String, String> {
+        let mut signatures: Vec<&str> = Vec::new();
+        let mut public_keys: Vec<&str> = Vec::new();
+        let mut key_types: Vec<&str> = Vec::new();
+        for approval in approvals.iter() {
+            match approval.split(":").collect::<Vec<&str>>().as_slice() {
+                [key_type, public_key, signature] => {
+                    key_types.push(key_type);
+                    public_keys.push(public_key);
+                    signatures.push(signature);
+                },
+                _ => return Err(format!("multisig_input_submission ERROR: {} is not <key_type>:<pk>:<sig>", approval))
+            }
+        }
+        Ok(format!("{} {} {} {}{}",
+                   partner_hash,
+                   signatures.join(","),
+                   public_keys.join(","),
+                   key_types.join(","),
+                   Self::submission_options(fee, nonce)))
+    }
+
+    fn timelock_options(timelock: (Option<i64>, Option<i64>)) -> String {
+        let mut options: String = String::new();
+        if let Some(not_before_block) = timelock.0 {
+            options.push_str(&format!(" not_before_block={}", not_before_block));
+        }
+        if let Some(not_before_time) = timelock.1 {
+            options.push_str(&format!(" not_before_time={}", not_before_time));
+        }
+        options
+    }
+
+    fn submission_options(fee: Amount, nonce: i64) -> String {
+        format!(" fee={} nonce={}", fee, nonce)
+    }
+}
+
+/*
+@name NodeClient
+@desc submit to, and query, a node's API. Data travels in the User-Agent
+      header and the account in the Origin header, as between nodes
+*/
+pub trait NodeClient {
+    fn request(node: &str, endpoint: &str, origin: &str, data: String) -> Result<String, String>;
+    fn submit(node: &str, endpoint: &str, sender: &str, submission: String) -> Result<String, String>;
+    fn account(node: &str, account: &str) -> Result<JsonValue, String>;
+}
+
+impl NodeClient for Wallet {
+    fn request(node: &str, endpoint: &str, origin: &str, data: String) -> Result<String, String> {
+        let node_url: String = format!("http://{}{}", node, endpoint);
+        let client = reqwest::Client::new();
+        let response = client.get(node_url.as_str())
+                             .header(ORIGIN, origin)
+                             .header(USER_AGENT, data)
+                             .send();
+        match response {
+            Ok(mut result) => result.text().map_err(|err| format!("request ERROR: could not read response from {}: {}", node, err)),
+            Err(err) => Err(format!("request ERROR: could not reach {}: {}", node, err))
+        }
+    }
+
+    /*
+    @name submit
+    @desc returns the transaction hash, a refused transaction is answered
+          with the endpoint only, the reason is in the node's log
+    */
+    fn submit(node: &str, endpoint: &str, sender: &str, submission: String) -> Result<String, String> {
+        let response: String = Self::request(node, endpoint, sender, submission)?;
+        match response.trim().splitn(3, " ").collect::<Vec<&str>>().as_slice() {
+            ["Transaction", "Received", transaction_hash] => Ok(String::from(*transaction_hash)),
+            _ => Err(format!("submit ERROR: {} refused the transaction, see its log", node))
+        }
+    }
+
+    fn account(node: &str, account: &str) -> Result<JsonValue, String> {
+        let response: String = Self::request(node, "/API/account/", account, String::from(account))?;
+        match json::parse(&response) {
+            Ok(account_json) if account_json["next_nonce"].is_number() => Ok(account_json),
+            _ => Err(format!("account ERROR: {} did not return account {}", node, account))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Wallet, WalletKeys, BuildTransaction, HDWallet};
+    use transaction::{Transaction, SignInput};
+    use amount::{Amount};
+    use signature::{Signature,
+                    SignatureType,
+                    SignatureFormat,
+                    DigitalSignature,
+                    PrivateKey,
+                    HDKeyDerivation,
+                    Verifier};
+    use std::path::{Path, PathBuf};
+    use std::{env, fs, process};
+
+    // an Ed25519 key derived for the tests, so no key file is needed
+    fn test_key() -> PrivateKey {
+        Signature::derive_key(&[9u8; 64], 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_address_of_the_test_key() {
+        let private_key: PrivateKey = Wallet::read_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let public_key: String = Wallet::public_key(&private_key).unwrap();
+        assert!(public_key.starts_with("MIIBCgKCAQEAuJ3hhGpo"));
+        assert_eq!(Wallet::public_key_hash(public_key.clone()), "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
+
+        // outputs go to the hash, whether it is given as an address or as is
+        let address: String = Wallet::address(public_key).unwrap();
+        assert_eq!(Wallet::recipient_hash(address.as_str()).unwrap(), "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
+        assert_eq!(Wallet::recipient_hash("2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94").unwrap(),
+                   "2675e61eec4ac990977ee70b7b27c22349597ad0381e6c7f02578278e3fddd94");
+        assert!(Wallet::recipient_hash(&address[1..]).is_err());
+    }
+
+    #[test]
+    fn test_seed_import_export_and_derivation() {
+        let seed_path: PathBuf = env::temp_dir().join(format!("aos-wallet-test-{}.seed", process::id()));
+        let phrase: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let _ = fs::remove_file(&seed_path);
+        Wallet::import_mnemonic(phrase, &seed_path).unwrap();
+        assert!(Wallet::import_mnemonic(phrase, &seed_path).is_err());
+        assert_eq!(Wallet::export_mnemonic(&seed_path).unwrap(), phrase);
+
+        let first: PrivateKey = Wallet::derive_key(&seed_path, "", 0, 0).unwrap();
+        let second: PrivateKey = Wallet::derive_key(&seed_path, "", 0, 1).unwrap();
+        let other_account: PrivateKey = Wallet::derive_key(&seed_path, "", 1, 0).unwrap();
+        assert_eq!(Wallet::derive_key(&seed_path, "", 0, 0).unwrap(), first);
+        assert_ne!(Wallet::public_key(&first).unwrap(), Wallet::public_key(&second).unwrap());
+        assert_ne!(Wallet::public_key(&first).unwrap(), Wallet::public_key(&other_account).unwrap());
+        assert_ne!(Wallet::derive_key(&seed_path, "passphrase", 0, 0).unwrap(), first);
+        fs::remove_file(&seed_path).unwrap();
+    }
+
+    #[test]
+    fn test_output_submission_is_signed_by_its_sender() {
+        assert_eq!(Wallet::output_submission(String::from("PKHASH"), Amount { value: 10 }, (None, None)), "PKHASH 10");
+        assert_eq!(Wallet::output_submission(String::from("PKHASH"), Amount { value: 10 }, (Some(100), Some(1700000000))),
+                   "PKHASH 10 not_before_block=100 not_before_time=1700000000");
+        assert_eq!(Wallet::multisig_output_submission(2, vec![String::from("PKHASH1"), String::from("PKHASH2"), String::from("PKHASH3")], Amount { value: 10 }, (None, None)),
+                   "multisig 2 PKHASH1,PKHASH2,PKHASH3 10");
+
+        let private_key: PrivateKey = test_key();
+        let public_key: String = Wallet::public_key(&private_key).unwrap();
+        let submission: String = Wallet::sign_output(&private_key, String::from("PKHASH 10"), Amount { value: 2 }, 5, String::from("africaos-test")).unwrap();
+        let sections: Vec<&str> = submission.split(" ").collect();
+        assert_eq!(&sections[..2], &["PKHASH", "10"]);
+        assert_eq!(sections[3], format!("sender_public_key={}", public_key));
+        assert_eq!(&sections[4..], &["sender_key_type=ed25519", "fee=2", "nonce=5"]);
+
+        let digital_signature: DigitalSignature = DigitalSignature {
+            signature_type: SignatureType::ED25519,
+            format_type: SignatureFormat::BYTES
+        };
+        let content: String = Transaction::output_signing_content(String::from("PKHASH 10"),
+                                                                  Wallet::public_key_hash(public_key.clone()),
+                                                                  Amount { value: 2 },
+                                                                  5,
+                                                                  String::from("africaos-test"));
+        assert!(Signature::verify_with_key(digital_signature,
+                                           String::from(sections[2].trim_start_matches("sender_signature=")),
+                                           content.as_bytes(),
+                                           public_key).is_ok());
+    }
+
+    #[test]
+    fn test_input_submission_is_signed_over_its_content() {
+        let private_key: PrivateKey = test_key();
+        let submission: String = Wallet::input_submission(&private_key,
+                                                          String::from("TESTTXHASH"),
+                                                          String::from("test_recipient"),
+                                                          Amount { value: 10 },
+                                                          Amount { value: 1 },
+                                                          3,
+                                                          String::from("africaos-test")).unwrap();
+        let sections: Vec<&str> = submission.split(" ").collect();
+        assert_eq!(sections.len(), 6);
+        assert_eq!(sections[0], "TESTTXHASH");
+        assert_eq!(sections[2], Wallet::public_key(&private_key).unwrap());
+        assert_eq!(&sections[3..], &["ed25519", "fee=1", "nonce=3"]);
+
+        let digital_signature: DigitalSignature = DigitalSignature {
+            signature_type: SignatureType::ED25519,
+            format_type: SignatureFormat::BYTES
+        };
+        let content: String = Transaction::input_signing_content(String::from("TESTTXHASH"),
+                                                                 String::from("test_recipient"),
+                                                                 Amount { value: 10 },
+                                                                 Amount { value: 1 },
+                                                                 3,
+                                                                 String::from("africaos-test"));
+        assert!(Signature::verify_with_key(digital_signature,
+                                           String::from(sections[1]),
+                                           content.as_bytes(),
+                                           String::from(sections[2])).is_ok());
+    }
+
+    #[test]
+    fn test_multisig_input_submission() {
+        let rsa_key: PrivateKey = Wallet::read_key(SignatureType::RSA, Path::new("../signature/test-fixtures/private.der")).unwrap();
+        let ed25519_key: PrivateKey = test_key();
+        let approve = |private_key: &PrivateKey| -> String {
+            Wallet::approve_input(private_key,
+                                  String::from("TESTTXHASH"),
+                                  String::from("treasury"),
+                                  Amount { value: 10 },
+                                  Amount { value: 1 },
+                                  0,
+                                  String::from("africaos-test")).unwrap()
+        };
+        let rsa_approval: String = approve(&rsa_key);
+        let ed25519_approval: String = approve(&ed25519_key);
+        assert!(rsa_approval.starts_with(&format!("rsa:{}:", Wallet::public_key(&rsa_key).unwrap())));
+
+        let submission: String = Wallet::multisig_input_submission(String::from("TESTTXHASH"),
+                                                                   vec![rsa_approval.clone(), ed25519_approval.clone()],
+                                                                   Amount { value: 1 },
+                                                                   0).unwrap();
+        let sections: Vec<&str> = submission.split(" ").collect();
+        assert_eq!(sections[1], format!("{},{}", rsa_approval.split(":").nth(2).unwrap(), ed25519_approval.split(":").nth(2).unwrap()));
+        assert_eq!(sections[2], format!("{},{}", Wallet::public_key(&rsa_key).unwrap(), Wallet::public_key(&ed25519_key).unwrap()));
+        assert_eq!(&sections[3..], &["rsa,ed25519", "fee=1", "nonce=0"]);
+        assert!(Wallet::multisig_input_submission(String::from("TESTTXHASH"), vec![String::from("rsa")], Amount { value: 1 }, 0).is_err());
+    }
+
+}
@@ -0,0 +1,253 @@
+/*
+Copyright 2018-Present The AfricaOS Authors
+This file is part of the AfricaOS library.
+The AfricaOS Platform is free software: you can redistribute it and/or modify
+it under the terms of the GNU Lesser General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+The AfricaOS Platform is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+GNU Lesser General Public License for more details.
+You should have received a copy of the GNU Lesser General Public License
+along with the AfricaOS Platform. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use wallet::{Wallet, WalletKeys, BuildTransaction, NodeClient, HDWallet};
+use signature::{Signature, SignatureType, PrivateKey, SignatureTypeString};
+use json::{JsonValue};
+use amount::{Amount, StringToAmount};
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: aos-wallet <command> [param=value ...]
+  generate key=<path> [key-type=ed25519]
+  mnemonic seed=<path> [words=24]
+  import   seed=<path> phrase=\"<mnemonic>\"
+  export   seed=<path>
+  address  <key>
+  balance  node=<ip:port> account=<account>
+  output   node=<ip:port> <key> to=<address or pkhash>[,...] [threshold=<M>] amount=<amount> [not-before-block=<block id>] [not-before-time=<unix seconds>] [fee=<fee>] [nonce=<nonce>]
+  approve  node=<ip:port> sender=<input account> <key> partner-hash=<output tx hash> amount=<amount> [fee=<fee>] [nonce=<nonce>]
+  input    node=<ip:port> sender=<account> <key> partner-hash=<output tx hash> amount=<amount> [fee=<fee>] [nonce=<nonce>]
+  input    node=<ip:port> sender=<account> approvals=<approval>,... partner-hash=<output tx hash> fee=<fee> nonce=<nonce>
+<key> is key=<path> [key-type=ed25519|rsa], or seed=<path> [account=0] [index=0] [passphrase=<passphrase>]";
+
+/*
+@name param
+@desc a required param=value
+*/
+fn param<'a>(params: &'a HashMap<String, String>, name: &str) -> Result<&'a str, String> {
+    match params.get(name) {
+        Some(value) => Ok(value.as_str()),
+        None => Err(format!("missing {}=", name))
+    }
+}
+
+fn number_param(params: &HashMap<String, String>, name: &str) -> Result<Option<i64>, String> {
+    match params.get(name) {
+        Some(value) => match value.parse::<i64>() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(format!("{}={} is not a number", name, value))
+        },
+        None => Ok(None)
+    }
+}
+
+/*
+@name amount_param
+@desc the required amount=, a whole number above 0
+*/
+fn amount_param(params: &HashMap<String, String>) -> Result<Amount, String> {
+    Amount::positive_amount(String::from(param(params, "amount")?))
+}
+
+/*
+@name fee_param
+@desc fee=, a whole number above 0, or the chain's minimum fee. It is signed
+*/
+fn fee_param(params: &HashMap<String, String>, account: &JsonValue) -> Result<Amount, String> {
+    match params.get("fee") {
+        Some(fee) => Amount::positive_amount(fee.clone()),
+        None => Ok(Amount { value: account["min_transaction_fee"].as_u64().unwrap_or(1).max(1) })
+    }
+}
+
+fn key_type_param(params: &HashMap<String, String>) -> Result<SignatureType, String> {
+    let key_type: &str = params.get("key-type").map(|key_type| key_type.as_str()).unwrap_or("ed25519");
+    match Signature::type_from_string(key_type) {
+        Some(key_type) => Ok(key_type),
+        None => Err(format!("unknown key type {}", key_type))
+    }
+}
+
+/*
+@name private_key_param
+@desc a key file, or the key derived from a seed at an account and index
+*/
+fn private_key_param(params: &HashMap<String, String>) -> Result<PrivateKey, String> {
+    match params.get("seed") {
+        Some(seed_path) => {
+            let account: i64 = number_param(params, "account")?.unwrap_or(0);
+            let index: i64 = number_param(params, "index")?.unwrap_or(0);
+            // derivation is hardened at every step, so the high bit is not part of the index
+            if account < 0 || account > 0x7fff_ffff || index < 0 || index > 0x7fff_ffff {
+                return Err(String::from("account= and index= are between 0 and 2147483647"))
+            }
+            let passphrase: &str = params.get("passphrase").map(|passphrase| passphrase.as_str()).unwrap_or("");
+            Wallet::derive_key(Path::new(seed_path), passphrase, account as u32, index as u32)
+        },
+        None => Wallet::read_key(key_type_param(params)?, Path::new(param(params, "key")?))
+    }
+}
+
+fn print_address(private_key: &PrivateKey) -> Result<(), String> {
+    let public_key: String = Wallet::public_key(private_key)?;
+    println!("public key: {}", public_key);
+    println!("public key hash: {}", Wallet::public_key_hash(public_key.clone()));
+    println!("address: {}", Wallet::address(public_key)?);
+    Ok(())
+}
+
+fn run(command: &str, params: HashMap<String, String>) -> Result<(), String> {
+    match command {
+        "generate" => {
+            let private_key: PrivateKey = Wallet::generate_key(key_type_param(&params)?, Path::new(param(&params, "key")?))?;
+            print_address(&private_key)?;
+        },
+        "mnemonic" => {
+            let words: i64 = number_param(&params, "words")?.unwrap_or(24);
+            let phrase: String = Wallet::new_seed(words as usize, Path::new(param(&params, "seed")?))?;
+            println!("write down your mnemonic, it restores every key of this seed:");
+            println!("{}", phrase);
+        },
+        "import" => {
+            Wallet::import_mnemonic(param(&params, "phrase")?, Path::new(param(&params, "seed")?))?;
+            println!("seed imported");
+        },
+        "export" => {
+            println!("{}", Wallet::export_mnemonic(Path::new(param(&params, "seed")?))?);
+        },
+        "address" => {
+            print_address(&private_key_param(&params)?)?;
+        },
+        "balance" => {
+            let account: JsonValue = Wallet::account(param(&params, "node")?, param(&params, "account")?)?;
+            println!("balance: {}", account["balance"]);
+            println!("next nonce: {}", account["next_nonce"]);
+        },
+        "output" => {
+            let node: &str = param(&params, "node")?;
+            let private_key: PrivateKey = private_key_param(&params)?;
+            // the sender is the account of the key, and signs the fee, nonce and chain id
+            let sender: String = Wallet::public_key_hash(Wallet::public_key(&private_key)?);
+            let account: JsonValue = Wallet::account(node, sender.as_str())?;
+            let nonce: i64 = match number_param(&params, "nonce")? {
+                Some(nonce) => nonce,
+                None => account["next_nonce"].as_i64().unwrap_or(0)
+            };
+            let amount: Amount = amount_param(&params)?;
+            let mut recipients: Vec<String> = Vec::new();
+            for recipient in param(&params, "to")?.split(",") {
+                recipients.push(Wallet::recipient_hash(recipient)?);
+            }
+            let timelock: (Option<i64>, Option<i64>) = (number_param(&params, "not-before-block")?, number_param(&params, "not-before-time")?);
+            let output: String = match number_param(&params, "threshold")? {
+                Some(threshold) if threshold >= 1 && threshold as usize <= recipients.len() => {
+                    Wallet::multisig_output_submission(threshold as usize, recipients, amount, timelock)
+                },
+                Some(threshold) => return Err(format!("threshold={} is not between 1 and the {} recipients", threshold, recipients.len())),
+                None if recipients.len() == 1 => {
+                    Wallet::output_submission(recipients.remove(0), amount, timelock)
+                },
+                None => return Err(String::from("an output to several recipients needs threshold="))
+            };
+            let submission: String = Wallet::sign_output(&private_key,
+                                                         output,
+                                                         fee_param(&params, &account)?,
+                                                         nonce,
+                                                         String::from(account["chain_id"].as_str().unwrap_or("")))?;
+            let transaction_hash: String = Wallet::submit(node, "/transaction/submit/output/", sender.as_str(), submission)?;
+            println!("output submitted: {}", transaction_hash);
+        },
+        "approve" => {
+            let node: &str = param(&params, "node")?;
+            let sender: &str = param(&params, "sender")?;
+            let private_key: PrivateKey = private_key_param(&params)?;
+            let amount: Amount = amount_param(&params)?;
+            // every approval signs the same nonce, the one the input is submitted with
+            let account: JsonValue = Wallet::account(node, sender)?;
+            let nonce: i64 = match number_param(&params, "nonce")? {
+                Some(nonce) => nonce,
+                None => account["next_nonce"].as_i64().unwrap_or(0)
+            };
+            let fee: Amount = fee_param(&params, &account)?;
+            let approval: String = Wallet::approve_input(&private_key,
+                                                         String::from(param(&params, "partner-hash")?),
+                                                         String::from(sender),
+                                                         amount,
+                                                         fee,
+                                                         nonce,
+                                                         String::from(account["chain_id"].as_str().unwrap_or("")))?;
+            println!("fee: {}", fee);
+            println!("nonce: {}", nonce);
+            println!("approval: {}", approval);
+        },
+        "input" => {
+            let node: &str = param(&params, "node")?;
+            let sender: &str = param(&params, "sender")?;
+            let partner_hash: String = String::from(param(&params, "partner-hash")?);
+            let submission: String = match params.get("approvals") {
+                Some(approvals) => {
+                    let nonce: i64 = number_param(&params, "nonce")?.ok_or(String::from("missing nonce=, the one the approvals were signed for"))?;
+                    let fee: Amount = Amount::positive_amount(String::from(param(&params, "fee").map_err(|_| String::from("missing fee=, the one the approvals were signed for"))?))?;
+                    Wallet::multisig_input_submission(partner_hash,
+                                                      approvals.split(",").map(String::from).collect(),
+                                                      fee,
+                                                      nonce)?
+                },
+                None => {
+                    let private_key: PrivateKey = private_key_param(&params)?;
+                    let amount: Amount = amount_param(&params)?;
+                    // the signature covers the fee, nonce and chain id, so they are fixed before signing
+                    let account: JsonValue = Wallet::account(node, sender)?;
+                    let nonce: i64 = match number_param(&params, "nonce")? {
+                        Some(nonce) => nonce,
+                        None => account["next_nonce"].as_i64().unwrap_or(0)
+                    };
+                    Wallet::input_submission(&private_key,
+                                             partner_hash,
+                                             String::from(sender),
+                                             amount,
+                                             fee_param(&params, &account)?,
+                                             nonce,
+                                             String::from(account["chain_id"].as_str().unwrap_or("")))?
+                }
+            };
+            let transaction_hash: String = Wallet::submit(node, "/transaction/submit/input/", sender, submission)?;
+            println!("input submitted: {}", transaction_hash);
+        },
+        _ => return Err(String::from(USAGE))
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command: String = args.first().cloned().unwrap_or(String::new());
+    let mut params: HashMap<String, String> = HashMap::new();
+    for arg in args.iter().skip(1) {
+        let split_param_by_assignment: Vec<&str> = arg.splitn(2, "=").collect();
+        if split_param_by_assignment.len() == 2 {
+            params.insert(String::from(split_param_by_assignment[0]), String::from(split_param_by_assignment[1]));
+        } else {
+            println!("Param {} has no key and value combination", arg);
+        }
+    }
+    if let Err(err) = run(command.as_str(), params) {
+        println!("aos-wallet: {}", err);
+        process::exit(1);
+    }
+}